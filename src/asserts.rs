@@ -0,0 +1,182 @@
+//! Generators for compile-time assertions about the types a macro processes.
+//!
+//! Each function returns a `const _` item as source text, which fails to
+//! compile if the assertion does not hold.
+
+use crate::attr::parse_attributes;
+use crate::{AttrValue, Fields, StructDecl};
+
+/// The layout requested by a type's `#[repr(...)]` attributes.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Repr {
+    pub c: bool,
+    pub transparent: bool,
+    /// `Some(1)` for `packed`, `Some(n)` for `packed(n)`.
+    pub packed: Option<u64>,
+    pub align: Option<u64>,
+    /// A primitive representation such as `u8`, for enums.
+    pub int: Option<String>,
+}
+
+impl Repr {
+    /// Collect every `#[repr(...)]` in `prologue`; unknown hints are ignored.
+    pub fn from_prologue(prologue: &str) -> Self {
+        let mut repr = Repr::default();
+        for attr in parse_attributes(prologue).into_iter().filter(|a| a.is("repr")) {
+            let args = match attr.parse_args() {
+                Ok(args) => args,
+                Err(_) => continue,
+            };
+            for (key, value) in args.iter() {
+                let arg = |v: &AttrValue| match v {
+                    AttrValue::List(inner) => inner.positional().next().and_then(|n| n.parse().ok()),
+                    _ => None,
+                };
+                match key {
+                    "C" => repr.c = true,
+                    "transparent" => repr.transparent = true,
+                    "packed" => repr.packed = Some(arg(value).unwrap_or(1)),
+                    "align" => repr.align = arg(value),
+                    "u8" | "u16" | "u32" | "u64" | "u128" | "usize" | "i8" | "i16" | "i32" | "i64" | "i128"
+                    | "isize" => repr.int = Some(key.to_string()),
+                    _ => {}
+                }
+            }
+        }
+        repr
+    }
+
+    pub fn is_default(&self) -> bool {
+        *self == Repr::default()
+    }
+}
+
+impl StructDecl {
+    pub fn repr(&self) -> Repr {
+        Repr::from_prologue(&self.prologue)
+    }
+}
+
+/// `const _: () = assert!(cond);`
+pub fn const_assert(cond: &str) -> String {
+    format!("const _: () = assert!({});", cond)
+}
+
+/// `const _: () = assert!(cond, "msg");`
+pub fn const_assert_msg(cond: &str, msg: &str) -> String {
+    format!("const _: () = assert!({}, {:?});", cond, msg)
+}
+
+/// Assert that `ty` is exactly `size` bytes; `size` is an expression.
+pub fn assert_size(ty: &str, size: &str) -> String {
+    const_assert_msg(
+        &format!("::core::mem::size_of::<{}>() == {}", ty, size),
+        &format!("size of `{}` must be {}", ty, size),
+    )
+}
+
+/// Assert that `ty` has alignment `align`; `align` is an expression.
+pub fn assert_align(ty: &str, align: &str) -> String {
+    const_assert_msg(
+        &format!("::core::mem::align_of::<{}>() == {}", ty, align),
+        &format!("alignment of `{}` must be {}", ty, align),
+    )
+}
+
+/// Assert that `ty` implements `bounds`, e.g. `Send + Sync`.
+pub fn assert_impl(ty: &str, bounds: &str) -> String {
+    format!(
+        "const _: fn() = || {{\n    fn assert_impl<T: ?Sized + {}>() {{}}\n    assert_impl::<{}>();\n}};",
+        bounds, ty
+    )
+}
+
+/// Assertions that the struct's layout matches its `#[repr(...)]`:
+///
+/// * `transparent`: same size and alignment as its first non-`PhantomData` field;
+/// * `packed`: alignment of at most the packing, and for plain `packed` a size
+///   equal to the sum of its field sizes;
+/// * `align(n)`: alignment of at least `n`.
+///
+/// Empty for generic structs, whose layout cannot be checked in a `const` item.
+pub fn layout_asserts(decl: &StructDecl) -> Vec<String> {
+    if !decl.generics.is_empty() {
+        return Vec::new();
+    }
+    let repr = decl.repr();
+    let name = decl.name.as_str();
+    let field_types: Vec<&str> = match &decl.fields {
+        Fields::Unit => Vec::new(),
+        Fields::Tuple(f) | Fields::Named(f) => f.iter().map(|f| f.ty.as_str()).collect(),
+    };
+    let mut asserts = Vec::new();
+    if repr.transparent {
+        if let Some(inner) = field_types.iter().find(|t| !t.contains("PhantomData")) {
+            asserts.push(assert_size(name, &format!("::core::mem::size_of::<{}>()", inner)));
+            asserts.push(assert_align(name, &format!("::core::mem::align_of::<{}>()", inner)));
+        }
+    }
+    if let Some(pack) = repr.packed {
+        asserts.push(const_assert_msg(
+            &format!("::core::mem::align_of::<{}>() <= {}", name, pack),
+            &format!("alignment of `{}` must be at most {}", name, pack),
+        ));
+        if pack == 1 {
+            let sum = field_types
+                .iter()
+                .map(|t| format!("::core::mem::size_of::<{}>()", t))
+                .collect::<Vec<String>>();
+            asserts.push(assert_size(name, &if sum.is_empty() { "0".to_string() } else { sum.join(" + ") }));
+        }
+    }
+    if let Some(align) = repr.align {
+        asserts.push(const_assert_msg(
+            &format!("::core::mem::align_of::<{}>() >= {}", name, align),
+            &format!("alignment of `{}` must be at least {}", name, align),
+        ));
+    }
+    asserts
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::asserts::{assert_impl, assert_size, const_assert, layout_asserts, Repr};
+    use crate::StructDecl;
+
+    #[test]
+    fn test_assert_items() {
+        assert_eq!(const_assert("N > 0"), "const _: () = assert!(N > 0);");
+        assert_eq!(
+            assert_size("Header", "16"),
+            "const _: () = assert!(::core::mem::size_of::<Header>() == 16, \"size of `Header` must be 16\");"
+        );
+        assert_eq!(
+            assert_impl("Handle", "Send + Sync"),
+            "const _: fn() = || {\n    fn assert_impl<T: ?Sized + Send + Sync>() {}\n    assert_impl::<Handle>();\n};"
+        );
+    }
+
+    #[test]
+    fn test_layout_asserts() {
+        let decl = StructDecl::from_string("#[derive(Clone)]\n#[repr(C, packed)]\nstruct Wire { tag: u8, len: u32 }".to_string());
+        assert_eq!(
+            decl.repr(),
+            Repr {
+                c: true,
+                packed: Some(1),
+                ..Repr::default()
+            }
+        );
+        let asserts = layout_asserts(&decl);
+        assert_eq!(asserts.len(), 2);
+        assert!(asserts[1].contains("size_of::<Wire>() == ::core::mem::size_of::<u8>() + ::core::mem::size_of::<u32>()"));
+
+        let decl = StructDecl::from_string("#[repr(transparent)] struct Id(u64, PhantomData<u8>);".to_string());
+        assert!(layout_asserts(&decl)[0].contains("size_of::<Id>() == ::core::mem::size_of::<u64>()"));
+        let decl = StructDecl::from_string("#[repr(align(64))] struct Line([u8; 64]);".to_string());
+        assert_eq!(decl.repr().align, Some(64));
+        assert_eq!(layout_asserts(&decl).len(), 1);
+        assert!(layout_asserts(&StructDecl::from_string("#[repr(C)] struct G<T>(T);".to_string())).is_empty());
+    }
+}
@@ -0,0 +1,265 @@
+use crate::scan::{attr_ranges, find_close};
+use crate::{FunctionDecl, Param, ParseError, ParseErrorKind};
+
+/// A single outer attribute, e.g. `#[route(GET, "/x")]`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Attribute {
+    /// The attribute path, e.g. `route` or `serde::rename`.
+    pub path: String,
+    /// The delimiter opening the arguments: `(`, `[` or `{`, or `=` for
+    /// `#[path = value]`; `None` for a bare `#[path]`.
+    pub delimiter: Option<char>,
+    /// The arguments without their delimiters, e.g. `GET, "/x"`.
+    pub args: String,
+}
+
+impl Attribute {
+    /// An attribute `#[path(args)]`, or `#[path]` if `args` is empty.
+    pub fn new(path: &str, args: &str) -> Self {
+        Attribute {
+            path: path.to_string(),
+            delimiter: if args.is_empty() { None } else { Some('(') },
+            args: args.to_string(),
+        }
+    }
+
+    /// Parse an attribute, panicking if the input is not one.
+    pub fn from_string(in_str: String) -> Self {
+        Self::try_from_string(&in_str).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_from_string(in_str: &str) -> Result<Self, ParseError> {
+        let trimmed = in_str.trim_start();
+        let start = in_str.len() - trimmed.len();
+        let open = trimmed
+            .strip_prefix('#')
+            .map(|r| in_str.len() - r.trim_start().len())
+            .filter(|&idx| in_str[idx..].starts_with('['))
+            .ok_or_else(|| ParseError::new(ParseErrorKind::Expected('['), start))?;
+        let close = find_close(in_str, open).ok_or_else(|| ParseError::new(ParseErrorKind::Unbalanced('['), open))?;
+        let inner = in_str[open + 1..close].trim();
+
+        let path_end = inner.find(['(', '[', '{', '=']).unwrap_or(inner.len());
+        let path = inner[..path_end].split_whitespace().collect::<String>();
+        let rest = inner[path_end..].trim();
+        let (delimiter, args) = match rest.chars().next() {
+            None => (None, ""),
+            Some('=') => (Some('='), rest[1..].trim()),
+            Some(c) => (Some(c), rest[1..rest.len() - 1].trim()),
+        };
+        Ok(Attribute {
+            path,
+            delimiter,
+            args: args.to_string(),
+        })
+    }
+
+    /// Whether this attribute's path is `path`, ignoring whitespace.
+    pub fn is(&self, path: &str) -> bool {
+        self.path == path.split_whitespace().collect::<String>()
+    }
+
+    /// Whether this is a conditional-compilation attribute, `cfg` or `cfg_attr`.
+    pub fn is_cfg(&self) -> bool {
+        self.is("cfg") || self.is("cfg_attr")
+    }
+
+    pub fn render(&self) -> String {
+        match self.delimiter {
+            None => format!("#[{}]", self.path),
+            Some('=') => format!("#[{} = {}]", self.path, self.args),
+            Some('[') => format!("#[{}[{}]]", self.path, self.args),
+            Some('{') => format!("#[{}{{{}}}]", self.path, self.args),
+            Some(_) => format!("#[{}({})]", self.path, self.args),
+        }
+    }
+}
+
+impl std::str::FromStr for Attribute {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, ParseError> {
+        Self::try_from_string(s)
+    }
+}
+
+/// The attributes in a prologue of attributes and comments, in order.
+pub(crate) fn parse_attributes(prologue: &str) -> Vec<Attribute> {
+    attr_ranges(prologue)
+        .into_iter()
+        .filter_map(|(start, end)| Attribute::try_from_string(&prologue[start..end]).ok())
+        .collect()
+}
+
+/// Remove every attribute with the given path from `prologue`, along with the
+/// whitespace up to the end of its line, returning the removed ones.
+pub(crate) fn remove_attributes(prologue: &mut String, path: &str) -> Vec<Attribute> {
+    let mut removed = Vec::new();
+    for (start, end) in attr_ranges(prologue).into_iter().rev() {
+        match Attribute::try_from_string(&prologue[start..end]) {
+            Ok(attr) if attr.is(path) => {
+                let rest = &prologue[end..];
+                let trailing = rest.len() - rest.trim_start_matches([' ', '\t']).len();
+                let end = end + trailing + if rest[trailing..].starts_with('\n') { 1 } else { 0 };
+                prologue.replace_range(start..end, "");
+                removed.push(attr);
+            }
+            _ => {}
+        }
+    }
+    // Removing the first line leaves the next line's indentation in front.
+    let lead = prologue.len() - prologue.trim_start_matches([' ', '\t']).len();
+    prologue.replace_range(..lead, "");
+    removed.reverse();
+    removed
+}
+
+impl FunctionDecl {
+    /// The attributes in `func_prologue`, in order; doc comments written as
+    /// `///` are not included.
+    pub fn attributes(&self) -> Vec<Attribute> {
+        parse_attributes(&self.func_prologue)
+    }
+
+    /// Remove every attribute with the given path, returning the removed ones.
+    pub fn remove_attributes(&mut self, path: &str) -> Vec<Attribute> {
+        remove_attributes(&mut self.func_prologue, path)
+    }
+
+    /// Replace the first attribute with the given path by `attr`, returning
+    /// `false` if there is none.
+    pub fn replace_attribute(&mut self, path: &str, attr: &Attribute) -> bool {
+        for (start, end) in attr_ranges(&self.func_prologue) {
+            if let Ok(existing) = Attribute::try_from_string(&self.func_prologue[start..end]) {
+                if existing.is(path) {
+                    self.func_prologue.replace_range(start..end, &attr.render());
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// The `cfg` and `cfg_attr` attributes, in order.
+    pub fn cfg_attributes(&self) -> Vec<Attribute> {
+        self.attributes().into_iter().filter(|a| a.is_cfg()).collect()
+    }
+
+    /// Prefix the rendered companion `item` with this function's `cfg` and
+    /// `cfg_attr` attributes, so it is compiled under the same conditions.
+    /// Attributes `item` already starts with are not repeated.
+    pub fn apply_cfgs_to(&self, item: &str) -> String {
+        let existing = parse_attributes(item);
+        let mut out = String::new();
+        for attr in self.cfg_attributes().iter().filter(|a| !existing.contains(a)) {
+            out.push_str(&attr.render());
+            out.push('\n');
+        }
+        out.push_str(item);
+        out
+    }
+
+    /// Add this function's `cfg` and `cfg_attr` attributes to `other`.
+    pub fn copy_cfgs_to(&self, other: &mut FunctionDecl) {
+        let existing = other.attributes();
+        for attr in self.cfg_attributes().iter().filter(|a| !existing.contains(a)) {
+            other.insert_attribute(attr);
+        }
+    }
+
+    /// Add `attr` after the existing attributes and doc comments.
+    pub fn insert_attribute(&mut self, attr: &Attribute) {
+        if !self.func_prologue.is_empty() && !self.func_prologue.ends_with(char::is_whitespace) {
+            self.func_prologue.push('\n');
+        }
+        self.func_prologue.push_str(&attr.render());
+        self.func_prologue.push('\n');
+    }
+}
+
+impl Param {
+    pub fn attributes(&self) -> Vec<Attribute> {
+        parse_attributes(&self.prologue)
+    }
+
+    pub fn has_attribute(&self, path: &str) -> bool {
+        self.attributes().iter().any(|a| a.is(path))
+    }
+
+    /// Remove every attribute with the given path, returning the removed ones.
+    pub fn remove_attributes(&mut self, path: &str) -> Vec<Attribute> {
+        let removed = remove_attributes(&mut self.prologue, path);
+        self.prologue = self.prologue.trim().to_string();
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::attr::Attribute;
+    use crate::FunctionDecl;
+
+    #[test]
+    fn test_attribute_parse() {
+        let attr: Attribute = "#[route(GET, \"/x\")]".parse().unwrap();
+        assert_eq!(attr, Attribute::new("route", "GET, \"/x\""));
+        let attr = Attribute::from_string("# [serde :: rename = \"a]b\"]".to_string());
+        assert_eq!(attr.path, "serde::rename");
+        assert_eq!(attr.delimiter, Some('='));
+        assert_eq!(attr.args, "\"a]b\"");
+        assert_eq!(attr.render(), "#[serde::rename = \"a]b\"]");
+        assert_eq!(Attribute::from_string("#[test]".to_string()).render(), "#[test]");
+        assert!("route(GET)".parse::<Attribute>().is_err());
+    }
+
+    #[test]
+    fn test_fn_attributes_edit() {
+        let mut decl = FunctionDecl::from_string(
+            "/// Handler\n#[route(GET, \"/x\")] #[inline]\n#[route(POST, \"/y\")]\npub fn handler() {}".to_string(),
+        );
+        assert_eq!(
+            decl.attributes().iter().map(|a| a.path.as_str()).collect::<Vec<&str>>(),
+            vec!["route", "inline", "route"]
+        );
+
+        let removed = decl.remove_attributes("route");
+        assert_eq!(removed.len(), 2);
+        assert_eq!(removed[1].args, "POST, \"/y\"");
+        assert_eq!(decl.func_prologue, "/// Handler\n#[inline]\n");
+
+        assert!(decl.replace_attribute("inline", &Attribute::new("inline", "always")));
+        assert!(!decl.replace_attribute("missing", &Attribute::new("x", "")));
+        decl.insert_attribute(&Attribute::new("must_use", ""));
+        assert_eq!(decl.func_prelude(), "/// Handler\n#[inline(always)]\n#[must_use]\npub fn handler() {");
+    }
+
+    #[test]
+    fn test_param_attributes() {
+        let decl = FunctionDecl::from_string("fn f(#[redact] #[subject] user: &User, n: u8) {}".to_string());
+        let mut params = decl.params();
+        assert!(params[0].has_attribute("redact"));
+        assert!(!params[1].has_attribute("redact"));
+        assert_eq!(params[0].name, "user");
+        assert_eq!(params[0].remove_attributes("redact").len(), 1);
+        assert_eq!(params[0].to_string(), "#[subject] user: &User");
+    }
+
+    #[test]
+    fn test_cfg_propagation() {
+        let decl = FunctionDecl::from_string(
+            "/// Doc\n#[cfg(feature = \"x\")]\n#[inline]\n#[cfg_attr(test, allow(dead_code))]\nfn f() {}".to_string(),
+        );
+        assert_eq!(decl.cfg_attributes().len(), 2);
+        assert_eq!(
+            decl.apply_cfgs_to("#[cfg(feature = \"x\")]\nstruct FArgs;"),
+            "#[cfg_attr(test, allow(dead_code))]\n#[cfg(feature = \"x\")]\nstruct FArgs;"
+        );
+        let mut companion = FunctionDecl::from_string("/// Companion\nfn f_twin() {}".to_string());
+        decl.copy_cfgs_to(&mut companion);
+        assert_eq!(
+            companion.func_prologue,
+            "/// Companion\n#[cfg(feature = \"x\")]\n#[cfg_attr(test, allow(dead_code))]\n"
+        );
+    }
+}
@@ -0,0 +1,296 @@
+use proc_macro2::TokenStream;
+
+use crate::scan::{find_close, find_top_level, is_ident_char, split_top_level};
+use crate::{Attribute, ParseError, ParseErrorKind};
+
+/// The value of one entry in [`AttrArgs`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AttrValue {
+    /// A bare identifier or path, e.g. `flag`.
+    Flag,
+    /// A literal as written, e.g. `"x"`, `3` or `true`.
+    Lit(String),
+    /// A path value, e.g. the `my::handler` of `with = my::handler`.
+    Path(String),
+    /// A nested list, e.g. the `(a, b = 1)` of `nested(a, b = 1)`.
+    List(AttrArgs),
+}
+
+/// Parsed arguments of an attribute, e.g. `name = "x", retries = 3, flag`.
+///
+/// Entries keep their order. A bare literal (`#[authorize("admin")]`) is a
+/// positional entry with an empty key.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AttrArgs {
+    entries: Vec<(String, AttrValue)>,
+}
+
+impl AttrArgs {
+    /// Parse an argument list without its delimiters, panicking if it is invalid.
+    pub fn from_string(in_str: String) -> Self {
+        Self::try_from_string(&in_str).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_from_string(in_str: &str) -> Result<Self, ParseError> {
+        let mut entries = Vec::new();
+        let mut offset = 0;
+        for arg in split_top_level(in_str, ',', true) {
+            let start = offset + arg.len() - arg.trim_start().len();
+            offset += arg.len() + 1;
+            let arg = arg.trim();
+            if arg.is_empty() {
+                continue;
+            }
+            entries.push(parse_entry(arg, start)?);
+        }
+        Ok(AttrArgs { entries })
+    }
+
+    /// Parse the `attr` token stream an attribute macro receives.
+    pub fn from_token_stream(ts: TokenStream) -> Result<Self, ParseError> {
+        Self::try_from_string(&ts.to_string())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &AttrValue)> {
+        self.entries.iter().map(|(k, v)| (k.as_str(), v))
+    }
+
+    /// The first entry with key `key`.
+    pub fn get(&self, key: &str) -> Option<&AttrValue> {
+        let key = key.split_whitespace().collect::<String>();
+        self.entries.iter().find(|(k, _)| *k == key).map(|(_, v)| v)
+    }
+
+    pub fn has_flag(&self, key: &str) -> bool {
+        self.get(key) == Some(&AttrValue::Flag)
+    }
+
+    /// The unescaped contents of a string literal value.
+    pub fn get_str(&self, key: &str) -> Option<String> {
+        match self.get(key)? {
+            AttrValue::Lit(lit) => unquote(lit),
+            _ => None,
+        }
+    }
+
+    pub fn get_int(&self, key: &str) -> Option<i64> {
+        match self.get(key)? {
+            AttrValue::Lit(lit) => parse_int(lit),
+            _ => None,
+        }
+    }
+
+    /// A `true`/`false` literal; a bare flag counts as `true`.
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        match self.get(key)? {
+            AttrValue::Flag => Some(true),
+            AttrValue::Lit(lit) if lit == "true" => Some(true),
+            AttrValue::Lit(lit) if lit == "false" => Some(false),
+            _ => None,
+        }
+    }
+
+    pub fn get_path(&self, key: &str) -> Option<&str> {
+        match self.get(key)? {
+            AttrValue::Path(path) => Some(path),
+            _ => None,
+        }
+    }
+
+    pub fn get_list(&self, key: &str) -> Option<&AttrArgs> {
+        match self.get(key)? {
+            AttrValue::List(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    /// Positional (keyless) literals, as written.
+    pub fn positional(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().filter_map(|(k, v)| match v {
+            AttrValue::Lit(lit) if k.is_empty() => Some(lit.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Keys not in `known`, for rejecting misspelled arguments.
+    pub fn unknown_keys(&self, known: &[&str]) -> Vec<&str> {
+        self.entries
+            .iter()
+            .map(|(k, _)| k.as_str())
+            .filter(|k| !k.is_empty() && !known.contains(k))
+            .collect()
+    }
+}
+
+impl std::str::FromStr for AttrArgs {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, ParseError> {
+        Self::try_from_string(s)
+    }
+}
+
+impl Attribute {
+    /// Parse this attribute's arguments.
+    pub fn parse_args(&self) -> Result<AttrArgs, ParseError> {
+        AttrArgs::try_from_string(&self.args)
+    }
+}
+
+fn parse_entry(arg: &str, offset: usize) -> Result<(String, AttrValue), ParseError> {
+    if !arg.starts_with(|c: char| is_ident_char(c) || c == ':') || is_literal(arg) {
+        return Ok((String::new(), AttrValue::Lit(arg.to_string())));
+    }
+    if let Some(eq) = find_top_level(arg, '=', true) {
+        let key = arg[..eq].split_whitespace().collect::<String>();
+        let value = arg[eq + 1..].trim();
+        if value.is_empty() {
+            return Err(ParseError::new(
+                ParseErrorKind::Invalid(format!("missing value for `{}`", key)),
+                offset + eq,
+            ));
+        }
+        let value = if is_literal(value) {
+            AttrValue::Lit(value.to_string())
+        } else {
+            AttrValue::Path(value.split_whitespace().collect())
+        };
+        return Ok((key, value));
+    }
+    if let Some(open) = arg.find('(') {
+        let close = find_close(arg, open).ok_or_else(|| ParseError::new(ParseErrorKind::Unbalanced('('), offset + open))?;
+        let nested = AttrArgs::try_from_string(&arg[open + 1..close]).map_err(|e| ParseError {
+            offset: e.offset + offset + open + 1,
+            ..e
+        })?;
+        return Ok((arg[..open].split_whitespace().collect(), AttrValue::List(nested)));
+    }
+    if arg.chars().all(|c| is_ident_char(c) || c == ':' || c.is_whitespace()) {
+        return Ok((arg.split_whitespace().collect(), AttrValue::Flag));
+    }
+    Err(ParseError::new(
+        ParseErrorKind::Invalid(format!("unsupported argument `{}`", arg)),
+        offset,
+    ))
+}
+
+fn is_literal(s: &str) -> bool {
+    s == "true"
+        || s == "false"
+        || s.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '"' || c == '\'')
+        || {
+            let s = s.strip_prefix('b').unwrap_or(s);
+            let s = s.strip_prefix('r').map(|r| r.trim_start_matches('#')).unwrap_or(s);
+            s.starts_with('"')
+        }
+}
+
+fn parse_int(lit: &str) -> Option<i64> {
+    let digits: String = lit.chars().filter(|&c| c != '_').collect();
+    let (neg, digits) = match digits.strip_prefix('-') {
+        Some(rest) => (true, rest.trim_start()),
+        None => (false, digits.as_str()),
+    };
+    let end = ["i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64", "u128", "usize"]
+        .iter()
+        .find_map(|suffix| digits.strip_suffix(suffix))
+        .unwrap_or(digits);
+    let value = if let Some(hex) = end.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if let Some(bin) = end.strip_prefix("0b") {
+        i64::from_str_radix(bin, 2).ok()?
+    } else if let Some(oct) = end.strip_prefix("0o") {
+        i64::from_str_radix(oct, 8).ok()?
+    } else {
+        end.parse().ok()?
+    };
+    Some(if neg { -value } else { value })
+}
+
+/// The contents of a string literal with escapes resolved.
+pub(crate) fn unquote(lit: &str) -> Option<String> {
+    if let Some(raw) = lit.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        let inner = raw[hashes..].strip_prefix('"')?;
+        return inner.strip_suffix(&format!("\"{}", "#".repeat(hashes))).map(|s| s.to_string());
+    }
+    let inner = lit.strip_prefix('"')?.strip_suffix('"')?;
+    let mut out = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next()? {
+            'n' => out.push('\n'),
+            't' => out.push('\t'),
+            'r' => out.push('\r'),
+            '0' => out.push('\0'),
+            'x' => {
+                let hex: String = chars.by_ref().take(2).collect();
+                out.push(char::from(u8::from_str_radix(&hex, 16).ok()?));
+            }
+            'u' => {
+                let rest = chars.as_str().strip_prefix('{')?;
+                let close = rest.find('}')?;
+                let code = u32::from_str_radix(&rest[..close].replace('_', ""), 16).ok()?;
+                out.push(char::from_u32(code)?);
+                chars = rest[close + 1..].chars();
+            }
+            // A line continuation skips the newline and leading whitespace.
+            '\n' => chars = chars.as_str().trim_start().chars(),
+            '\r' if chars.as_str().starts_with('\n') => chars = chars.as_str().trim_start().chars(),
+            other => out.push(other),
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use proc_macro2::TokenStream;
+
+    use crate::attr_args::{AttrArgs, AttrValue};
+
+    #[test]
+    fn test_attr_args() {
+        let args: AttrArgs = "name = \"x \\\"y\\\"\", retries = 3, flag, with = my :: handler, nested(a, b = 0x10), \"pos\""
+            .parse()
+            .unwrap();
+        assert_eq!(args.get_str("name").as_deref(), Some("x \"y\""));
+        assert_eq!(args.get_int("retries"), Some(3));
+        assert!(args.has_flag("flag"));
+        assert_eq!(args.get_bool("flag"), Some(true));
+        assert_eq!(args.get_bool("retries"), None);
+        assert_eq!(args.get_path("with"), Some("my::handler"));
+        let nested = args.get_list("nested").unwrap();
+        assert!(nested.has_flag("a"));
+        assert_eq!(nested.get_int("b"), Some(16));
+        assert_eq!(args.positional().collect::<Vec<&str>>(), vec!["\"pos\""]);
+        assert_eq!(args.unknown_keys(&["name", "retries", "flag", "with"]), vec!["nested"]);
+        assert_eq!(args.get("missing"), None);
+    }
+
+    #[test]
+    fn test_attr_args_tokens_and_errors() {
+        let ts: TokenStream = "skip = false, limit = -5i32, path = r#\"a\"b\"#".parse().unwrap();
+        let args = AttrArgs::from_token_stream(ts).unwrap();
+        assert_eq!(args.get_bool("skip"), Some(false));
+        assert_eq!(args.get_int("limit"), Some(-5));
+        assert_eq!(args.get_str("path").as_deref(), Some("a\"b"));
+        let escaped: AttrArgs = "s = \"\\x41\\u{42}\\u{1F600}\\\n    c\"".parse().unwrap();
+        assert_eq!(escaped.get_str("s").as_deref(), Some("AB\u{1F600}c"));
+        assert!(matches!(args.get("skip"), Some(AttrValue::Lit(_))));
+
+        let err = "a = 1, b =".parse::<AttrArgs>().unwrap_err();
+        assert_eq!(err.offset, 9);
+        assert!("a(b".parse::<AttrArgs>().is_err());
+    }
+}
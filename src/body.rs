@@ -0,0 +1,476 @@
+use crate::mod_decl::ItemKind;
+use crate::scan::{find_close, find_top_level, is_ident_char, split_leading_attrs, CodeChars};
+use crate::FunctionDecl;
+
+/// One top-level statement of a body, or its tail expression.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Stmt {
+    /// Whitespace and comments before the statement, kept for re-emitting.
+    pub leading: String,
+    /// The statement itself, including any trailing `;`.
+    pub text: String,
+}
+
+impl Stmt {
+    /// A statement placed on its own line.
+    pub fn new(text: &str) -> Self {
+        Stmt {
+            leading: "\n".to_string(),
+            text: text.to_string(),
+        }
+    }
+
+    pub fn is_let(&self) -> bool {
+        let (_, rest) = split_leading_attrs(&self.text);
+        rest.starts_with("let") && !rest[3..].starts_with(is_ident_char)
+    }
+}
+
+/// A function body split into top-level statements; see
+/// [`FunctionDecl::body`].
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Body {
+    pub stmts: Vec<Stmt>,
+    /// Whitespace and comments after the last statement.
+    pub trailing: String,
+}
+
+impl Body {
+    /// Split `src` (the inside of a block) into statements. A statement ends
+    /// at a top-level `;`, or for block-like statements (`if`, `match`,
+    /// loops, blocks, items) at the closing `}` unless it continues with
+    /// `else`, `.`, `?` or `;`.
+    pub fn parse(src: &str) -> Self {
+        let mut stmts = Vec::new();
+        let mut prev_end = 0;
+        let mut start = None;
+        let mut block_like = false;
+        let mut depth = 0i32;
+        for (idx, c) in CodeChars::new(src) {
+            let stmt_start = match start {
+                Some(s) => s,
+                None if c.is_whitespace() => continue,
+                None => {
+                    block_like = ends_at_brace(&src[idx..]);
+                    start = Some(idx);
+                    idx
+                }
+            };
+            let end = match c {
+                '(' | '[' | '{' => {
+                    depth += 1;
+                    None
+                }
+                ')' | ']' => {
+                    depth -= 1;
+                    None
+                }
+                '}' => {
+                    depth -= 1;
+                    let next = src[idx + 1..].trim_start();
+                    let continues = (next.starts_with("else") && !next[4..].starts_with(is_ident_char))
+                        || next.starts_with(['.', '?', ';']);
+                    if depth == 0 && block_like && !continues {
+                        Some(idx + 1)
+                    } else {
+                        None
+                    }
+                }
+                ';' if depth == 0 => Some(idx + 1),
+                _ => None,
+            };
+            if let Some(end) = end {
+                stmts.push(Stmt {
+                    leading: src[prev_end..stmt_start].to_string(),
+                    text: src[stmt_start..end].to_string(),
+                });
+                prev_end = end;
+                start = None;
+            }
+        }
+        if let Some(stmt_start) = start {
+            let text = src[stmt_start..].trim_end();
+            stmts.push(Stmt {
+                leading: src[prev_end..stmt_start].to_string(),
+                text: text.to_string(),
+            });
+            prev_end = stmt_start + text.len();
+        }
+        Body {
+            stmts,
+            trailing: src[prev_end..].to_string(),
+        }
+    }
+
+    /// The number of statements, counting those in nested blocks (branches,
+    /// loop bodies, closures) as well as the top-level ones; a rough measure
+    /// of how much code the body expands to.
+    pub fn stmt_count(&self) -> usize {
+        self.stmts.iter().map(|s| 1 + nested_stmt_count(&s.text)).sum()
+    }
+
+    /// The final expression giving the body its value, if there is one.
+    pub fn tail(&self) -> Option<&Stmt> {
+        self.stmts
+            .last()
+            .filter(|s| !s.text.ends_with(';') && !ends_at_item(&s.text))
+    }
+
+    pub fn render(&self) -> String {
+        let mut out: String = self
+            .stmts
+            .iter()
+            .map(|s| format!("{}{}", s.leading, s.text))
+            .collect();
+        out.push_str(&self.trailing);
+        out.trim_start().to_string()
+    }
+
+    /// The statements that differ between `self` and `other`, as a minimal
+    /// list of replaced runs. Statements are compared ignoring whitespace, and
+    /// the comments before them are not compared at all.
+    pub fn diff(&self, other: &Body) -> Vec<Hunk> {
+        let (old, new) = (&self.stmts, &other.stmts);
+        // lcs[i][j]: length of the longest common subsequence of old[i..] and new[j..].
+        let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+        for i in (0..old.len()).rev() {
+            for j in (0..new.len()).rev() {
+                lcs[i][j] = if same_stmt(&old[i], &new[j]) {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+
+        let mut hunks: Vec<Hunk> = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < old.len() || j < new.len() {
+            if i < old.len() && j < new.len() && same_stmt(&old[i], &new[j]) {
+                i += 1;
+                j += 1;
+                continue;
+            }
+            let hunk = match hunks.last_mut() {
+                Some(h) if h.old_start + h.removed.len() == i && h.new_start + h.added.len() == j => h,
+                _ => {
+                    hunks.push(Hunk {
+                        old_start: i,
+                        new_start: j,
+                        removed: Vec::new(),
+                        added: Vec::new(),
+                    });
+                    hunks.last_mut().unwrap()
+                }
+            };
+            if j < new.len() && (i == old.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+                hunk.added.push(new[j].clone());
+                j += 1;
+            } else {
+                hunk.removed.push(old[i].clone());
+                i += 1;
+            }
+        }
+        hunks
+    }
+}
+
+/// A run of statements replaced between two bodies; see [`Body::diff`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Hunk {
+    /// Index of the first removed statement in the old body, or where the
+    /// additions go if nothing was removed.
+    pub old_start: usize,
+    /// Index of the first added statement in the new body.
+    pub new_start: usize,
+    pub removed: Vec<Stmt>,
+    pub added: Vec<Stmt>,
+}
+
+impl Hunk {
+    /// Line comments describing the change, one `// - ` line per removed
+    /// line and one `// + ` line per added line.
+    pub fn annotation(&self) -> String {
+        let lines = |stmts: &[Stmt], mark: &str| {
+            stmts
+                .iter()
+                .flat_map(|s| s.text.lines().map(|l| format!("// {} {}", mark, l.trim())).collect::<Vec<String>>())
+                .collect::<Vec<String>>()
+        };
+        let mut out = lines(&self.removed, "-");
+        out.extend(lines(&self.added, "+"));
+        out.join("\n")
+    }
+}
+
+fn same_stmt(a: &Stmt, b: &Stmt) -> bool {
+    let squeeze = |s: &Stmt| s.text.chars().filter(|c| !c.is_whitespace()).collect::<String>();
+    squeeze(a) == squeeze(b)
+}
+
+/// Statements inside the outermost blocks of `text`.
+fn nested_stmt_count(text: &str) -> usize {
+    let mut count = 0;
+    let mut skip_until = 0;
+    for (idx, c) in CodeChars::new(text) {
+        if idx >= skip_until && c == '{' {
+            if let Some(close) = find_close(text, idx) {
+                count += Body::parse(&text[idx + 1..close]).stmt_count();
+                skip_until = close + 1;
+            }
+        }
+    }
+    count
+}
+
+fn ends_at_item(text: &str) -> bool {
+    !matches!(ItemKind::sniff(text), ItemKind::Other | ItemKind::Macro)
+}
+
+/// Whether a statement starting at `src` may end at its first top-level `}`.
+fn ends_at_brace(src: &str) -> bool {
+    let (_, rest) = split_leading_attrs(src);
+    // Skip a loop label, e.g. `'outer: loop`.
+    let rest = match rest.strip_prefix('\'') {
+        Some(label) => label.trim_start_matches(is_ident_char).trim_start_matches(':').trim_start(),
+        None => rest,
+    };
+    let word = &rest[..rest.find(|c: char| !is_ident_char(c)).unwrap_or(rest.len())];
+    rest.starts_with('{')
+        || matches!(word, "if" | "match" | "loop" | "while" | "for" | "unsafe" | "async")
+        || ends_at_item(rest)
+        || ItemKind::sniff(rest) == ItemKind::Macro
+}
+
+/// Byte ranges of the operand of each `return` in `src` belonging to the
+/// enclosing function, skipping closures, `async` blocks and nested `fn`
+/// items. The range is empty for a bare `return`.
+pub(crate) fn return_operands(src: &str) -> Vec<(usize, usize)> {
+    let chars: Vec<(usize, char)> = CodeChars::new(src).collect();
+    let mut ranges = Vec::new();
+    let mut skip_until = 0;
+    let mut prev = None;
+    for (pos, &(idx, c)) in chars.iter().enumerate() {
+        if idx < skip_until || c.is_whitespace() {
+            continue;
+        }
+        let at_word = is_ident_char(c) && !src[..idx].ends_with(is_ident_char);
+        if let Some(end) = nested_scope_end(src, idx, c, prev, at_word) {
+            skip_until = end;
+        } else if at_word && src[idx..].starts_with("return") && !src[idx + 6..].starts_with(is_ident_char) {
+            let start = idx + 6;
+            let end = operand_end(&chars[pos..], start).unwrap_or(src.len());
+            let operand = src[start..end].trim();
+            let lead = src[start..end].len() - src[start..end].trim_start().len();
+            ranges.push((start + lead, start + lead + operand.len()));
+        }
+        prev = Some(c);
+    }
+    ranges
+}
+
+/// If a closure, `async` block or `fn` item starts at `idx`, the end of its body.
+fn nested_scope_end(src: &str, idx: usize, c: char, prev: Option<char>, at_word: bool) -> Option<usize> {
+    let rest = &src[idx..];
+    let before = src[..idx].trim_end();
+    let after_keyword = ["move", "async"]
+        .iter()
+        .any(|kw| before.ends_with(kw) && !before[..before.len() - kw.len()].ends_with(is_ident_char));
+    let starts_closure = after_keyword || matches!(prev, None | Some('(' | ',' | '=' | '{' | ';' | '>' | '['));
+    let body_start = if c == '|' && starts_closure {
+        let params_end = if rest.starts_with("||") { 2 } else { 1 + rest[1..].find('|')? + 1 };
+        let after = rest[params_end..].trim_start();
+        if !(after.starts_with('{') || after.starts_with("->")) {
+            return None;
+        }
+        idx + params_end + rest[params_end..].find('{')?
+    } else if at_word && (rest.starts_with("async") || rest.starts_with("fn")) {
+        let word_len = if rest.starts_with("fn") { 2 } else { 5 };
+        if rest[word_len..].starts_with(is_ident_char) {
+            return None;
+        }
+        let after = rest[word_len..].trim_start();
+        if word_len == 5 && !(after.starts_with('{') || after.starts_with("move")) {
+            return None;
+        }
+        idx + find_top_level(rest, '{', true)?
+    } else {
+        return None;
+    };
+    find_close(src, body_start).map(|close| close + 1)
+}
+
+/// End of a `return` operand: a `;` or `,` at its own depth, or the close of
+/// the enclosing bracket.
+fn operand_end(chars: &[(usize, char)], start: usize) -> Option<usize> {
+    let mut depth = 0i32;
+    for &(idx, c) in chars.iter().filter(|(idx, _)| *idx >= start) {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' if depth == 0 => return Some(idx),
+            ')' | ']' | '}' => depth -= 1,
+            ';' | ',' if depth == 0 => return Some(idx),
+            _ => {}
+        }
+    }
+    None
+}
+
+impl FunctionDecl {
+    /// The body split into statements.
+    pub fn body(&self) -> Body {
+        Body::parse(&self.fn_body)
+    }
+
+    pub fn statements(&self) -> Vec<Stmt> {
+        self.body().stmts
+    }
+
+    pub fn set_body(&mut self, body: &Body) {
+        self.fn_body = body.render();
+    }
+
+    /// Rewrite every exit point of the body with `f`: the operand of each
+    /// explicit `return` (`()` for a bare one) and the tail expression. A
+    /// unit function without a tail gets `f("()")` appended as one.
+    ///
+    /// Returns inside closures, `async` blocks and nested items are left
+    /// alone, as are exits through `?`.
+    pub fn wrap_returns<F: Fn(&str) -> String>(&mut self, f: F) -> &mut Self {
+        let mut body = self.fn_body.clone();
+        for (start, end) in return_operands(&self.fn_body).into_iter().rev() {
+            let operand = &self.fn_body[start..end];
+            let wrapped = if operand.is_empty() {
+                format!(" {}", f("()"))
+            } else {
+                f(operand)
+            };
+            body.replace_range(start..end, &wrapped);
+        }
+
+        let mut parsed = Body::parse(&body);
+        let ends_in_return = |s: &Stmt| s.text.starts_with("return") && !s.text[6..].starts_with(is_ident_char);
+        match parsed.stmts.last_mut() {
+            Some(last) if ends_in_return(last) => {}
+            Some(last) if !last.text.ends_with(';') && !ends_at_item(&last.text) => last.text = f(&last.text),
+            _ if self.return_type().is_unit() => parsed.stmts.push(Stmt::new(&f("()"))),
+            _ => {}
+        }
+        self.set_body(&parsed);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::body::{Body, Hunk, Stmt};
+    use crate::FunctionDecl;
+
+    const BODY: &str = "let x = if a { 1 } else { 2 };
+    // check it
+    if x > 1 {
+        go(\"}\");
+    } else if x == 0 {
+        stop();
+    }
+    let f = |v: u8| { v + 1 };
+    match x { _ => {} }.clone();
+    'outer: loop { break 'outer; }
+    fn helper() {}
+    f(x)";
+
+    #[test]
+    fn test_statements() {
+        let body = Body::parse(BODY);
+        let texts: Vec<&str> = body.stmts.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(texts.len(), 7);
+        assert_eq!(texts[0], "let x = if a { 1 } else { 2 };");
+        assert!(texts[1].starts_with("if x > 1 {") && texts[1].ends_with("stop();\n    }"));
+        assert_eq!(body.stmts[1].leading, "\n    // check it\n    ");
+        assert_eq!(texts[2], "let f = |v: u8| { v + 1 };");
+        assert_eq!(texts[3], "match x { _ => {} }.clone();");
+        assert_eq!(texts[4], "'outer: loop { break 'outer; }");
+        assert_eq!(texts[5], "fn helper() {}");
+        assert_eq!(body.tail().unwrap().text, "f(x)");
+        assert!(body.stmts[0].is_let());
+        assert_eq!(body.stmt_count(), 14);
+        assert_eq!(Body::parse("a(); b").stmt_count(), 2);
+        assert_eq!(Body::parse("").stmt_count(), 0);
+        assert_eq!(body.render(), BODY);
+    }
+
+    #[test]
+    fn test_wrap_returns() {
+        let mut decl = FunctionDecl::from_string(
+            "fn f(x: u8) -> u8 {
+    if x == 0 { return 1; }
+    let g = |y: u8| { return y; };
+    let c = move |x: u8| { return x; };
+    let s = \"return 5;\";
+    match x { 1 => return g(x), _ => {} }
+    if x > 9 { return (x, 2).0 } else { x + 1 }
+}"
+            .to_string(),
+        );
+        decl.wrap_returns(|e| format!("__record({})", e));
+        let expected = "if x == 0 { return __record(1); }
+    let g = |y: u8| { return y; };
+    let c = move |x: u8| { return x; };
+    let s = \"return 5;\";
+    match x { 1 => return __record(g(x)), _ => {} }
+    __record(if x > 9 { return __record((x, 2).0) } else { x + 1 })";
+        assert_eq!(decl.fn_body, expected);
+
+        let mut decl = FunctionDecl::from_string("fn g() { if done() { return; } work(); }".to_string());
+        decl.wrap_returns(|e| format!("log({})", e));
+        assert_eq!(decl.fn_body, "if done() { return log(()); } work();\nlog(())");
+
+        let mut decl = FunctionDecl::from_string(
+            "async fn h() -> u8 { let c = async move |x: u8| { return x; }; return c(1).await; }".to_string(),
+        );
+        decl.wrap_returns(|e| format!("log({})", e));
+        assert_eq!(decl.fn_body, "let c = async move |x: u8| { return x; }; return log(c(1).await);");
+    }
+
+    #[test]
+    fn test_statement_rewrite() {
+        let mut decl = FunctionDecl::from_string("fn f() { a(); b(); }".to_string());
+        let mut body = decl.body();
+        assert!(body.tail().is_none());
+        body.stmts.insert(1, Stmt::new("trace();"));
+        body.stmts.retain(|s| s.text != "a();");
+        decl.set_body(&body);
+        assert_eq!(decl.fn_body, "trace(); b();");
+        assert_eq!(decl.statements().len(), 2);
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = Body::parse("let a = load();\n    check(a);\n    save(a);\n    a");
+        let new = Body::parse("let a = load();\n    trace(&a);\n    check( a );\n    save_v2(a);\n    a");
+        let hunks = old.diff(&new);
+        assert_eq!(
+            hunks,
+            vec![
+                Hunk {
+                    old_start: 1,
+                    new_start: 1,
+                    removed: vec![],
+                    added: vec![new.stmts[1].clone()],
+                },
+                Hunk {
+                    old_start: 2,
+                    new_start: 3,
+                    removed: vec![old.stmts[2].clone()],
+                    added: vec![new.stmts[3].clone()],
+                },
+            ]
+        );
+        assert_eq!(hunks[1].annotation(), "// - save(a);\n// + save_v2(a);");
+        assert!(old.diff(&old).is_empty());
+        assert_eq!(Body::default().diff(&old)[0].added.len(), 4);
+    }
+}
@@ -0,0 +1,170 @@
+use regex::CaptureLocations;
+
+use crate::{scan, FunctionDecl, ParseError, ParseErrorKind, SourceOffsets, Visibility, QUALIFIER_PATTERN};
+
+/// A function parsed without allocating: every field borrows from the input.
+///
+/// Fields mirror [`FunctionDecl`]. Use this when a macro only needs to look
+/// at a function, and [`FunctionDeclRef::into_owned`] once it has to change
+/// one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FunctionDeclRef<'a> {
+    pub func_prologue: &'a str,
+    /// The visibility as written, e.g. `pub(crate)`; see
+    /// [`FunctionDeclRef::visibility`].
+    pub vis: &'a str,
+    pub const_str: &'a str,
+    pub async_str: &'a str,
+    pub unsafe_str: &'a str,
+    pub abi: &'a str,
+    pub fn_name: &'a str,
+    pub generics: &'a str,
+    pub fn_decl: &'a str,
+    pub ret_decl: &'a str,
+    pub where_clause: &'a str,
+    pub fn_body: &'a str,
+    /// The whole input, for re-emitting the function unchanged.
+    pub source: &'a str,
+}
+
+impl<'a> FunctionDeclRef<'a> {
+    pub fn parse(in_str: &'a str) -> Result<Self, ParseError> {
+        match Self::parse_with_offsets(in_str, &mut QUALIFIER_PATTERN.capture_locations())? {
+            (decl, (_, _, Some(_))) => Ok(decl),
+            (_, (_, _, None)) => Err(ParseError::new(
+                ParseErrorKind::Expected('{'),
+                in_str.rfind(';').unwrap_or(in_str.len()),
+            )),
+        }
+    }
+
+    /// Parse a function that may end in `;`, also returning the byte offsets
+    /// of the signature start (after attributes), the body's opening brace (or
+    /// `;`) and its closing brace, `None` if there is no body.
+    pub(crate) fn parse_with_offsets(
+        in_str: &'a str,
+        locs: &mut CaptureLocations,
+    ) -> Result<(Self, SourceOffsets), ParseError> {
+        let fn_idx = scan::find_keyword(in_str, "fn")
+            .ok_or_else(|| ParseError::new(ParseErrorKind::MissingKeyword("fn"), 0))?;
+
+        let head = &in_str[..fn_idx];
+        QUALIFIER_PATTERN.captures_read(locs, head).ok_or_else(|| {
+            ParseError::new(ParseErrorKind::Invalid("unsupported fn qualifiers".to_string()), 0)
+        })?;
+        // Absent parts are empty slices of the input at the `fn` keyword, so
+        // that every field has a position in it (see `FunctionDeclRef::offsets`).
+        let group = |idx: usize| locs.get(idx).map(|(start, end)| &head[start..end]).unwrap_or(&head[fn_idx..]);
+        let func_prologue = group(1).trim_matches(' ');
+        let qualifier = |idx: usize| group(idx).trim();
+        let sig_start = (2..=6).find_map(|idx| locs.get(idx)).map(|(start, _)| start).unwrap_or(fn_idx);
+
+        let after_fn = fn_idx + 2;
+        let params_idx = after_fn
+            + scan::find_top_level(&in_str[after_fn..], '(', true)
+                .ok_or_else(|| ParseError::new(ParseErrorKind::Expected('('), after_fn))?;
+        let params_end = scan::find_close(in_str, params_idx)
+            .ok_or_else(|| ParseError::new(ParseErrorKind::Unbalanced('('), params_idx))?;
+        let after_params = &in_str[params_end + 1..];
+        let body_idx = params_end
+            + 1
+            + match (
+                scan::find_top_level(after_params, '{', true),
+                scan::find_top_level(after_params, ';', true),
+            ) {
+                (Some(brace), Some(semi)) => brace.min(semi),
+                (Some(idx), None) | (None, Some(idx)) => idx,
+                (None, None) => return Err(ParseError::new(ParseErrorKind::Expected('{'), params_end + 1)),
+            };
+        let body_end = if in_str[body_idx..].starts_with(';') {
+            None
+        } else {
+            Some(
+                scan::find_close(in_str, body_idx)
+                    .ok_or_else(|| ParseError::new(ParseErrorKind::Unbalanced('{'), body_idx))?,
+            )
+        };
+
+        let name_and_generics = in_str[after_fn..params_idx].trim();
+        let (fn_name, generics) = match name_and_generics.find('<') {
+            Some(idx) => (name_and_generics[..idx].trim(), &name_and_generics[idx..]),
+            None => (name_and_generics, &name_and_generics[name_and_generics.len()..]),
+        };
+        let ret_and_where = &in_str[params_end + 1..body_idx];
+        let (ret_decl, where_clause) = match scan::find_keyword(ret_and_where, "where") {
+            Some(idx) => (&ret_and_where[..idx], ret_and_where[idx..].trim()),
+            None => (ret_and_where, &ret_and_where[ret_and_where.len()..]),
+        };
+
+        let decl = FunctionDeclRef {
+            func_prologue,
+            vis: qualifier(2),
+            const_str: qualifier(3),
+            async_str: qualifier(4),
+            unsafe_str: qualifier(5),
+            abi: qualifier(6),
+            fn_name,
+            generics,
+            fn_decl: &in_str[params_idx..=params_end],
+            ret_decl: ret_decl.trim().trim_start_matches("->").trim(),
+            where_clause,
+            fn_body: body_end.map_or(&in_str[body_idx..body_idx], |end| in_str[body_idx + 1..end].trim()),
+            source: in_str,
+        };
+        Ok((decl, (sig_start, body_idx, body_end)))
+    }
+
+    pub fn visibility(&self) -> Visibility {
+        Visibility::from_string(self.vis)
+    }
+
+    pub fn into_owned(self) -> FunctionDecl {
+        FunctionDecl {
+            func_prologue: self.func_prologue.to_string(),
+            vis: self.visibility(),
+            const_str: self.const_str.to_string(),
+            async_str: self.async_str.to_string(),
+            unsafe_str: self.unsafe_str.to_string(),
+            abi: self.abi.to_string(),
+            fn_name: self.fn_name.to_string(),
+            generics: self.generics.to_string(),
+            fn_decl: self.fn_decl.to_string(),
+            ret_decl: self.ret_decl.to_string(),
+            where_clause: self.where_clause.to_string(),
+            fn_body: self.fn_body.to_string(),
+            span: None,
+            source: None,
+        }
+    }
+}
+
+impl From<FunctionDeclRef<'_>> for FunctionDecl {
+    fn from(decl: FunctionDeclRef<'_>) -> Self {
+        decl.into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{FunctionDecl, FunctionDeclRef, Visibility};
+
+    #[test]
+    fn test_borrowed_parse() {
+        let input = "/// Doc\npub(crate) const unsafe fn get<T>(p: *const T) -> T where T: Copy { *p }";
+        let decl = FunctionDeclRef::parse(input).unwrap();
+        assert_eq!(decl.func_prologue, "/// Doc\n");
+        assert_eq!(decl.vis, "pub(crate)");
+        assert_eq!(decl.visibility(), Visibility::PubCrate);
+        assert_eq!((decl.const_str, decl.unsafe_str, decl.async_str), ("const", "unsafe", ""));
+        assert_eq!((decl.fn_name, decl.generics), ("get", "<T>"));
+        assert_eq!(decl.fn_decl, "(p: *const T)");
+        assert_eq!((decl.ret_decl, decl.where_clause), ("T", "where T: Copy"));
+        assert_eq!(decl.fn_body, "*p");
+        assert_eq!(decl.source, input);
+
+        let owned: FunctionDecl = decl.into();
+        let expected = FunctionDecl::from_string(input.to_string());
+        assert_eq!(format!("{:?}", owned), format!("{:?}", expected));
+        assert!(FunctionDeclRef::parse("fn f();").is_err());
+    }
+}
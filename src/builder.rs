@@ -0,0 +1,247 @@
+use crate::{render_params, Attribute, FunctionDecl, ImplBlockDecl, ImplItem, Param, Visibility};
+
+/// Fluent construction of a [`FunctionDecl`] that did not come from parsing,
+/// e.g. a generated companion function.
+#[derive(Clone, Debug, Default)]
+pub struct FunctionDeclBuilder {
+    decl: FunctionDecl,
+    params: Vec<Param>,
+}
+
+impl FunctionDeclBuilder {
+    pub fn new(name: &str) -> Self {
+        FunctionDeclBuilder {
+            decl: FunctionDecl {
+                fn_name: name.to_string(),
+                ..FunctionDecl::default()
+            },
+            params: Vec::new(),
+        }
+    }
+
+    pub fn name(mut self, name: &str) -> Self {
+        self.decl.fn_name = name.to_string();
+        self
+    }
+
+    pub fn vis(mut self, vis: Visibility) -> Self {
+        self.decl.vis = vis;
+        self
+    }
+
+    pub fn is_async(mut self, is_async: bool) -> Self {
+        self.decl.async_str = if is_async { "async".to_string() } else { String::new() };
+        self
+    }
+
+    /// Append a parameter, e.g. `param("&self", "")` or `param("id", "u64")`.
+    pub fn param(mut self, name: &str, ty: &str) -> Self {
+        self.params.push(Param::new(name, ty));
+        self
+    }
+
+    /// Replace all parameters.
+    pub fn params(mut self, params: &[Param]) -> Self {
+        self.params = params.to_vec();
+        self
+    }
+
+    /// The return type, without `->`; empty for `()`.
+    pub fn ret(mut self, ty: &str) -> Self {
+        self.decl.ret_decl = ty.to_string();
+        self
+    }
+
+    /// Generic parameters including the angle brackets, e.g. `<T: Clone>`.
+    pub fn generics(mut self, generics: &str) -> Self {
+        self.decl.generics = generics.to_string();
+        self
+    }
+
+    /// The full `where` clause, e.g. `where T: Send`.
+    pub fn where_clause(mut self, where_clause: &str) -> Self {
+        self.decl.where_clause = where_clause.to_string();
+        self
+    }
+
+    pub fn attribute(mut self, attr: &Attribute) -> Self {
+        self.decl.insert_attribute(attr);
+        self
+    }
+
+    /// Add a `///` doc comment line.
+    pub fn doc(mut self, line: &str) -> Self {
+        self.decl.func_prologue.push_str(&format!("/// {}\n", line));
+        self
+    }
+
+    pub fn body(mut self, body: &str) -> Self {
+        self.decl.fn_body = body.to_string();
+        self
+    }
+
+    pub fn build(self) -> FunctionDecl {
+        FunctionDecl {
+            fn_decl: render_params(&self.params),
+            ..self.decl
+        }
+    }
+}
+
+impl FunctionDecl {
+    pub fn builder(name: &str) -> FunctionDeclBuilder {
+        FunctionDeclBuilder::new(name)
+    }
+}
+
+/// Fluent construction of an [`ImplBlockDecl`]. Associated types come
+/// first, then associated consts, then methods and other items, each group
+/// in the order added.
+#[derive(Clone, Debug)]
+pub struct ImplBuilder {
+    decl: ImplBlockDecl,
+    types: Vec<ImplItem>,
+    consts: Vec<ImplItem>,
+}
+
+impl ImplBuilder {
+    /// An inherent impl for `self_ty`, e.g. `Point<T>`.
+    pub fn new(self_ty: &str) -> Self {
+        ImplBuilder {
+            decl: ImplBlockDecl {
+                prologue: String::new(),
+                generics: String::new(),
+                trait_name: String::new(),
+                self_ty: self_ty.to_string(),
+                where_clause: String::new(),
+                items: Vec::new(),
+            },
+            types: Vec::new(),
+            consts: Vec::new(),
+        }
+    }
+
+    /// Implement `trait_name` instead of an inherent impl.
+    pub fn for_trait(mut self, trait_name: &str) -> Self {
+        self.decl.trait_name = trait_name.to_string();
+        self
+    }
+
+    /// Generic parameters including the angle brackets, e.g. `<T: Clone>`.
+    pub fn generics(mut self, generics: &str) -> Self {
+        self.decl.generics = generics.to_string();
+        self
+    }
+
+    /// The full `where` clause, e.g. `where T: Send`.
+    pub fn where_clause(mut self, where_clause: &str) -> Self {
+        self.decl.where_clause = where_clause.to_string();
+        self
+    }
+
+    pub fn attribute(mut self, attr: &Attribute) -> Self {
+        if !self.decl.prologue.is_empty() {
+            self.decl.prologue.push(' ');
+        }
+        self.decl.prologue.push_str(&attr.render());
+        self
+    }
+
+    /// Add `const NAME: ty = value;`.
+    pub fn assoc_const(mut self, name: &str, ty: &str, value: &str) -> Self {
+        self.consts.push(ImplItem::Other(format!("const {}: {} = {};", name, ty, value)));
+        self
+    }
+
+    /// Add `type Name = ty;`.
+    pub fn assoc_type(mut self, name: &str, ty: &str) -> Self {
+        self.types.push(ImplItem::Other(format!("type {} = {};", name, ty)));
+        self
+    }
+
+    pub fn method(mut self, method: FunctionDecl) -> Self {
+        self.decl.items.push(ImplItem::Fn(method));
+        self
+    }
+
+    /// Add any other item as written, e.g. a macro invocation.
+    pub fn item(mut self, item: &str) -> Self {
+        self.decl.items.push(ImplItem::Other(item.trim().to_string()));
+        self
+    }
+
+    pub fn build(self) -> ImplBlockDecl {
+        let mut items = self.types;
+        items.extend(self.consts);
+        items.extend(self.decl.items);
+        ImplBlockDecl { items, ..self.decl }
+    }
+}
+
+impl ImplBlockDecl {
+    pub fn builder(self_ty: &str) -> ImplBuilder {
+        ImplBuilder::new(self_ty)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Attribute, Decl, FunctionDecl, ImplBlockDecl, Visibility};
+
+    #[test]
+    fn test_builder() {
+        let decl = FunctionDecl::builder("__orig_fetch")
+            .vis(Visibility::PubCrate)
+            .is_async(true)
+            .doc("Original body of `fetch`.")
+            .attribute(&Attribute::new("inline", ""))
+            .generics("<T: Id>")
+            .param("&self", "")
+            .param("id", "T")
+            .ret("Result<Item, Error>")
+            .where_clause("where T: Send")
+            .body("self.db.get(id).await")
+            .build();
+        let expected = "/// Original body of `fetch`.
+#[inline]
+pub(crate) async fn __orig_fetch<T: Id>(&self, id: T) -> Result<Item, Error> where T: Send {
+self.db.get(id).await
+}";
+        assert_eq!(decl.render(), expected);
+        assert_eq!(FunctionDecl::from_string(decl.render()).params().len(), 2);
+        assert_eq!(FunctionDecl::builder("f").build().render(), "fn f() {\n\n}");
+    }
+
+    #[test]
+    fn test_impl_builder() {
+        let block = ImplBlockDecl::builder("Wrapper<T>")
+            .for_trait("Mul")
+            .generics("<T: Copy>")
+            .where_clause("where T: Mul<Output = T>")
+            .attribute(&Attribute::new("automatically_derived", ""))
+            .method(
+                FunctionDecl::builder("mul")
+                    .param("self", "")
+                    .param("rhs", "Self")
+                    .ret("Self::Output")
+                    .body("Wrapper(self.0 * rhs.0)")
+                    .build(),
+            )
+            .assoc_const("SCALE", "usize", "3")
+            .assoc_type("Output", "Wrapper<T>")
+            .build();
+        let expected = "#[automatically_derived] impl<T: Copy> Mul for Wrapper<T> where T: Mul<Output = T> {
+    type Output = Wrapper<T>;
+
+    const SCALE: usize = 3;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+    Wrapper(self.0 * rhs.0)
+    }
+}";
+        assert_eq!(block.render(), expected);
+        let reparsed = ImplBlockDecl::from_string(block.render());
+        assert_eq!(reparsed.items.len(), 3);
+        assert_eq!(reparsed.method("mul").unwrap().ret_decl, "Self::Output");
+    }
+}
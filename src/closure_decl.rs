@@ -0,0 +1,234 @@
+use crate::scan::{find_close, find_top_level, is_ident_char};
+use crate::{indent, params, sealed, Body, Decl, FunctionDecl, Param, ParseError, ParseErrorKind};
+
+/// How a closure or async block captures its environment.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CaptureMode {
+    /// Borrow captured variables, the default.
+    #[default]
+    Ref,
+    /// `move`: take ownership of captured variables.
+    Move,
+}
+
+/// A parsed closure (`|x: u32| -> u64 { x + 1 }`) or async block
+/// (`async move { ... }`).
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClosureDecl {
+    /// `async` for an async closure or block, otherwise empty.
+    pub async_str: String,
+    pub capture: CaptureMode,
+    /// The parameter list including its pipes, e.g. `|a, b: u8|`; empty for
+    /// an async block.
+    pub params_decl: String,
+    /// The return type, empty if not written.
+    pub ret_decl: String,
+    /// The body without its braces, trimmed.
+    pub body: String,
+    /// `false` for a closure whose body is a bare expression, e.g. `|x| x + 1`.
+    pub braced: bool,
+}
+
+impl ClosureDecl {
+    /// Parse a closure or async block, panicking if the input is not one.
+    pub fn from_string(in_str: String) -> Self {
+        Self::try_from_string(&in_str).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_from_string(in_str: &str) -> Result<Self, ParseError> {
+        let src = in_str.trim_end();
+        let mut pos = src.len() - src.trim_start().len();
+        let mut decl = ClosureDecl::default();
+        if let Some(len) = word_at(src, pos, "async") {
+            decl.async_str = "async".to_string();
+            pos = len;
+        }
+        if let Some(len) = word_at(src, pos, "move") {
+            decl.capture = CaptureMode::Move;
+            pos = len;
+        }
+
+        let rest = &src[pos..];
+        if rest.starts_with('{') && !decl.async_str.is_empty() {
+            let close = find_close(src, pos).ok_or_else(|| ParseError::new(ParseErrorKind::Unbalanced('{'), pos))?;
+            decl.body = src[pos + 1..close].trim().to_string();
+            decl.braced = true;
+            return Ok(decl);
+        }
+        if !rest.starts_with('|') {
+            return Err(ParseError::new(ParseErrorKind::Expected('|'), pos));
+        }
+        let params_end = if rest.starts_with("||") {
+            pos + 1
+        } else {
+            pos + 1
+                + find_top_level(&rest[1..], '|', false)
+                    .ok_or_else(|| ParseError::new(ParseErrorKind::Unbalanced('|'), pos))?
+        };
+        decl.params_decl = src[pos..=params_end].to_string();
+
+        let after = &src[params_end + 1..];
+        let after_start = params_end + 1 + after.len() - after.trim_start().len();
+        if let Some(ret) = src[after_start..].strip_prefix("->") {
+            let open = after_start
+                + 2
+                + find_top_level(ret, '{', true)
+                    .ok_or_else(|| ParseError::new(ParseErrorKind::Expected('{'), after_start))?;
+            decl.ret_decl = src[after_start + 2..open].trim().to_string();
+            let close = find_close(src, open).ok_or_else(|| ParseError::new(ParseErrorKind::Unbalanced('{'), open))?;
+            decl.body = src[open + 1..close].trim().to_string();
+            decl.braced = true;
+        } else if src[after_start..].starts_with('{') && find_close(src, after_start) == Some(src.len() - 1) {
+            decl.body = src[after_start + 1..src.len() - 1].trim().to_string();
+            decl.braced = true;
+        } else {
+            decl.body = src[after_start..].to_string();
+        }
+        Ok(decl)
+    }
+
+    pub fn is_async_block(&self) -> bool {
+        self.params_decl.is_empty()
+    }
+
+    pub fn params(&self) -> Vec<Param> {
+        let inner = self.params_decl.trim_start_matches('|').trim_end_matches('|');
+        params::parse_params(&format!("({})", inner))
+    }
+
+    pub fn set_params(&mut self, params: &[Param]) {
+        let list = params::render_params(params);
+        self.params_decl = format!("|{}|", &list[1..list.len() - 1]);
+    }
+
+    /// Apply `FunctionDecl` body helpers (`prepend_to_body`, `wrap_returns`,
+    /// ...) to this closure's body, which becomes braced.
+    pub fn map_body<F: FnOnce(&mut FunctionDecl)>(&mut self, f: F) -> &mut Self {
+        let mut func = FunctionDecl {
+            ret_decl: self.ret_decl.clone(),
+            fn_body: self.body.clone(),
+            ..FunctionDecl::default()
+        };
+        f(&mut func);
+        self.body = func.fn_body;
+        self.braced = true;
+        self
+    }
+
+    pub fn prepend_to_body(&mut self, code: &str) -> &mut Self {
+        self.map_body(|f| {
+            f.prepend_to_body(code);
+        })
+    }
+
+    pub fn append_to_body(&mut self, code: &str) -> &mut Self {
+        self.map_body(|f| {
+            f.append_to_body(code);
+        })
+    }
+
+    pub fn wrap_body(&mut self, prefix: &str, suffix: &str) -> &mut Self {
+        self.map_body(|f| {
+            f.wrap_body(prefix, suffix);
+        })
+    }
+
+    /// The body split into statements.
+    pub fn statements(&self) -> Body {
+        Body::parse(&self.body)
+    }
+}
+
+/// The end of `word` if it starts at `pos` as a whole word, skipping whitespace after it.
+fn word_at(src: &str, pos: usize, word: &str) -> Option<usize> {
+    let rest = &src[pos..];
+    if rest.starts_with(word) && !rest[word.len()..].starts_with(is_ident_char) {
+        let after = &rest[word.len()..];
+        Some(pos + word.len() + after.len() - after.trim_start().len())
+    } else {
+        None
+    }
+}
+
+impl std::str::FromStr for ClosureDecl {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, ParseError> {
+        Self::try_from_string(s)
+    }
+}
+
+impl sealed::Sealed for ClosureDecl {}
+
+impl Decl for ClosureDecl {
+    fn render(&self) -> String {
+        let mut out = String::new();
+        if !self.async_str.is_empty() {
+            out.push_str("async ");
+        }
+        if self.capture == CaptureMode::Move {
+            out.push_str("move ");
+        }
+        if !self.params_decl.is_empty() {
+            out.push_str(&self.params_decl);
+            out.push(' ');
+        }
+        if !self.ret_decl.is_empty() {
+            out.push_str(&format!("-> {} ", self.ret_decl));
+        }
+        if self.braced || !self.ret_decl.is_empty() {
+            out.push_str(&format!("{{\n{}\n}}", indent(&self.body)));
+        } else {
+            out.push_str(&self.body);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::closure_decl::{CaptureMode, ClosureDecl};
+    use crate::{Decl, Param};
+
+    #[test]
+    fn test_closure_parse() {
+        let decl: ClosureDecl = "move |x: u32, (a, b): (u8, u8)| -> u64 { let y = x + 1; y as u64 }".parse().unwrap();
+        assert_eq!(decl.capture, CaptureMode::Move);
+        assert_eq!(decl.params().len(), 2);
+        assert_eq!(decl.params()[1].ty, "(u8, u8)");
+        assert_eq!(decl.ret_decl, "u64");
+        assert_eq!(decl.body, "let y = x + 1; y as u64");
+        assert_eq!(decl.statements().stmts.len(), 2);
+
+        let decl = ClosureDecl::from_string("|a, b| a.cmp(&b)".to_string());
+        assert!(!decl.braced);
+        assert_eq!(decl.body, "a.cmp(&b)");
+        assert_eq!(decl.render(), "|a, b| a.cmp(&b)");
+        assert_eq!(ClosureDecl::from_string("|| {}".to_string()).params_decl, "||");
+        assert!("fn a() {}".parse::<ClosureDecl>().is_err());
+    }
+
+    #[test]
+    fn test_async_block() {
+        let mut decl = ClosureDecl::from_string("async move { fetch().await }".to_string());
+        assert!(decl.is_async_block());
+        assert_eq!(decl.body, "fetch().await");
+        decl.prepend_to_body("let _g = span.enter();");
+        assert_eq!(decl.render(), "async move {\n    let _g = span.enter();\n    fetch().await\n}");
+    }
+
+    #[test]
+    fn test_closure_body_helpers() {
+        let mut decl = ClosureDecl::from_string("|x: u8| x + 1".to_string());
+        decl.map_body(|f| {
+            f.wrap_returns(|e| format!("record({})", e));
+        });
+        assert_eq!(decl.render(), "|x: u8| {\n    record(x + 1)\n}");
+        let mut params = decl.params();
+        params.push(Param::new("y", "u8"));
+        decl.set_params(&params);
+        assert_eq!(decl.params_decl, "|x: u8, y: u8|");
+    }
+}
@@ -0,0 +1,77 @@
+use std::fmt;
+
+use crate::FunctionDecl;
+
+/// The first difference found between two function signatures that should
+/// be interchangeable.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SignatureMismatch {
+    /// Which part differs: `name`, `qualifiers`, `generics`, `params`,
+    /// `return type` or `where clause`.
+    pub part: &'static str,
+    pub left: String,
+    pub right: String,
+}
+
+impl fmt::Display for SignatureMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "signatures differ in {}: `{}` vs `{}`", self.part, self.left, self.right)
+    }
+}
+
+impl std::error::Error for SignatureMismatch {}
+
+fn squash(s: &str) -> String {
+    s.split_whitespace().collect()
+}
+
+impl FunctionDecl {
+    /// Check that `other` can stand in for this function: same name,
+    /// qualifiers, generics, parameters, return type and where clause, ignoring
+    /// formatting. Attributes, visibility and bodies may differ.
+    pub fn check_compatible(&self, other: &FunctionDecl) -> Result<(), SignatureMismatch> {
+        let qualifiers = |d: &FunctionDecl| {
+            [&d.const_str, &d.async_str, &d.unsafe_str, &d.abi]
+                .iter()
+                .filter(|q| !q.is_empty())
+                .map(|q| q.as_str())
+                .collect::<Vec<&str>>()
+                .join(" ")
+        };
+        let params = |d: &FunctionDecl| d.params().iter().map(|p| p.to_string()).collect::<Vec<String>>().join(", ");
+        let parts = vec![
+            ("name", self.fn_name.clone(), other.fn_name.clone()),
+            ("qualifiers", qualifiers(self), qualifiers(other)),
+            ("generics", self.generics.clone(), other.generics.clone()),
+            ("params", params(self), params(other)),
+            ("return type", self.ret_decl.clone(), other.ret_decl.clone()),
+            ("where clause", self.where_clause.clone(), other.where_clause.clone()),
+        ];
+        for (part, left, right) in parts {
+            if squash(&left) != squash(&right) {
+                return Err(SignatureMismatch { part, left, right });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::FunctionDecl;
+
+    #[test]
+    fn test_check_compatible() {
+        let a = FunctionDecl::from_string("pub fn read(buf: &mut [u8]) -> io::Result<usize> { a() }".to_string());
+        let b = FunctionDecl::from_string("#[inline]\nfn read(buf : &mut [ u8 ]) -> io::Result< usize > { b() }".to_string());
+        assert_eq!(a.check_compatible(&b), Ok(()));
+
+        let c = FunctionDecl::from_string("async fn read(buf: &mut [u8]) -> io::Result<usize> { c() }".to_string());
+        let err = a.check_compatible(&c).unwrap_err();
+        assert_eq!(err.part, "qualifiers");
+        assert_eq!(err.to_string(), "signatures differ in qualifiers: `` vs `async`");
+
+        let d = FunctionDecl::from_string("fn read(data: &mut [u8]) -> io::Result<usize> { d() }".to_string());
+        assert_eq!(a.check_compatible(&d).unwrap_err().part, "params");
+    }
+}
@@ -0,0 +1,184 @@
+use crate::scan::{find_keyword, find_top_level, split_leading_attrs, split_visibility};
+use crate::{sealed, Decl, ParseError, ParseErrorKind, Visibility};
+
+/// A parsed `const` item, e.g. `pub const LIMIT: usize = 3;`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConstDecl {
+    pub prologue: String,
+    pub vis: Visibility,
+    /// The name, `_` for an unnamed const.
+    pub name: String,
+    pub ty: String,
+    /// The value expression, empty for a declaration without one (in a trait).
+    pub value: String,
+}
+
+impl ConstDecl {
+    /// Parse a const item, panicking if the input is not one.
+    pub fn from_string(in_str: String) -> Self {
+        Self::try_from_string(&in_str).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_from_string(in_str: &str) -> Result<Self, ParseError> {
+        let (prologue, vis, rest) = split_item(in_str, "const")?;
+        let (name, ty, value) = split_binding(rest, in_str.len() - rest.len())?;
+        Ok(ConstDecl {
+            prologue,
+            vis,
+            name,
+            ty,
+            value,
+        })
+    }
+}
+
+/// A parsed `static` item, e.g. `static mut COUNT: u32 = 0;`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StaticDecl {
+    pub prologue: String,
+    pub vis: Visibility,
+    /// `true` for a `static mut`.
+    pub mutable: bool,
+    pub name: String,
+    pub ty: String,
+    /// The value expression, empty for a declaration without one (in an
+    /// `extern` block).
+    pub value: String,
+}
+
+impl StaticDecl {
+    /// Parse a static item, panicking if the input is not one.
+    pub fn from_string(in_str: String) -> Self {
+        Self::try_from_string(&in_str).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_from_string(in_str: &str) -> Result<Self, ParseError> {
+        let (prologue, vis, rest) = split_item(in_str, "static")?;
+        let (mutable, rest) = match rest.strip_prefix("mut") {
+            Some(after) if after.starts_with(char::is_whitespace) => (true, after.trim_start()),
+            _ => (false, rest),
+        };
+        let (name, ty, value) = split_binding(rest, in_str.len() - rest.len())?;
+        Ok(StaticDecl {
+            prologue,
+            vis,
+            mutable,
+            name,
+            ty,
+            value,
+        })
+    }
+}
+
+/// Split an item into its prologue, visibility and the text after `keyword`.
+pub(crate) fn split_item<'a>(in_str: &'a str, keyword: &'static str) -> Result<(String, Visibility, &'a str), ParseError> {
+    let idx = find_keyword(in_str, keyword).ok_or_else(|| ParseError::new(ParseErrorKind::MissingKeyword(keyword), 0))?;
+    let (prologue, head) = split_leading_attrs(&in_str[..idx]);
+    let (vis, _) = split_visibility(head);
+    Ok((
+        prologue.to_string(),
+        Visibility::from_string(vis),
+        in_str[idx + keyword.len()..].trim(),
+    ))
+}
+
+/// Split `NAME: Type = value;` into its parts; `offset` is where `src`
+/// starts in the item, for errors.
+fn split_binding(src: &str, offset: usize) -> Result<(String, String, String), ParseError> {
+    let src = src.trim_end().trim_end_matches(';').trim_end();
+    let colon = find_top_level(src, ':', true).ok_or_else(|| ParseError::new(ParseErrorKind::Expected(':'), offset))?;
+    let (ty, value) = match find_top_level(&src[colon + 1..], '=', true) {
+        Some(idx) => (&src[colon + 1..colon + 1 + idx], src[colon + 2 + idx..].trim()),
+        None => (&src[colon + 1..], ""),
+    };
+    Ok((src[..colon].trim().to_string(), ty.trim().to_string(), value.to_string()))
+}
+
+fn render_binding(prologue: &str, head: String, ty: &str, value: &str) -> String {
+    format!(
+        "{}{}{}: {}{};",
+        prologue,
+        if prologue.is_empty() { "" } else { "\n" },
+        head,
+        ty,
+        if value.is_empty() { String::new() } else { format!(" = {}", value) }
+    )
+}
+
+impl std::str::FromStr for ConstDecl {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, ParseError> {
+        Self::try_from_string(s)
+    }
+}
+
+impl std::str::FromStr for StaticDecl {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, ParseError> {
+        Self::try_from_string(s)
+    }
+}
+
+impl sealed::Sealed for ConstDecl {}
+impl sealed::Sealed for StaticDecl {}
+
+impl Decl for ConstDecl {
+    fn render(&self) -> String {
+        render_binding(
+            &self.prologue,
+            format!("{}const {}", self.vis.prefix(), self.name),
+            &self.ty,
+            &self.value,
+        )
+    }
+}
+
+impl Decl for StaticDecl {
+    fn render(&self) -> String {
+        render_binding(
+            &self.prologue,
+            format!(
+                "{}static {}{}",
+                self.vis.prefix(),
+                if self.mutable { "mut " } else { "" },
+                self.name
+            ),
+            &self.ty,
+            &self.value,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ConstDecl, Decl, StaticDecl, Visibility};
+
+    #[test]
+    fn test_const_and_static() {
+        let decl: ConstDecl = "/// The limit\npub(crate) const LIMIT: HashMap<u8, u8> = { let m = x(); m };"
+            .parse()
+            .unwrap();
+        assert_eq!(decl.prologue, "/// The limit");
+        assert_eq!(decl.vis, Visibility::PubCrate);
+        assert_eq!((decl.name.as_str(), decl.ty.as_str()), ("LIMIT", "HashMap<u8, u8>"));
+        assert_eq!(decl.value, "{ let m = x(); m }");
+        assert_eq!(
+            decl.render(),
+            "/// The limit\npub(crate) const LIMIT: HashMap<u8, u8> = { let m = x(); m };"
+        );
+        assert_eq!(ConstDecl::from_string("const N: usize;".to_string()).render(), "const N: usize;");
+        assert_eq!(ConstDecl::from_string("const _: () = ();".to_string()).name, "_");
+
+        let decl = StaticDecl::from_string("#[no_mangle]\nstatic mut COUNT: u32 = 0;".to_string());
+        assert!(decl.mutable);
+        assert_eq!((decl.name.as_str(), decl.ty.as_str(), decl.value.as_str()), ("COUNT", "u32", "0"));
+        assert_eq!(decl.render(), "#[no_mangle]\nstatic mut COUNT: u32 = 0;");
+        assert!(!StaticDecl::from_string("pub static NAME: &str = \"a;b\";".to_string()).mutable);
+        assert!("static X = 1;".parse::<StaticDecl>().is_err());
+        assert!("const fn f() {}".parse::<StaticDecl>().is_err());
+    }
+}
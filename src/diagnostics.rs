@@ -0,0 +1,99 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::schema::json_str;
+use crate::ItemKind;
+
+/// Options for the lenient item parsers such as [`crate::parse_items_with`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseOptions {
+    /// Append every construct that is kept verbatim because it was not
+    /// recognised or failed to parse to `log_path`, one JSON object per line
+    /// with the item kind, the reason and the source snippet.
+    pub log_unsupported: bool,
+    /// Defaults to `pmt-unsupported.log` in `$CARGO_TARGET_DIR`, or in
+    /// `target` if that is not set.
+    pub log_path: PathBuf,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        let target = std::env::var_os("CARGO_TARGET_DIR").map(PathBuf::from).unwrap_or_else(|| "target".into());
+        ParseOptions {
+            log_unsupported: false,
+            log_path: target.join("pmt-unsupported.log"),
+        }
+    }
+}
+
+impl ParseOptions {
+    pub fn new() -> Self {
+        ParseOptions::default()
+    }
+
+    /// The defaults with logging of unsupported constructs turned on.
+    pub fn logging() -> Self {
+        ParseOptions {
+            log_unsupported: true,
+            ..ParseOptions::default()
+        }
+    }
+
+    /// Record a construct passed through verbatim. Logging is best effort: a
+    /// file that cannot be written never fails the parse.
+    pub(crate) fn report_unsupported(&self, kind: ItemKind, reason: &str, snippet: &str) {
+        if !self.log_unsupported {
+            return;
+        }
+        let line = format!(
+            "{{\"kind\":{},\"reason\":{},\"snippet\":{}}}\n",
+            json_str(&format!("{:?}", kind)),
+            json_str(reason),
+            json_str(snippet.trim())
+        );
+        if let Some(dir) = self.log_path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            let _ = fs::create_dir_all(dir);
+        }
+        if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(&self.log_path) {
+            let _ = file.write_all(line.as_bytes());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{parse_items_with, Item, ParseOptions};
+
+    #[test]
+    fn test_log_unsupported() {
+        let dir = std::env::temp_dir().join(format!("pmt-diagnostics-{}", std::process::id()));
+        let options = ParseOptions {
+            log_path: dir.join("unsupported.log"),
+            ..ParseOptions::logging()
+        };
+        let items = parse_items_with(
+            "use a::b;\nfn broken {}\nmod m { safe fn f(); }\nfn ok() {}",
+            &options,
+        );
+        assert_eq!(items.len(), 4);
+        assert!(matches!(items[1], Item::Other(..)));
+        let log = fs::read_to_string(&options.log_path).unwrap();
+        let lines: Vec<&str> = log.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[0],
+            "{\"kind\":\"Fn\",\"reason\":\"expected `(` at offset 2\",\"snippet\":\"fn broken {}\"}"
+        );
+        assert_eq!(
+            lines[1],
+            "{\"kind\":\"Other\",\"reason\":\"unrecognised item\",\"snippet\":\"safe fn f();\"}"
+        );
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(!ParseOptions::new().log_unsupported);
+        assert!(ParseOptions::new().log_path.ends_with("pmt-unsupported.log"));
+    }
+}
@@ -0,0 +1,264 @@
+use crate::field::Fields;
+use crate::generics::split_type_header;
+use crate::scan::{find_close, find_keyword, find_top_level, split_leading_attrs, split_top_level, split_visibility};
+use crate::{sealed, Decl, ParseError, ParseErrorKind, Visibility};
+
+/// One variant of an enum, e.g. `#[default] Named { a: u8 } = 3`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Variant {
+    /// Attributes and doc comments preceding the variant.
+    pub prologue: String,
+    pub name: String,
+    pub fields: Fields,
+    /// The explicit discriminant expression, empty if there is none.
+    pub discriminant: String,
+}
+
+impl Variant {
+    pub fn from_string(in_str: &str) -> Self {
+        let (prologue, rest) = split_leading_attrs(in_str);
+        let rest = rest.trim();
+        let name_end = rest
+            .find(|c: char| !(c == '_' || c.is_alphanumeric()))
+            .unwrap_or(rest.len());
+        let name = &rest[..name_end];
+        let after = rest[name_end..].trim_start();
+        let (fields, after) = match after.chars().next() {
+            Some('(') | Some('{') => match find_close(after, 0) {
+                Some(close) => (Fields::from_string(&after[..=close]), &after[close + 1..]),
+                None => (Fields::Unit, after),
+            },
+            _ => (Fields::Unit, after),
+        };
+        let discriminant = after.trim().trim_start_matches('=').trim();
+
+        Variant {
+            prologue: prologue.to_string(),
+            name: name.to_string(),
+            fields,
+            discriminant: discriminant.to_string(),
+        }
+    }
+
+    pub fn is_unit(&self) -> bool {
+        self.fields == Fields::Unit
+    }
+
+    /// A pattern matching this variant and binding every field, for use in
+    /// generated `match` arms: `Self::A(f0, f1)`, `Self::B { a, b }`.
+    pub fn match_pattern(&self, path: &str) -> String {
+        match &self.fields {
+            Fields::Unit => format!("{}::{}", path, self.name),
+            Fields::Tuple(f) => format!(
+                "{}::{}({})",
+                path,
+                self.name,
+                (0..f.len()).map(|i| format!("f{}", i)).collect::<Vec<String>>().join(", ")
+            ),
+            Fields::Named(f) => format!(
+                "{}::{} {{ {} }}",
+                path,
+                self.name,
+                f.iter().map(|f| f.name.clone()).collect::<Vec<String>>().join(", ")
+            ),
+        }
+    }
+
+    fn render(&self) -> String {
+        let prologue = self
+            .prologue
+            .lines()
+            .map(|l| format!("    {}\n", l.trim()))
+            .collect::<String>();
+        let discriminant = if self.discriminant.is_empty() {
+            String::new()
+        } else {
+            format!(" = {}", self.discriminant)
+        };
+        format!(
+            "{}    {}{}{},\n",
+            prologue,
+            self.name,
+            self.fields.render("    "),
+            discriminant
+        )
+    }
+}
+
+/// A parsed `enum` definition.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EnumDecl {
+    pub prologue: String,
+    pub vis: Visibility,
+    pub name: String,
+    /// Generic parameters including the angle brackets, e.g. `<T: Clone>`.
+    pub generics: String,
+    /// The `where` clause, empty if there is none.
+    pub where_clause: String,
+    pub variants: Vec<Variant>,
+}
+
+impl EnumDecl {
+    /// Parse an enum definition, panicking if the input is not one.
+    pub fn from_string(in_str: String) -> Self {
+        Self::try_from_string(&in_str).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_from_string(in_str: &str) -> Result<Self, ParseError> {
+        let enum_idx = find_keyword(in_str, "enum")
+            .ok_or_else(|| ParseError::new(ParseErrorKind::MissingKeyword("enum"), 0))?;
+        let (prologue, head) = split_leading_attrs(&in_str[..enum_idx]);
+        let (vis, _) = split_visibility(head);
+
+        let after_enum = enum_idx + 4;
+        let body_idx = after_enum
+            + find_top_level(&in_str[after_enum..], '{', true)
+                .ok_or_else(|| ParseError::new(ParseErrorKind::Expected('{'), after_enum))?;
+        let body_end = find_close(in_str, body_idx)
+            .ok_or_else(|| ParseError::new(ParseErrorKind::Unbalanced('{'), body_idx))?;
+
+        let header = &in_str[after_enum..body_idx];
+        let (name, generics, where_clause) = split_type_header(header)
+            .ok_or_else(|| ParseError::new(ParseErrorKind::Unbalanced('<'), after_enum))?;
+
+        let variants = split_top_level(&in_str[body_idx + 1..body_end], ',', false)
+            .into_iter()
+            .filter(|v| !split_leading_attrs(v).1.trim().is_empty())
+            .map(Variant::from_string)
+            .collect();
+
+        Ok(EnumDecl {
+            prologue: prologue.to_string(),
+            vis: Visibility::from_string(vis),
+            name,
+            generics,
+            where_clause,
+            variants,
+        })
+    }
+
+    pub fn variant(&self, name: &str) -> Option<&Variant> {
+        self.variants.iter().find(|v| v.name == name)
+    }
+
+    /// Generate a `match` over `scrutinee` with one arm per variant, the arm
+    /// body produced by `arm` from the variant.
+    pub fn match_arms<F: Fn(&Variant) -> String>(&self, scrutinee: &str, arm: F) -> String {
+        format!(
+            "match {} {{\n{}}}",
+            scrutinee,
+            self.variants
+                .iter()
+                .map(|v| format!("    {} => {},\n", v.match_pattern("Self"), arm(v)))
+                .collect::<String>()
+        )
+    }
+}
+
+impl std::str::FromStr for EnumDecl {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, ParseError> {
+        Self::try_from_string(s)
+    }
+}
+
+impl sealed::Sealed for EnumDecl {}
+
+impl Decl for EnumDecl {
+    fn render(&self) -> String {
+        format!(
+            "{}{}{}enum {}{}{} {{\n{}}}",
+            self.prologue,
+            if self.prologue.is_empty() { "" } else { "\n" },
+            self.vis.prefix(),
+            self.name,
+            self.generics,
+            if self.where_clause.is_empty() {
+                String::new()
+            } else {
+                format!(" {}", self.where_clause)
+            },
+            self.variants.iter().map(|v| v.render()).collect::<String>()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::enum_decl::EnumDecl;
+    use crate::field::Fields;
+    use crate::{Decl, Visibility};
+
+    const INPUT: &str = "/// A message
+    #[derive(Debug)]
+    pub(crate) enum Message<T: Clone> where T: Default {
+        /// Nothing to do
+        Quit,
+        Move { x: i32, y: i32 },
+        Write(String, T),
+        #[allow(dead_code)]
+        Code = 3,
+    }";
+
+    #[test]
+    fn test_enum_parse() {
+        let decl: EnumDecl = INPUT.parse().unwrap();
+        assert_eq!(decl.prologue, "/// A message\n    #[derive(Debug)]");
+        assert_eq!(decl.vis, Visibility::PubCrate);
+        assert_eq!(decl.name, "Message");
+        assert_eq!(decl.generics, "<T: Clone>");
+        assert_eq!(decl.where_clause, "where T: Default");
+        assert_eq!(decl.variants.len(), 4);
+
+        let quit = &decl.variants[0];
+        assert_eq!(quit.prologue, "/// Nothing to do");
+        assert!(quit.is_unit());
+        let mv = decl.variant("Move").unwrap();
+        assert!(matches!(mv.fields, Fields::Named(_)));
+        assert_eq!(mv.fields.iter().map(|f| f.name.as_str()).collect::<Vec<&str>>(), vec!["x", "y"]);
+        let write = decl.variant("Write").unwrap();
+        assert_eq!(write.fields.iter().map(|f| f.ty.as_str()).collect::<Vec<&str>>(), vec!["String", "T"]);
+        let code = decl.variant("Code").unwrap();
+        assert_eq!(code.prologue, "#[allow(dead_code)]");
+        assert_eq!(code.discriminant, "3");
+    }
+
+    #[test]
+    fn test_enum_render() {
+        let decl = EnumDecl::from_string(INPUT.to_string());
+        let expected = "/// A message
+    #[derive(Debug)]
+pub(crate) enum Message<T: Clone> where T: Default {
+    /// Nothing to do
+    Quit,
+    Move {
+        x: i32,
+        y: i32,
+    },
+    Write(String, T),
+    #[allow(dead_code)]
+    Code = 3,
+}";
+        assert_eq!(decl.render(), expected);
+    }
+
+    #[test]
+    fn test_enum_match_arms() {
+        let decl = EnumDecl::from_string("enum E { A, B(u8), C { c: u8 } }".to_string());
+        let expected = "match self {
+    Self::A => \"A\",
+    Self::B(f0) => \"B\",
+    Self::C { c } => \"C\",
+}";
+        assert_eq!(decl.match_arms("self", |v| format!("\"{}\"", v.name)), expected);
+    }
+
+    #[test]
+    fn test_enum_parse_errors() {
+        assert!("struct A;".parse::<EnumDecl>().is_err());
+        assert!("enum A { B".parse::<EnumDecl>().is_err());
+    }
+}
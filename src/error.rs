@@ -0,0 +1,43 @@
+use std::fmt;
+
+/// What went wrong while parsing an item.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum ParseErrorKind {
+    /// The item keyword (`fn`, `enum`, ...) could not be found.
+    MissingKeyword(&'static str),
+    /// A required delimiter, such as the `(` of a parameter list, was not found.
+    Expected(char),
+    /// A bracket was opened but never closed.
+    Unbalanced(char),
+    /// Anything else, described in free text.
+    Invalid(String),
+}
+
+/// A structured parse failure, with the byte offset into the input where it
+/// was detected.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub offset: usize,
+}
+
+impl ParseError {
+    pub fn new(kind: ParseErrorKind, offset: usize) -> Self {
+        ParseError { kind, offset }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            ParseErrorKind::MissingKeyword(kw) => write!(f, "expected a `{}` declaration", kw),
+            ParseErrorKind::Expected(c) => write!(f, "expected `{}` at offset {}", c, self.offset),
+            ParseErrorKind::Unbalanced(c) => write!(f, "unclosed `{}` at offset {}", c, self.offset),
+            ParseErrorKind::Invalid(msg) => write!(f, "{} at offset {}", msg, self.offset),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
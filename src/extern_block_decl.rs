@@ -0,0 +1,165 @@
+use crate::mod_decl::inner_attrs_len;
+use crate::scan::{find_close, find_keyword, find_top_level, split_items, split_leading_attrs};
+use crate::{indent, sealed, Decl, FunctionDecl, ItemKind, ParseError, ParseErrorKind, StaticDecl};
+
+/// One item inside an `extern` block.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[allow(clippy::large_enum_variant)]
+pub enum ForeignItem {
+    /// A foreign function; its `fn_body` is always empty.
+    Fn(FunctionDecl),
+    Static(StaticDecl),
+    /// Any other item (a type or macro), or one that failed to parse, kept
+    /// verbatim.
+    Other(String),
+}
+
+impl ForeignItem {
+    pub fn render(&self) -> String {
+        match self {
+            ForeignItem::Fn(decl) => decl.render_declaration(),
+            ForeignItem::Static(decl) => decl.render(),
+            ForeignItem::Other(s) => s.clone(),
+        }
+    }
+}
+
+/// A parsed `extern` block, e.g. `extern "C" { fn abs(x: i32) -> i32; }`.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExternBlockDecl {
+    pub prologue: String,
+    /// `unsafe` for an `unsafe extern` block, otherwise empty.
+    pub unsafe_str: String,
+    /// The `extern` keyword including any ABI string, e.g. `extern "C"`.
+    pub abi: String,
+    /// Inner attributes and comments at the top of the block, e.g. `#![allow(x)]`.
+    pub inner_attrs: String,
+    pub items: Vec<ForeignItem>,
+}
+
+impl ExternBlockDecl {
+    /// Parse an `extern` block, panicking if the input is not one.
+    pub fn from_string(in_str: String) -> Self {
+        Self::try_from_string(&in_str).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_from_string(in_str: &str) -> Result<Self, ParseError> {
+        let extern_idx = find_keyword(in_str, "extern")
+            .ok_or_else(|| ParseError::new(ParseErrorKind::MissingKeyword("extern"), 0))?;
+        let (prologue, head) = split_leading_attrs(&in_str[..extern_idx]);
+        let body_idx = extern_idx
+            + find_top_level(&in_str[extern_idx..], '{', false)
+                .ok_or_else(|| ParseError::new(ParseErrorKind::Expected('{'), extern_idx))?;
+        let body_end = find_close(in_str, body_idx)
+            .ok_or_else(|| ParseError::new(ParseErrorKind::Unbalanced('{'), body_idx))?;
+
+        let body = &in_str[body_idx + 1..body_end];
+        let inner_len = inner_attrs_len(body);
+        Ok(ExternBlockDecl {
+            prologue: prologue.to_string(),
+            unsafe_str: head.trim().to_string(),
+            abi: in_str[extern_idx..body_idx].trim().to_string(),
+            inner_attrs: body[..inner_len].trim().to_string(),
+            items: split_items(&body[inner_len..]).into_iter().map(parse_foreign_item).collect(),
+        })
+    }
+
+    pub fn functions(&self) -> impl Iterator<Item = &FunctionDecl> {
+        self.items.iter().filter_map(|i| match i {
+            ForeignItem::Fn(f) => Some(f),
+            _ => None,
+        })
+    }
+
+    pub fn function(&self, name: &str) -> Option<&FunctionDecl> {
+        self.functions().find(|f| f.fn_name == name)
+    }
+}
+
+fn parse_foreign_item(src: &str) -> ForeignItem {
+    let parsed = match ItemKind::sniff(src) {
+        ItemKind::Fn => FunctionDecl::try_from_declaration(src).map(ForeignItem::Fn),
+        ItemKind::Static => src.parse().map(ForeignItem::Static),
+        _ => return ForeignItem::Other(src.to_string()),
+    };
+    parsed.unwrap_or_else(|_| ForeignItem::Other(src.to_string()))
+}
+
+impl std::str::FromStr for ExternBlockDecl {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, ParseError> {
+        Self::try_from_string(s)
+    }
+}
+
+impl sealed::Sealed for ExternBlockDecl {}
+
+impl Decl for ExternBlockDecl {
+    fn render(&self) -> String {
+        let mut body: Vec<String> = Vec::new();
+        if !self.inner_attrs.is_empty() {
+            body.push(indent(&self.inner_attrs));
+        }
+        body.extend(self.items.iter().map(|i| indent(&i.render())));
+        format!(
+            "{}{}{}{} {{\n{}\n}}",
+            self.prologue,
+            if self.prologue.is_empty() { "" } else { "\n" },
+            if self.unsafe_str.is_empty() { String::new() } else { format!("{} ", self.unsafe_str) },
+            self.abi,
+            body.join("\n")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Decl, ExternBlockDecl, ForeignItem, FunctionDecl};
+
+    #[test]
+    fn test_foreign_fn() {
+        let decl =
+            FunctionDecl::try_from_declaration("pub unsafe extern \"C\" fn printf(fmt: *const c_char, ...) -> c_int;")
+                .unwrap();
+        assert_eq!((decl.unsafe_str.as_str(), decl.abi.as_str()), ("unsafe", "extern \"C\""));
+        assert!(decl.is_variadic() && decl.fn_body.is_empty());
+        assert_eq!(decl.params().len(), 2);
+        assert_eq!(
+            decl.render_declaration(),
+            "pub unsafe extern \"C\" fn printf(fmt: *const c_char, ...) -> c_int;"
+        );
+        assert!(FunctionDecl::try_from_declaration("fn f(args: ...);").unwrap().is_variadic());
+        assert!(!FunctionDecl::try_from_declaration("fn f(x: u8);").unwrap().is_variadic());
+        assert!(FunctionDecl::try_from_declaration("fn f() {}").is_err());
+        assert!(FunctionDecl::try_from_string("extern \"C\" fn f(x: u8);").is_err());
+    }
+
+    #[test]
+    fn test_extern_block() {
+        let src = "#[link(name = \"c\")]
+unsafe extern \"C\" {
+    #![allow(dead_code)]
+    /// Absolute value
+    pub fn abs(x: i32) -> i32;
+    fn printf(fmt: *const c_char, ...) -> c_int;
+    static mut errno: c_int;
+    type FILE;
+}";
+        let block: ExternBlockDecl = src.parse().unwrap();
+        assert_eq!(block.prologue, "#[link(name = \"c\")]");
+        assert_eq!((block.unsafe_str.as_str(), block.abi.as_str()), ("unsafe", "extern \"C\""));
+        assert_eq!(block.inner_attrs, "#![allow(dead_code)]");
+        assert_eq!(block.functions().count(), 2);
+        assert_eq!(block.function("abs").unwrap().func_prologue, "/// Absolute value\n");
+        assert!(block.function("printf").unwrap().is_variadic());
+        assert!(matches!(&block.items[2], ForeignItem::Static(s) if s.mutable && s.name == "errno"));
+        assert!(matches!(&block.items[3], ForeignItem::Other(s) if s == "type FILE;"));
+        assert_eq!(block.render(), src);
+
+        let block = ExternBlockDecl::from_string("extern { fn f(); }".to_string());
+        assert_eq!(block.render(), "extern {\n    fn f();\n}");
+    }
+}
@@ -0,0 +1,183 @@
+use crate::scan::{find_top_level, split_leading_attrs, split_top_level, split_visibility};
+use crate::types::TypeKind;
+use crate::visibility::Visibility;
+
+/// A single field of a struct or enum variant, e.g. `pub name: String`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FieldDecl {
+    /// Attributes and doc comments preceding the field.
+    pub prologue: String,
+    pub vis: Visibility,
+    /// The field name, empty for tuple fields.
+    pub name: String,
+    pub ty: String,
+}
+
+impl FieldDecl {
+    pub fn new(name: &str, ty: &str) -> Self {
+        FieldDecl {
+            prologue: String::new(),
+            vis: Visibility::Private,
+            name: name.to_string(),
+            ty: ty.to_string(),
+        }
+    }
+
+    /// Classify the field's type.
+    pub fn kind(&self) -> TypeKind {
+        TypeKind::classify(&self.ty)
+    }
+
+    fn parse(in_str: &str, named: bool) -> Self {
+        let (prologue, rest) = split_leading_attrs(in_str);
+        let (vis, rest) = split_visibility(rest);
+        let (name, ty) = match find_top_level(rest, ':', true) {
+            Some(idx) if named => (rest[..idx].trim(), rest[idx + 1..].trim()),
+            _ => ("", rest.trim()),
+        };
+        FieldDecl {
+            prologue: prologue.to_string(),
+            vis: Visibility::from_string(vis),
+            name: name.to_string(),
+            ty: ty.to_string(),
+        }
+    }
+
+    fn render(&self, indent: &str) -> String {
+        let prologue = self
+            .prologue
+            .lines()
+            .map(|l| format!("{}{}\n", indent, l.trim()))
+            .collect::<String>();
+        let vis = self.vis.prefix();
+        if self.name.is_empty() {
+            format!("{}{}{}{}", prologue, indent, vis, self.ty)
+        } else {
+            format!("{}{}{}{}: {}", prologue, indent, vis, self.name, self.ty)
+        }
+    }
+}
+
+/// The fields of a struct or enum variant.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Fields {
+    Unit,
+    Tuple(Vec<FieldDecl>),
+    Named(Vec<FieldDecl>),
+}
+
+impl Fields {
+    /// Parse a `(...)` or `{...}` field list including its delimiters.
+    pub fn from_string(in_str: &str) -> Self {
+        let in_str = in_str.trim();
+        if let Some(inner) = in_str.strip_prefix('(').and_then(|s| s.strip_suffix(')')) {
+            Fields::Tuple(parse_fields(inner, false))
+        } else if let Some(inner) = in_str.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+            Fields::Named(parse_fields(inner, true))
+        } else {
+            Fields::Unit
+        }
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, FieldDecl> {
+        match self {
+            Fields::Unit => [].iter(),
+            Fields::Tuple(f) | Fields::Named(f) => f.iter(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.iter().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Render the field list; `indent` is the indentation of the owning item.
+    pub fn render(&self, indent: &str) -> String {
+        match self {
+            Fields::Unit => String::new(),
+            Fields::Tuple(fields) => format!(
+                "({})",
+                fields
+                    .iter()
+                    .map(|f| {
+                        let rendered = f.render("");
+                        if f.prologue.is_empty() {
+                            rendered
+                        } else {
+                            format!("\n{}", rendered)
+                        }
+                    })
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            Fields::Named(fields) => {
+                let inner_indent = format!("{}    ", indent);
+                format!(
+                    " {{\n{}{}}}",
+                    fields
+                        .iter()
+                        .map(|f| format!("{},\n", f.render(&inner_indent)))
+                        .collect::<String>(),
+                    indent
+                )
+            }
+        }
+    }
+}
+
+fn parse_fields(inner: &str, named: bool) -> Vec<FieldDecl> {
+    split_top_level(inner, ',', true)
+        .into_iter()
+        .filter(|f| !f.trim().is_empty())
+        .map(|f| FieldDecl::parse(f, named))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::field::{FieldDecl, Fields};
+    use crate::visibility::Visibility;
+
+    #[test]
+    fn test_named_fields() {
+        let fields = Fields::from_string("{ /// The a\n pub a: HashMap<u8, String>, #[serde(skip)] b: Option<u8>, }");
+        match &fields {
+            Fields::Named(f) => {
+                assert_eq!(f.len(), 2);
+                assert_eq!(f[0].prologue, "/// The a");
+                assert_eq!(f[0].vis, Visibility::Pub);
+                assert_eq!(f[0].name, "a");
+                assert_eq!(f[0].ty, "HashMap<u8, String>");
+                assert_eq!(f[1].prologue, "#[serde(skip)]");
+                assert_eq!(f[1].name, "b");
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(
+            fields.render(""),
+            " {\n    /// The a\n    pub a: HashMap<u8, String>,\n    #[serde(skip)]\n    b: Option<u8>,\n}"
+        );
+    }
+
+    #[test]
+    fn test_tuple_fields() {
+        let fields = Fields::from_string("(pub u8, std::string::String)");
+        assert_eq!(
+            fields,
+            Fields::Tuple(vec![
+                FieldDecl {
+                    vis: Visibility::Pub,
+                    ..FieldDecl::new("", "u8")
+                },
+                FieldDecl::new("", "std::string::String"),
+            ])
+        );
+        assert_eq!(fields.render(""), "(pub u8, std::string::String)");
+        assert_eq!(Fields::from_string("").len(), 0);
+    }
+}
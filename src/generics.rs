@@ -0,0 +1,61 @@
+use crate::scan::{find_close_angle, split_top_level};
+
+/// Split `Name<generics> where ...` into its three parts.
+pub(crate) fn split_type_header(header: &str) -> Option<(String, String, String)> {
+    let header = header.trim();
+    let name_end = header
+        .find(|c: char| !(c == '_' || c.is_alphanumeric()))
+        .unwrap_or(header.len());
+    let name = header[..name_end].to_string();
+    let rest = header[name_end..].trim_start();
+    let (generics, rest) = if rest.starts_with('<') {
+        let close = find_close_angle(rest, 0)?;
+        (rest[..=close].to_string(), rest[close + 1..].trim())
+    } else {
+        (String::new(), rest)
+    };
+    Some((name, generics, rest.to_string()))
+}
+
+/// Turn declared generic parameters into the arguments used to name the type,
+/// dropping bounds and defaults: `<'a, T: Clone = u8, const N: usize>` gives
+/// `<'a, T, N>`.
+pub fn generic_args(generics: &str) -> String {
+    let inner = generics.trim();
+    let inner = inner.strip_prefix('<').unwrap_or(inner);
+    let inner = inner.strip_suffix('>').unwrap_or(inner);
+    let args: Vec<&str> = split_top_level(inner, ',', true)
+        .into_iter()
+        .map(|p| p.trim())
+        .filter(|p| !p.is_empty())
+        .map(|p| {
+            let p = p.strip_prefix("const ").map(|c| c.trim_start()).unwrap_or(p);
+            p.split([':', '=']).next().unwrap_or(p).trim()
+        })
+        .collect();
+    if args.is_empty() {
+        String::new()
+    } else {
+        format!("<{}>", args.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::generics::{generic_args, split_type_header};
+
+    #[test]
+    fn test_split_type_header() {
+        assert_eq!(
+            split_type_header(" Foo<T: Fn() -> u8> where T: Copy "),
+            Some(("Foo".to_string(), "<T: Fn() -> u8>".to_string(), "where T: Copy".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_generic_args() {
+        assert_eq!(generic_args("<'a, T: Clone = u8, const N: usize>"), "<'a, T, N>");
+        assert_eq!(generic_args("<'a: 'b, 'b>"), "<'a, 'b>");
+        assert_eq!(generic_args(""), "");
+    }
+}
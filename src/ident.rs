@@ -0,0 +1,134 @@
+//! Identifier helpers for generated code: keyword-aware escaping,
+//! sanitizing arbitrary text into identifiers and collision-free names for
+//! companion items.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::NameRegistry;
+
+/// Strict and reserved keywords (2018 edition and later).
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate", "do", "dyn", "else",
+    "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in", "let", "loop", "macro", "match", "mod",
+    "move", "mut", "override", "priv", "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait",
+    "true", "try", "type", "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// Keywords that cannot be written as raw identifiers.
+const NOT_RAW: &[&str] = &["crate", "self", "Self", "super"];
+
+/// Prefix of every name produced by [`mangle`] and [`unique`].
+const PREFIX: &str = "__proc_macro_tools";
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+pub fn is_keyword(s: &str) -> bool {
+    KEYWORDS.contains(&s)
+}
+
+/// `name` as an identifier usable in any position: keywords become raw
+/// identifiers (`type` gives `r#type`), and those that cannot be raw get a
+/// trailing underscore (`self_`).
+pub fn raw(name: &str) -> String {
+    if NOT_RAW.contains(&name) {
+        format!("{}_", name)
+    } else if is_keyword(name) {
+        format!("r#{}", name)
+    } else {
+        name.to_string()
+    }
+}
+
+/// `name` without any `r#` prefix, e.g. for building a derived name.
+pub fn unraw(name: &str) -> &str {
+    name.strip_prefix("r#").unwrap_or(name)
+}
+
+/// Turn arbitrary text into a valid identifier: runs of characters that
+/// cannot appear in one become a single `_`, a leading digit gets a `_`
+/// prefix, and keywords are escaped as by [`raw`]. `"my-crate 2.0"` gives
+/// `my_crate_2_0`.
+pub fn sanitize(s: &str) -> String {
+    let out = replace_invalid(s);
+    match out.chars().next() {
+        None => "_".to_string(),
+        Some(c) if c.is_numeric() => format!("_{}", out),
+        _ => raw(&out),
+    }
+}
+
+/// A name for a generated item derived from `base` and `salt`, unlikely to
+/// clash with user code: `mangle("foo", "inner")` gives
+/// `__proc_macro_tools_foo_inner`. The same inputs always give the same
+/// name; use [`unique`] when one item may be expanded more than once.
+pub fn mangle(base: &str, salt: &str) -> String {
+    let mut name = format!("{}_{}", PREFIX, replace_invalid(base));
+    if !salt.is_empty() {
+        name.push('_');
+        name.push_str(&replace_invalid(salt));
+    }
+    name
+}
+
+fn replace_invalid(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in unraw(s.trim()).chars() {
+        if c == '_' || c.is_alphanumeric() {
+            out.push(c);
+        } else if !out.ends_with('_') {
+            out.push('_');
+        }
+    }
+    out
+}
+
+/// Like [`mangle`] with a counter appended that is shared by every
+/// expansion in the compiler process, e.g. `__proc_macro_tools_foo_inner_0`,
+/// so applying a macro twice to one item gives two different names.
+pub fn unique(base: &str, salt: &str) -> String {
+    format!("{}_{}", mangle(base, salt), COUNTER.fetch_add(1, Ordering::Relaxed))
+}
+
+impl NameRegistry {
+    /// [`NameRegistry::claim`] for arbitrary text, sanitized first.
+    pub fn claim_ident(&mut self, text: &str) -> String {
+        self.claim(&sanitize(text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ident::{is_keyword, mangle, raw, sanitize, unique, unraw};
+    use crate::NameRegistry;
+
+    #[test]
+    fn test_raw_and_sanitize() {
+        assert!(is_keyword("type") && !is_keyword("kind"));
+        assert_eq!(raw("type"), "r#type");
+        assert_eq!(raw("self"), "self_");
+        assert_eq!(raw("value"), "value");
+        assert_eq!(unraw("r#match"), "match");
+        assert_eq!(sanitize("my-crate 2.0"), "my_crate_2_0");
+        assert_eq!(sanitize("3d"), "_3d");
+        assert_eq!(sanitize("fn"), "r#fn");
+        assert_eq!(sanitize("r#loop"), "r#loop");
+        assert_eq!(sanitize("Ünïcode::path"), "Ünïcode_path");
+        assert_eq!(sanitize("_private"), "_private");
+        assert_eq!(sanitize("--"), "_");
+        assert_eq!(sanitize(""), "_");
+    }
+
+    #[test]
+    fn test_mangle() {
+        assert_eq!(mangle("foo", "inner"), "__proc_macro_tools_foo_inner");
+        assert_eq!(mangle("r#type", ""), "__proc_macro_tools_type");
+        assert_eq!(mangle("self", "impl"), "__proc_macro_tools_self_impl");
+        let (a, b) = (unique("foo", "inner"), unique("foo", "inner"));
+        assert!(a.starts_with("__proc_macro_tools_foo_inner_"));
+        assert_ne!(a, b);
+
+        let mut names = NameRegistry::new();
+        assert_eq!(names.claim_ident("user id"), "user_id");
+        assert_eq!(names.claim_ident("user-id"), "user_id_2");
+    }
+}
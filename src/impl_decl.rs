@@ -0,0 +1,373 @@
+use crate::scan::{find_close, find_close_angle, find_keyword, find_top_level, split_items, split_leading_attrs};
+use crate::trait_decl::{TraitDecl, TraitItem, TraitMethod};
+use crate::{generic_args, indent, sealed, Decl, FunctionDecl, ParseError, ParseErrorKind, Visibility};
+
+/// One item inside an `impl` block.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[allow(clippy::large_enum_variant)]
+pub enum ImplItem {
+    Fn(FunctionDecl),
+    /// Any other item (associated const, type, macro invocation), kept verbatim.
+    Other(String),
+}
+
+impl ImplItem {
+    pub fn render(&self) -> String {
+        match self {
+            ImplItem::Fn(f) => f.render(),
+            ImplItem::Other(s) => s.clone(),
+        }
+    }
+}
+
+/// Options for [`ImplBlockDecl::extract_trait`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ExtractOptions {
+    /// Visibility of the generated trait.
+    pub vis: Visibility,
+    /// Also extract private methods, not just those with a `pub` qualifier.
+    pub include_private: bool,
+    /// Skip associated functions without a `self` receiver.
+    pub methods_only: bool,
+}
+
+/// A parsed `impl` block, inherent or for a trait.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ImplBlockDecl {
+    /// Attributes and qualifiers (`unsafe`, `default`) before `impl`.
+    pub prologue: String,
+    /// Generic parameters including the angle brackets, e.g. `<T: Clone>`.
+    pub generics: String,
+    /// The implemented trait, empty for an inherent impl.
+    pub trait_name: String,
+    pub self_ty: String,
+    /// The `where` clause, empty if there is none.
+    pub where_clause: String,
+    pub items: Vec<ImplItem>,
+}
+
+impl ImplBlockDecl {
+    /// Parse an impl block, panicking if the input is not one.
+    pub fn from_string(in_str: String) -> Self {
+        Self::try_from_string(&in_str).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_from_string(in_str: &str) -> Result<Self, ParseError> {
+        let impl_idx = find_keyword(in_str, "impl")
+            .ok_or_else(|| ParseError::new(ParseErrorKind::MissingKeyword("impl"), 0))?;
+        let prologue = in_str[..impl_idx].trim().to_string();
+
+        let mut pos = impl_idx + 4;
+        let generics = if in_str[pos..].trim_start().starts_with('<') {
+            let open = pos + in_str[pos..].find('<').unwrap_or(0);
+            let close = find_close_angle(in_str, open)
+                .ok_or_else(|| ParseError::new(ParseErrorKind::Unbalanced('<'), open))?;
+            pos = close + 1;
+            in_str[open..=close].to_string()
+        } else {
+            String::new()
+        };
+
+        let body_idx = pos
+            + find_top_level(&in_str[pos..], '{', true)
+                .ok_or_else(|| ParseError::new(ParseErrorKind::Expected('{'), pos))?;
+        let body_end = find_close(in_str, body_idx)
+            .ok_or_else(|| ParseError::new(ParseErrorKind::Unbalanced('{'), body_idx))?;
+
+        let header = &in_str[pos..body_idx];
+        let (header, where_clause) = match find_keyword(header, "where") {
+            Some(idx) => (&header[..idx], header[idx..].trim()),
+            None => (header, ""),
+        };
+        let (trait_name, self_ty) = match find_keyword(header, "for") {
+            Some(idx) => (header[..idx].trim(), header[idx + 3..].trim()),
+            None => ("", header.trim()),
+        };
+
+        let items = split_items(&in_str[body_idx + 1..body_end])
+            .into_iter()
+            .map(|item| {
+                let (_, rest) = split_leading_attrs(item);
+                match (find_keyword(rest, "fn"), FunctionDecl::try_from_string(item)) {
+                    (Some(_), Ok(f)) if !rest.trim_end().ends_with(';') => ImplItem::Fn(f),
+                    _ => ImplItem::Other(item.to_string()),
+                }
+            })
+            .collect();
+
+        Ok(ImplBlockDecl {
+            prologue,
+            generics,
+            trait_name: trait_name.to_string(),
+            self_ty: self_ty.to_string(),
+            where_clause: where_clause.to_string(),
+            items,
+        })
+    }
+
+    pub fn is_trait_impl(&self) -> bool {
+        !self.trait_name.is_empty()
+    }
+
+    pub fn methods(&self) -> impl Iterator<Item = &FunctionDecl> {
+        self.items.iter().filter_map(|i| match i {
+            ImplItem::Fn(f) => Some(f),
+            ImplItem::Other(_) => None,
+        })
+    }
+
+    pub fn methods_mut(&mut self) -> impl Iterator<Item = &mut FunctionDecl> {
+        self.items.iter_mut().filter_map(|i| match i {
+            ImplItem::Fn(f) => Some(f),
+            ImplItem::Other(_) => None,
+        })
+    }
+
+    pub fn method(&self, name: &str) -> Option<&FunctionDecl> {
+        self.methods().find(|f| f.fn_name == name)
+    }
+
+    /// Replace every method with the result of `f`, keeping the item order.
+    pub fn map_methods<F: FnMut(FunctionDecl) -> FunctionDecl>(mut self, mut f: F) -> Self {
+        self.items = self
+            .items
+            .into_iter()
+            .map(|i| match i {
+                ImplItem::Fn(decl) => ImplItem::Fn(f(decl)),
+                other => other,
+            })
+            .collect();
+        self
+    }
+
+    /// Replace the method named `name`, returning `false` if there is none.
+    pub fn replace_method(&mut self, name: &str, method: FunctionDecl) -> bool {
+        match self.methods_mut().find(|f| f.fn_name == name) {
+            Some(existing) => {
+                *existing = method;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Generate a trait named `trait_name` from this block's public methods,
+    /// together with an `impl` of it for the same type forwarding each method
+    /// to the inherent one. The trait takes the block's generics and `where`
+    /// clause so its signatures can name them. Trait methods keep only their
+    /// doc comments, and their parameters are normalized as trait
+    /// declarations require (no `mut` bindings or patterns).
+    pub fn extract_trait(&self, trait_name: &str, options: &ExtractOptions) -> (TraitDecl, ImplBlockDecl) {
+        let extracted: Vec<&FunctionDecl> = self
+            .methods()
+            .filter(|f| options.include_private || !f.vis.is_private())
+            .filter(|f| !options.methods_only || f.receiver().is_some())
+            .collect();
+
+        let mut trait_items = Vec::new();
+        let mut impl_items = Vec::new();
+        for method in extracted {
+            let mut sig = method.clone();
+            sig.func_prologue = method
+                .func_prologue
+                .lines()
+                .filter(|l| l.trim_start().starts_with("///"))
+                .map(|l| format!("{}\n", l.trim()))
+                .collect();
+            sig.vis = Visibility::Private;
+            sig.set_params(&method.forwardable_params());
+            sig.fn_body = String::new();
+
+            let mut forward = sig.clone();
+            forward.func_prologue = String::new();
+            let args: Vec<String> = forward
+                .params()
+                .into_iter()
+                .map(|p| if p.is_receiver() { "self".to_string() } else { p.name })
+                .collect();
+            forward.fn_body = format!(
+                "Self::{}({}){}",
+                method.fn_name,
+                args.join(", "),
+                if method.async_str.is_empty() { "" } else { ".await" }
+            );
+
+            trait_items.push(TraitItem::Method(TraitMethod { sig, body: None }));
+            impl_items.push(ImplItem::Fn(forward));
+        }
+
+        let trait_decl = TraitDecl {
+            prologue: String::new(),
+            vis: options.vis.clone(),
+            unsafe_str: String::new(),
+            name: trait_name.to_string(),
+            generics: self.generics.clone(),
+            supertraits: String::new(),
+            where_clause: self.where_clause.clone(),
+            items: trait_items,
+        };
+        let impl_decl = ImplBlockDecl {
+            prologue: String::new(),
+            generics: self.generics.clone(),
+            trait_name: format!("{}{}", trait_name, generic_args(&self.generics)),
+            self_ty: self.self_ty.clone(),
+            where_clause: self.where_clause.clone(),
+            items: impl_items,
+        };
+        (trait_decl, impl_decl)
+    }
+
+    /// The `impl` line up to (not including) the opening brace.
+    pub fn header(&self) -> String {
+        format!(
+            "{}{}impl{} {}{}{}",
+            self.prologue,
+            if self.prologue.is_empty() { "" } else { " " },
+            self.generics,
+            if self.trait_name.is_empty() {
+                String::new()
+            } else {
+                format!("{} for ", self.trait_name)
+            },
+            self.self_ty,
+            if self.where_clause.is_empty() {
+                String::new()
+            } else {
+                format!(" {}", self.where_clause)
+            }
+        )
+    }
+}
+
+impl std::str::FromStr for ImplBlockDecl {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, ParseError> {
+        Self::try_from_string(s)
+    }
+}
+
+impl sealed::Sealed for ImplBlockDecl {}
+
+impl Decl for ImplBlockDecl {
+    fn render(&self) -> String {
+        format!(
+            "{} {{\n{}\n}}",
+            self.header(),
+            self.items
+                .iter()
+                .map(|i| indent(&i.render()))
+                .collect::<Vec<String>>()
+                .join("\n\n")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::impl_decl::{ExtractOptions, ImplBlockDecl, ImplItem};
+    use crate::{Decl, Visibility};
+
+    const INPUT: &str = "#[automatically_derived]
+    unsafe impl<T: Clone> Service for Wrapper<T> where T: Debug {
+        type Output = u8;
+
+        #[inline]
+        fn call(&self, req: Request) -> u8 {
+            let s = \"}\";
+            s.len() as u8
+        }
+
+        const LIMIT: u8 = 3;
+
+        pub fn ready(&mut self) -> bool { true }
+    }";
+
+    #[test]
+    fn test_impl_parse() {
+        let decl: ImplBlockDecl = INPUT.parse().unwrap();
+        assert_eq!(decl.prologue, "#[automatically_derived]\n    unsafe");
+        assert_eq!(decl.generics, "<T: Clone>");
+        assert_eq!(decl.trait_name, "Service");
+        assert_eq!(decl.self_ty, "Wrapper<T>");
+        assert_eq!(decl.where_clause, "where T: Debug");
+        assert_eq!(decl.items.len(), 4);
+        assert!(matches!(&decl.items[0], ImplItem::Other(s) if s == "type Output = u8;"));
+        assert_eq!(
+            decl.methods().map(|m| m.fn_name.as_str()).collect::<Vec<&str>>(),
+            vec!["call", "ready"]
+        );
+        assert_eq!(decl.method("call").unwrap().func_prologue, "#[inline]\n");
+        assert!(decl.method("call").unwrap().fn_body.ends_with("s.len() as u8"));
+    }
+
+    #[test]
+    fn test_impl_inherent() {
+        let decl = ImplBlockDecl::from_string("impl Foo { fn a() {} }".to_string());
+        assert!(!decl.is_trait_impl());
+        assert_eq!(decl.self_ty, "Foo");
+        assert_eq!(decl.render(), "impl Foo {\n    fn a() {\n\n    }\n}");
+    }
+
+    #[test]
+    fn test_extract_trait() {
+        let decl = ImplBlockDecl::from_string(
+            "impl<T> Repo<T> {
+                /// Look up an item
+                #[inline]
+                pub fn get(&self, id: u64) -> Option<&T> { self.items.get(&id) }
+                pub async fn save(&mut self, (id, item): (u64, T)) { self.items.insert(id, item); }
+                pub fn into_items(mut self) -> Vec<T> { self.items.drain().map(|(_, v)| v).collect() }
+                pub fn new() -> Self { Repo { items: HashMap::new() } }
+                fn helper(&self) {}
+            }"
+            .to_string(),
+        );
+        let options = ExtractOptions {
+            vis: Visibility::Pub,
+            methods_only: true,
+            ..ExtractOptions::default()
+        };
+        let (trait_decl, forward) = decl.extract_trait("Store", &options);
+        let expected = "pub trait Store<T> {
+    /// Look up an item
+    fn get(&self, id: u64) -> Option<&T>;
+
+    async fn save(&mut self, __arg1: (u64, T));
+
+    fn into_items(self) -> Vec<T>;
+}";
+        assert_eq!(trait_decl.render(), expected);
+        assert_eq!(forward.header(), "impl<T> Store<T> for Repo<T>");
+        let bodies: Vec<&str> = forward.methods().map(|m| m.fn_body.as_str()).collect();
+        assert_eq!(
+            bodies,
+            vec!["Self::get(self, id)", "Self::save(self, __arg1).await", "Self::into_items(self)"]
+        );
+
+        let decl = ImplBlockDecl::from_string("impl<'a, K: Hash> Index<'a, K> where K: Eq { pub fn get(&self, k: &K) -> &'a str { todo!() } }".to_string());
+        let (trait_decl, forward) = decl.extract_trait("Lookup", &ExtractOptions::default());
+        assert_eq!(trait_decl.render(), "trait Lookup<'a, K: Hash> where K: Eq {\n    fn get(&self, k: &K) -> &'a str;\n}");
+        assert_eq!(forward.header(), "impl<'a, K: Hash> Lookup<'a, K> for Index<'a, K> where K: Eq");
+    }
+
+    #[test]
+    fn test_impl_splice_methods() {
+        let decl = ImplBlockDecl::from_string("impl Foo { const A: u8 = 1; fn a(&self) -> u8 { 1 } }".to_string());
+        let decl = decl.map_methods(|mut f| {
+            f.fn_body = format!("log();\n{}", f.fn_body);
+            f
+        });
+        let expected = "impl Foo {
+    const A: u8 = 1;
+
+    fn a(&self) -> u8 {
+    log();
+    1
+    }
+}";
+        assert_eq!(decl.render(), expected);
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use lazy_static::lazy_static;
+
+use crate::{Item, ItemKind, ParseError};
+
+/// Parses one custom item, given its full text including attributes.
+///
+/// It may return any [`Item`]: a decl the DSL item expands to, or
+/// `Item::Other(ItemKind::Custom(tag), text)` to keep it as text.
+pub type ItemParser = fn(&str) -> Result<Item, ParseError>;
+
+/// A registered keyword. Spelled as an alias so that serde does not try to
+/// borrow it from the input; see [`deserialize_tag`].
+pub(crate) type KindTag = &'static str;
+
+lazy_static! {
+    static ref ITEM_PARSERS: RwLock<HashMap<&'static str, ItemParser>> = RwLock::new(HashMap::new());
+}
+
+/// Teach [`ItemKind::sniff`] and [`crate::parse_items`] about a custom item
+/// introduced by the keyword `kind_tag`, e.g. `route` for items like
+/// `route GET "/users" => list_users;`, so that macro input mixing a DSL with
+/// Rust items goes through one pipeline.
+///
+/// Such items are split like Rust items: they end at a top-level `;` or at
+/// the `}` closing their first block. Rust keywords always keep their own
+/// meaning, and registering a tag again replaces its parser.
+pub fn register_item_parser(kind_tag: &'static str, parser: ItemParser) {
+    ITEM_PARSERS.write().unwrap_or_else(|e| e.into_inner()).insert(kind_tag, parser);
+}
+
+/// The custom kind for an item starting with the identifier `ident`, followed
+/// by `rest` in the same word, if one is registered.
+pub(crate) fn custom_kind(ident: &str, rest: &str) -> Option<ItemKind> {
+    // `route(x)`, `route.x` or `route::x` are expressions and paths, not items.
+    if rest.starts_with(|c: char| "(.:=;!".contains(c)) {
+        return None;
+    }
+    let parsers = ITEM_PARSERS.read().unwrap_or_else(|e| e.into_inner());
+    parsers.get_key_value(ident).map(|(tag, _)| ItemKind::Custom(tag))
+}
+
+/// Deserialize a custom kind tag, which must be registered in this process.
+#[cfg(feature = "serde")]
+pub(crate) fn deserialize_tag<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<&'static str, D::Error> {
+    let tag = <String as serde::Deserialize>::deserialize(deserializer)?;
+    let parsers = ITEM_PARSERS.read().unwrap_or_else(|e| e.into_inner());
+    match parsers.get_key_value(tag.as_str()) {
+        Some((tag, _)) => Ok(tag),
+        None => Err(serde::de::Error::custom(format!("no item parser registered for `{}`", tag))),
+    }
+}
+
+pub(crate) fn item_parser(kind_tag: &str) -> Option<ItemParser> {
+    ITEM_PARSERS.read().unwrap_or_else(|e| e.into_inner()).get(kind_tag).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::scan::split_leading_attrs;
+    use crate::{parse_items, register_item_parser, Item, ItemKind, ParseError, ParseErrorKind};
+
+    fn parse_route(src: &str) -> Result<Item, ParseError> {
+        let (_, rest) = split_leading_attrs(src);
+        let words: Vec<&str> = rest.trim_end_matches(';').split_whitespace().collect();
+        match words.as_slice() {
+            ["test_route", method, path, "=>", handler] => Ok(Item::Fn(crate::FunctionDecl::from_string(format!(
+                "fn {}_{}() -> (&'static str, &'static str) {{ ({:?}, {}) }}",
+                method.to_lowercase(),
+                handler,
+                method,
+                path
+            )))),
+            _ => Err(ParseError::new(ParseErrorKind::Invalid("malformed route".to_string()), 0)),
+        }
+    }
+
+    #[test]
+    fn test_register_item_parser() {
+        assert_eq!(ItemKind::sniff("test_route GET \"/\" => index;"), ItemKind::Other);
+        register_item_parser("test_route", parse_route);
+        assert_eq!(ItemKind::sniff("test_route GET \"/\" => index;"), ItemKind::Custom("test_route"));
+        assert_eq!(ItemKind::sniff("test_route(1);"), ItemKind::Other);
+
+        let items = parse_items("use a::b;\ntest_route GET \"/users\" => list_users;\ntest_route broken;\nfn x() {}");
+        assert_eq!(items.len(), 4);
+        assert!(matches!(&items[1], Item::Fn(f) if f.fn_name == "get_list_users"));
+        assert_eq!(items[2].kind(), ItemKind::Custom("test_route"));
+        assert_eq!(items[2].render(), "test_route broken;");
+        assert_eq!(items[3].kind(), ItemKind::Fn);
+    }
+}
@@ -1,10 +1,115 @@
+//! String-based helpers for writing procedural macros.
+//!
+//! The API comes in two tiers. Everything re-exported from [`prelude`] (the
+//! decl/model types and the [`Decl`] trait) follows semver. Generators under
+//! [`presets`] are experimental, require the `unstable` feature, and may change
+//! in any release.
+
 use lazy_static::lazy_static;
-use proc_macro;
-use regex::{Captures, Regex};
+use proc_macro2::{Span, TokenStream};
+use regex::{CaptureLocations, Regex};
+
+mod attr;
+mod attr_args;
+mod body;
+mod borrowed;
+mod builder;
+mod closure_decl;
+mod compat;
+mod const_decl;
+mod diagnostics;
+mod enum_decl;
+mod error;
+mod extern_block_decl;
+mod field;
+mod generics;
+mod impl_decl;
+mod item_parser;
+mod manifest;
+mod mod_decl;
+mod names;
+mod params;
+mod preserve;
+mod render;
+mod rewrite;
+mod scan;
+mod schema;
+mod signature;
+mod spans;
+mod splice;
+mod struct_decl;
+mod trait_decl;
+mod type_alias_decl;
+mod types;
+mod verify;
+mod visibility;
+pub mod asserts;
+pub mod ident;
+pub mod tokens;
+#[cfg(any(test, feature = "unstable"))]
+pub mod presets;
+
+pub use attr::Attribute;
+pub use attr_args::{AttrArgs, AttrValue};
+pub use body::{Body, Hunk, Stmt};
+pub use borrowed::FunctionDeclRef;
+pub use builder::{FunctionDeclBuilder, ImplBuilder};
+pub use closure_decl::{CaptureMode, ClosureDecl};
+pub use compat::SignatureMismatch;
+pub use const_decl::{ConstDecl, StaticDecl};
+pub use diagnostics::ParseOptions;
+pub use enum_decl::{EnumDecl, Variant};
+pub use error::{ParseError, ParseErrorKind};
+pub use extern_block_decl::{ExternBlockDecl, ForeignItem};
+pub use field::{FieldDecl, Fields};
+pub use generics::generic_args;
+pub use impl_decl::{ExtractOptions, ImplBlockDecl, ImplItem};
+pub use item_parser::{register_item_parser, ItemParser};
+pub use manifest::{Manifest, ManifestEntry};
+pub use mod_decl::{parse_items, parse_items_with, Item, ItemKind, ModDecl};
+pub use names::NameRegistry;
+pub use params::{parse_params, render_params, Param, Receiver};
+pub use preserve::SourceText;
+pub use render::{BraceStyle, RenderOptions};
+pub use schema::SchemaFormat;
+pub use signature::{render_labels, FnSignature, LabelOptions};
+pub use spans::{FieldOffsets, SpanMap};
+pub use splice::{Splice, SplicePoint};
+pub use struct_decl::StructDecl;
+pub use trait_decl::{TraitDecl, TraitItem, TraitMethod};
+pub use type_alias_decl::TypeAliasDecl;
+pub use types::{has_elided_lifetime, lifetimes_in, ReturnType, TypeKind};
+pub use verify::verify;
+pub use visibility::Visibility;
+
+/// The stable API tier: `use proc_macro_tools::prelude::*;`.
+pub mod prelude {
+    pub use crate::{
+        AttrArgs, AttrValue, Attribute, Body, BraceStyle, CaptureMode, ClosureDecl, ConstDecl, Decl, EnumDecl,
+        ExternBlockDecl, ExtractOptions, FieldDecl, Fields, FnSignature, ForeignItem, FunctionDecl, FunctionDeclBuilder,
+        FunctionDeclRef, Hunk, ImplBlockDecl, ImplBuilder, ImplItem, Item, ItemKind, LabelOptions, ModDecl,
+        NameRegistry, Param, ParseError, ParseErrorKind, ParseOptions, Receiver, RenderOptions, ReturnType,
+        SchemaFormat, SignatureMismatch, SourceText, Splice, SplicePoint, StaticDecl, Stmt, StructDecl, TraitDecl,
+        TraitItem, TraitMethod, TypeAliasDecl, TypeKind, Variant, Visibility,
+    };
+}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Common behaviour of every parsed item type in this crate.
+///
+/// This trait is sealed and cannot be implemented outside this crate, so new
+/// methods can be added to it without a breaking change.
+pub trait Decl: sealed::Sealed {
+    /// Re-emit the item as source text.
+    fn render(&self) -> String;
+}
 
 lazy_static! {
-    static ref FN_PATTERN: Regex =
-        Regex::new(r#"^([\w\W]*?) *(pub +)?(async +)?fn +([\w\W]*?)(\([\w\W]*?) +?(->[\w\W]*?)?[ \n]*\{([\S\s]*)\}"#)
+    static ref QUALIFIER_PATTERN: Regex =
+        Regex::new(r#"^([\w\W]*?) *(pub(?: *\([^)]*\))? +)?(const +)?(async +)?(unsafe +)?(extern *(?:"[^"]*" *)?)?$"#)
             .unwrap();
 }
 
@@ -12,94 +117,397 @@ fn add_space_or_empty(input: &str) -> String {
     if !input.is_empty() {
         format!("{} ", input)
     } else {
-        format!("")
+        String::new()
     }
 }
 
+/// Indent every non-empty line of `s` by four spaces.
+pub(crate) fn indent(s: &str) -> String {
+    s.lines()
+        .map(|l| if l.is_empty() { String::new() } else { format!("    {}", l) })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Signature start, body open and body close offsets; see `parse_with_offsets`.
+type SourceOffsets = (usize, usize, Option<usize>);
+
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FunctionDecl {
     pub func_prologue: String,
-    pub pub_str: String,
+    pub vis: Visibility,
+    /// `const` for a const fn, otherwise empty.
+    pub const_str: String,
     pub async_str: String,
+    /// `unsafe` for an unsafe fn, otherwise empty.
+    pub unsafe_str: String,
+    /// The `extern` qualifier including any ABI string, e.g. `extern "C"`;
+    /// empty if there is none.
+    pub abi: String,
     pub fn_name: String,
+    /// Generic parameters including the angle brackets, e.g. `<T: Clone>`.
+    pub generics: String,
     pub fn_decl: String,
     pub ret_decl: String,
+    /// The `where` clause, empty if there is none.
+    pub where_clause: String,
     pub fn_body: String,
+    /// Span of the original item when parsed from tokens; not serialized.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub span: Option<Span>,
+    /// The original text when parsed with [`FunctionDecl::parse_preserving`].
+    pub source: Option<SourceText>,
 }
 
 impl FunctionDecl {
+    /// Parse a function declaration, panicking if the input is not one.
     pub fn from_string(in_str: String) -> Self {
-        let caps: Captures = FN_PATTERN
-            .captures(in_str.as_ref())
-            .unwrap_or_else(|| panic!("Can only use on a function declaration"));
+        Self::try_from_string(&in_str).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_from_string(in_str: &str) -> Result<Self, ParseError> {
+        Self::parse_reusing(in_str, &mut QUALIFIER_PATTERN.capture_locations())
+    }
+
+    /// Parse many functions at once, one result per input in the same order.
+    ///
+    /// This is cheaper than calling [`FunctionDecl::try_from_string`] in a
+    /// loop, as the qualifier match buffers are shared between inputs. With
+    /// the `parallel` feature the inputs are parsed on the rayon thread pool.
+    pub fn parse_many(inputs: &[&str]) -> Vec<Result<Self, ParseError>> {
+        #[cfg(feature = "parallel")]
+        {
+            use rayon::prelude::*;
+            inputs
+                .par_iter()
+                // Workers return borrowed decls, which are `Send`; the owned
+                // ones are built here.
+                .map_init(|| QUALIFIER_PATTERN.capture_locations(), |locs, s| Self::parse_ref_reusing(s, locs))
+                .collect::<Vec<_>>()
+                .into_iter()
+                .map(|r| r.map(FunctionDeclRef::into_owned))
+                .collect()
+        }
+        #[cfg(not(feature = "parallel"))]
+        {
+            let mut locs = QUALIFIER_PATTERN.capture_locations();
+            inputs.iter().map(|s| Self::parse_reusing(s, &mut locs)).collect()
+        }
+    }
 
-        if caps.len() != 8 {
-            panic!("Must be a proper fn declaration");
+    fn parse_reusing(in_str: &str, locs: &mut CaptureLocations) -> Result<Self, ParseError> {
+        Self::parse_ref_reusing(in_str, locs).map(FunctionDeclRef::into_owned)
+    }
+
+    fn parse_ref_reusing<'a>(in_str: &'a str, locs: &mut CaptureLocations) -> Result<FunctionDeclRef<'a>, ParseError> {
+        match FunctionDeclRef::parse_with_offsets(in_str, locs)? {
+            (decl, (_, _, Some(_))) => Ok(decl),
+            (_, (_, _, None)) => Err(ParseError::new(
+                ParseErrorKind::Expected('{'),
+                in_str.rfind(';').unwrap_or(in_str.len()),
+            )),
         }
+    }
 
-        let func_prologue = caps[1].trim_matches(' ').to_string();
-        let pub_str = caps.get(2).map(|m| m.as_str()).unwrap_or("").trim().to_string();
-        let async_str = caps.get(3).map(|m| m.as_str()).unwrap_or("").trim().to_string();
-        let fn_name = caps[4].trim().to_string();
-        let fn_decl = caps[5].trim().to_string();
-
-        let ret_decl = caps
-            .get(6)
-            .map(|m| {
-                let mut s = m.as_str().to_string();
-                let (p, _) = s.char_indices().nth(2).unwrap();
-                s.drain(0..p);
-                s.trim().to_string()
-            })
-            .unwrap_or("".to_string());
-
-        let fn_body = caps[7].trim().to_string();
-
-        FunctionDecl {
-            func_prologue,
-            pub_str,
-            async_str,
-            fn_name,
-            fn_decl,
-            ret_decl,
-            fn_body,
+    /// Parse a function that may end in `;` instead of a body, as in trait
+    /// definitions. The second value is the body if there is one; the returned
+    /// decl's `fn_body` is left empty when there is not.
+    pub(crate) fn parse_signature(in_str: &str) -> Result<(Self, Option<String>), ParseError> {
+        let (decl, (_, _, body_close)) = Self::parse_with_offsets(in_str, &mut QUALIFIER_PATTERN.capture_locations())?;
+        let body = body_close.map(|_| decl.fn_body.clone());
+        Ok((decl, body))
+    }
+
+    /// Parse a function declaration without a body, ending in `;`, such as
+    /// a foreign function in an `extern` block. The returned decl's
+    /// `fn_body` is empty; render it with [`FunctionDecl::render_declaration`].
+    pub fn try_from_declaration(in_str: &str) -> Result<Self, ParseError> {
+        match Self::parse_with_offsets(in_str, &mut QUALIFIER_PATTERN.capture_locations())? {
+            (decl, (_, _, None)) => Ok(decl),
+            (_, (_, body_open, Some(_))) => Err(ParseError::new(ParseErrorKind::Expected(';'), body_open)),
+        }
+    }
+
+    /// The signature followed by `;`, e.g. `fn abs(x: i32) -> i32;`.
+    pub fn render_declaration(&self) -> String {
+        format!("{};", self.func_signature())
+    }
+
+    /// Parse a function that may end in `;`, also returning the byte offsets
+    /// of the signature start (after attributes), the body's opening brace (or
+    /// `;`) and its closing brace, `None` if there is no body.
+    fn parse_with_offsets(in_str: &str, locs: &mut CaptureLocations) -> Result<(Self, SourceOffsets), ParseError> {
+        FunctionDeclRef::parse_with_offsets(in_str, locs).map(|(decl, offsets)| (decl.into_owned(), offsets))
+    }
+
+    /// Parse a function from a token stream, remembering the span of its first
+    /// token so that re-emitted code reports errors at the original item.
+    pub fn from_token_stream(ts: TokenStream) -> Result<Self, ParseError> {
+        let span = ts.clone().into_iter().next().map(|tt| tt.span());
+        let mut decl = Self::try_from_string(&ts.to_string())?;
+        decl.span = span;
+        Ok(decl)
+    }
+
+    /// The return type, classified.
+    pub fn return_type(&self) -> ReturnType {
+        ReturnType::from_string(&self.ret_decl)
+    }
+
+    /// The lifetime parameters declared in the generics, without bounds:
+    /// `<'a, 'b: 'a, T>` gives `["'a", "'b"]`.
+    pub fn lifetimes(&self) -> Vec<String> {
+        generic_args(&self.generics)
+            .trim_start_matches('<')
+            .trim_end_matches('>')
+            .split(',')
+            .map(|a| a.trim())
+            .filter(|a| a.starts_with('\''))
+            .map(|a| a.to_string())
+            .collect()
+    }
+
+    /// Whether any parameter, the receiver or the return type relies on
+    /// lifetime elision, so re-emitting the signature somewhere the elision
+    /// rules differ (e.g. a boxed future) needs the lifetimes named.
+    pub fn has_elided_lifetimes(&self) -> bool {
+        self.params().iter().any(|p| p.has_elided_lifetime()) || has_elided_lifetime(&self.ret_decl)
+    }
+
+    pub fn params(&self) -> Vec<Param> {
+        params::parse_params(&self.fn_decl)
+    }
+
+    pub fn set_params(&mut self, params: &[Param]) {
+        self.fn_decl = params::render_params(params);
+    }
+
+    /// Whether this is a C-variadic function, whose last parameter is `...`.
+    pub fn is_variadic(&self) -> bool {
+        matches!(self.params().last(), Some(p) if p.is_variadic())
+    }
+
+    /// The kind of `self` receiver this function takes, if any.
+    pub fn receiver(&self) -> Option<Receiver> {
+        self.params().first().and_then(|p| p.receiver())
+    }
+
+    /// The call-site argument list forwarding this function's parameters,
+    /// e.g. `a, (x, y), b` for `(mut a: A, (x, y): (u8, u8), ref b: B)`. The
+    /// receiver is left out, as it goes before the method name. `None` if a
+    /// parameter pattern discards part of its value; see
+    /// [`Param::forward_arg`].
+    pub fn forward_args(&self) -> Option<String> {
+        self.params()
+            .iter()
+            .filter(|p| !p.is_receiver())
+            .map(|p| p.forward_arg())
+            .collect::<Option<Vec<String>>>()
+            .map(|args| args.join(", "))
+    }
+
+    /// A call to `target` forwarding this function's arguments, going through
+    /// `self.` when the function is a method. `None` if a parameter pattern
+    /// discards part of its value; see [`FunctionDecl::forward_args`].
+    pub fn delegate_call(&self, target: &str) -> Option<String> {
+        Some(self.call_with(target, &self.forward_args()?))
+    }
+
+    /// [`FunctionDecl::delegate_call`] with the parameters bound as by
+    /// [`FunctionDecl::forwardable_params`], for a function whose parameters
+    /// have been replaced by those.
+    pub(crate) fn forwarding_call(&self, target: &str) -> String {
+        let args: Vec<String> = self
+            .forwardable_params()
+            .into_iter()
+            .filter(|p| !p.is_receiver())
+            .map(|p| p.name)
+            .collect();
+        self.call_with(target, &args.join(", "))
+    }
+
+    fn call_with(&self, target: &str, args: &str) -> String {
+        if self.receiver().is_some() {
+            format!("self.{}({})", target, args)
+        } else {
+            format!("{}({})", target, args)
         }
     }
 
+    /// The parameters with pattern parameters bound to `__arg{index}` and
+    /// `mut`/`ref` dropped, so every argument can be forwarded by name.
+    pub(crate) fn forwardable_params(&self) -> Vec<Param> {
+        self.params()
+            .into_iter()
+            .enumerate()
+            .map(|(idx, p)| match p.receiver() {
+                Some(Receiver::Value { .. }) => Param::new("self", ""),
+                Some(_) => p,
+                None if p.is_pattern => Param::new(&format!("__arg{}", idx), &p.ty),
+                None => Param::new(&p.name, &p.ty),
+            })
+            .collect()
+    }
+
+    /// Split this function into `(inner, wrapper)`: `inner` is the original
+    /// renamed to `__inner_{name}` with all attributes but `cfg`s dropped,
+    /// `wrapper` keeps the original name, attributes and signature and has
+    /// `wrapper_body_template` as its body, with `{call}` replaced by a call
+    /// to `inner` (awaited if async).
+    ///
+    /// The wrapper binds pattern parameters to `__arg{index}` and drops `mut`
+    /// and `ref` so every argument can be forwarded by name.
+    pub fn delegate(&self, wrapper_body_template: &str) -> (FunctionDecl, FunctionDecl) {
+        let mut inner = self.clone();
+        inner.func_prologue = String::new();
+        self.copy_cfgs_to(&mut inner);
+        inner.vis = Visibility::Private;
+        inner.fn_name = format!("__inner_{}", self.fn_name);
+
+        let mut wrapper = self.clone();
+        wrapper.set_params(&self.forwardable_params());
+        let call = format!(
+            "{}{}",
+            wrapper.forwarding_call(&inner.fn_name),
+            if self.async_str.is_empty() { "" } else { ".await" }
+        );
+        wrapper.fn_body = wrapper_body_template.replace("{call}", &call);
+        (inner, wrapper)
+    }
+
     pub fn func_prelude(&self) -> String {
+        format!("{} {{", self.func_signature())
+    }
+
+    /// The prologue and signature, without the body or its opening brace.
+    pub fn func_signature(&self) -> String {
         format!(
-            "{}{}{}fn {}{}{} {{",
+            "{}{}{}{}{}{}fn {}{}{}{}{}",
             self.func_prologue,
-            add_space_or_empty(&self.pub_str),
+            self.vis.prefix(),
+            add_space_or_empty(&self.const_str),
             add_space_or_empty(&self.async_str),
+            add_space_or_empty(&self.unsafe_str),
+            add_space_or_empty(&self.abi),
             self.fn_name,
+            self.generics,
             self.fn_decl,
             if !self.ret_decl.is_empty() {
                 format!(" -> {}", self.ret_decl)
             } else {
                 "".to_string()
+            },
+            if !self.where_clause.is_empty() {
+                format!(" {}", self.where_clause)
+            } else {
+                "".to_string()
             }
         )
     }
 
+    /// Render the function and lex it into tokens that all carry `span`
+    /// (typically the span of the original item).
+    pub fn to_token_stream_spanned(&self, span: Span) -> Result<TokenStream, ParseError> {
+        tokens::parse_spanned(&self.render(), span)
+    }
+
     pub fn func_end(&self) -> String {
-        format!("}}")
+        "}".to_string()
     }
 
     pub fn into_func_body(self, body_add: String) -> String {
         format!("{}\n{}\n{}", self.func_prelude(), body_add, self.func_end())
     }
+
+    pub fn make_async(&mut self) -> &mut Self {
+        self.async_str = "async".to_string();
+        self
+    }
+
+    pub fn rename(&mut self, new_name: &str) -> &mut Self {
+        self.fn_name = new_name.to_string();
+        self
+    }
+
+    /// Replace the return type with `template`, where `{}` stands for the
+    /// current one (`()` if there is none), e.g. `anyhow::Result<{}>`.
+    pub fn wrap_return_type(&mut self, template: &str) -> &mut Self {
+        let current = if self.ret_decl.is_empty() { "()" } else { &self.ret_decl };
+        self.ret_decl = template.replace("{}", current);
+        self
+    }
+
+    /// Append a parameter given as source, e.g. `ctx: &Context`.
+    pub fn add_param(&mut self, param: &str) -> &mut Self {
+        let mut params = self.params();
+        params.push(Param::from_string(param));
+        self.set_params(&params);
+        self
+    }
+
+    /// Insert `code` before the existing body.
+    pub fn prepend_to_body(&mut self, code: &str) -> &mut Self {
+        self.fn_body = if self.fn_body.is_empty() {
+            code.to_string()
+        } else {
+            format!("{}\n{}", code, self.fn_body)
+        };
+        self
+    }
+
+    /// Run `code` after the existing body while keeping the body's value as
+    /// the function's result. Early `return`s skip `code`.
+    pub fn append_to_body(&mut self, code: &str) -> &mut Self {
+        self.fn_body = format!("let __result = {{\n{}\n}};\n{}\n__result", indent(&self.fn_body), code);
+        self
+    }
+
+    /// Surround the existing body with `prefix` and `suffix`, e.g.
+    /// `async move {` and `}.await`.
+    pub fn wrap_body(&mut self, prefix: &str, suffix: &str) -> &mut Self {
+        self.fn_body = format!("{}\n{}\n{}", prefix, indent(&self.fn_body), suffix);
+        self
+    }
+}
+
+impl std::str::FromStr for FunctionDecl {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, ParseError> {
+        Self::try_from_string(s)
+    }
+}
+
+/// Panics if `s` is not a function declaration; use `str::parse` to handle
+/// the error instead.
+impl From<&str> for FunctionDecl {
+    fn from(s: &str) -> Self {
+        Self::from_string(s.to_string())
+    }
+}
+
+impl sealed::Sealed for FunctionDecl {}
+
+impl Decl for FunctionDecl {
+    fn render(&self) -> String {
+        if let Some(source) = &self.source {
+            return source.render(self);
+        }
+        format!("{}\n{}\n{}", self.func_prelude(), self.fn_body, self.func_end())
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::FunctionDecl;
+    use crate::{Decl, FunctionDecl, ParseError, ParseErrorKind, Visibility};
 
     #[test]
     fn test_func_simple_one_line() {
         let test = "fn simple_sameline() {}".to_string();
         let funcdecl = FunctionDecl::from_string(test);
         assert_eq!(funcdecl.func_prologue, "");
-        assert_eq!(funcdecl.pub_str, "");
+        assert_eq!(funcdecl.vis, Visibility::Private);
         assert_eq!(funcdecl.async_str, "");
         assert_eq!(funcdecl.fn_name, "simple_sameline");
         assert_eq!(funcdecl.fn_decl, "()");
@@ -112,7 +520,7 @@ mod tests {
         }".to_string();
         let funcdecl = FunctionDecl::from_string(test);
         assert_eq!(funcdecl.func_prologue, "");
-        assert_eq!(funcdecl.pub_str, "");
+        assert_eq!(funcdecl.vis, Visibility::Private);
         assert_eq!(funcdecl.async_str, "");
         assert_eq!(funcdecl.fn_name, "simple_newline");
         assert_eq!(funcdecl.fn_decl, "()");
@@ -127,7 +535,7 @@ mod tests {
         }".to_string();
         let funcdecl = FunctionDecl::from_string(test);
         assert_eq!(funcdecl.func_prologue, "");
-        assert_eq!(funcdecl.pub_str, "");
+        assert_eq!(funcdecl.vis, Visibility::Private);
         assert_eq!(funcdecl.async_str, "");
         assert_eq!(funcdecl.fn_name, "simple_newline_brace");
         assert_eq!(funcdecl.fn_decl, "()");
@@ -140,7 +548,7 @@ mod tests {
         let test = "fn with_params(_: String) {}".to_string();
         let funcdecl = FunctionDecl::from_string(test);
         assert_eq!(funcdecl.func_prologue, "");
-        assert_eq!(funcdecl.pub_str, "");
+        assert_eq!(funcdecl.vis, Visibility::Private);
         assert_eq!(funcdecl.async_str, "");
         assert_eq!(funcdecl.fn_name, "with_params");
         assert_eq!(funcdecl.fn_decl, "(_: String)");
@@ -155,7 +563,7 @@ mod tests {
         }".to_string();
         let funcdecl = FunctionDecl::from_string(test);
         assert_eq!(funcdecl.func_prologue, "");
-        assert_eq!(funcdecl.pub_str, "");
+        assert_eq!(funcdecl.vis, Visibility::Private);
         assert_eq!(funcdecl.async_str, "");
         assert_eq!(funcdecl.fn_name, "with_body");
         assert_eq!(funcdecl.fn_decl, "(_: String)");
@@ -170,7 +578,7 @@ mod tests {
         }".to_string();
         let funcdecl = FunctionDecl::from_string(test);
         assert_eq!(funcdecl.func_prologue, "");
-        assert_eq!(funcdecl.pub_str, "");
+        assert_eq!(funcdecl.vis, Visibility::Private);
         assert_eq!(funcdecl.async_str, "");
         assert_eq!(funcdecl.fn_name, "with_return");
         assert_eq!(funcdecl.fn_decl, "(_: String)");
@@ -185,7 +593,7 @@ mod tests {
         }".to_string();
         let funcdecl = FunctionDecl::from_string(test);
         assert_eq!(funcdecl.func_prologue, "");
-        assert_eq!(funcdecl.pub_str, "pub");
+        assert_eq!(funcdecl.vis, Visibility::Pub);
         assert_eq!(funcdecl.async_str, "");
         assert_eq!(funcdecl.fn_name, "with_return");
         assert_eq!(funcdecl.fn_decl, "(_: String)");
@@ -200,7 +608,7 @@ mod tests {
         }".to_string();
         let funcdecl = FunctionDecl::from_string(test);
         assert_eq!(funcdecl.func_prologue, "");
-        assert_eq!(funcdecl.pub_str, "");
+        assert_eq!(funcdecl.vis, Visibility::Private);
         assert_eq!(funcdecl.async_str, "async");
         assert_eq!(funcdecl.fn_name, "with_return");
         assert_eq!(funcdecl.fn_decl, "(_: String)");
@@ -215,7 +623,7 @@ mod tests {
         }".to_string();
         let funcdecl = FunctionDecl::from_string(test);
         assert_eq!(funcdecl.func_prologue, "");
-        assert_eq!(funcdecl.pub_str, "pub");
+        assert_eq!(funcdecl.vis, Visibility::Pub);
         assert_eq!(funcdecl.async_str, "async");
         assert_eq!(funcdecl.fn_name, "with_return");
         assert_eq!(funcdecl.fn_decl, "(_: String)");
@@ -231,7 +639,7 @@ mod tests {
         }".to_string();
         let funcdecl = FunctionDecl::from_string(test);
         assert_eq!(funcdecl.func_prologue, "#[some_macro]\n");
-        assert_eq!(funcdecl.pub_str, "pub");
+        assert_eq!(funcdecl.vis, Visibility::Pub);
         assert_eq!(funcdecl.async_str, "async");
         assert_eq!(funcdecl.fn_name, "with_return");
         assert_eq!(funcdecl.fn_decl, "(_: String)");
@@ -249,7 +657,7 @@ mod tests {
         }".to_string();
         let funcdecl = FunctionDecl::from_string(test);
         assert_eq!(funcdecl.func_prologue, "#[some_macro]\n");
-        assert_eq!(funcdecl.pub_str, "pub");
+        assert_eq!(funcdecl.vis, Visibility::Pub);
         assert_eq!(funcdecl.async_str, "async");
         assert_eq!(funcdecl.fn_name, "with_return");
         assert_eq!(funcdecl.fn_decl, "(_: String)");
@@ -273,5 +681,212 @@ mod tests {
             {\nlet foo = \"\".to_string();\n            let bar = foo.trim();\n            bar\n}";
         assert_eq!(funcdecl.into_func_body(body), expected);
     }
-}
 
+    #[test]
+    fn test_func_render() {
+        let test = "pub fn with_return(_: String) -> String {
+            \"\".to_string()
+        }".to_string();
+        let funcdecl = FunctionDecl::from_string(test);
+        assert_eq!(funcdecl.render(), "pub fn with_return(_: String) -> String {\n\"\".to_string()\n}");
+    }
+
+    #[test]
+    fn test_func_body_with_braces_in_literals_and_comments() {
+        let test = r#"fn tricky() -> String {
+            // a stray } in a comment
+            let c = '}';
+            /* and { another } */
+            format!("{}}}", c)
+        }
+        fn next_item() {}"#.to_string();
+        let funcdecl = FunctionDecl::from_string(test);
+        assert_eq!(funcdecl.fn_name, "tricky");
+        assert_eq!(funcdecl.ret_decl, "String");
+        assert!(funcdecl.fn_body.starts_with("// a stray } in a comment"));
+        assert!(funcdecl.fn_body.ends_with(r#"format!("{}}}", c)"#));
+    }
+
+    #[test]
+    fn test_func_nested_blocks_and_raw_strings() {
+        let test = r##"fn nested(f: impl Fn(u8) -> u8) -> u8 {
+            let s = r#"}"#;
+            let g = |x| { if x > 0 { f(x) } else { 0 } };
+            g(s.len() as u8)
+        } fn other() { }"##.to_string();
+        let funcdecl = FunctionDecl::from_string(test);
+        assert_eq!(funcdecl.fn_decl, "(f: impl Fn(u8) -> u8)");
+        assert_eq!(funcdecl.ret_decl, "u8");
+        assert!(funcdecl.fn_body.ends_with("g(s.len() as u8)"));
+    }
+
+    #[test]
+    fn test_func_fn_in_doc_comment() {
+        let test = "/// Calls fn other\n#[attr(fn)]\nfn documented() {}".to_string();
+        let funcdecl = FunctionDecl::from_string(test);
+        assert_eq!(funcdecl.func_prologue, "/// Calls fn other\n#[attr(fn)]\n");
+        assert_eq!(funcdecl.fn_name, "documented");
+    }
+
+    #[test]
+    fn test_func_from_str() {
+        let funcdecl: FunctionDecl = "pub fn parsed(a: u8) -> u8 { a }".parse().unwrap();
+        assert_eq!(funcdecl.fn_name, "parsed");
+        assert_eq!(FunctionDecl::from("fn converted() {}").fn_name, "converted");
+
+        let err = "struct NotAFn;".parse::<FunctionDecl>().err().unwrap();
+        assert_eq!(err, ParseError::new(ParseErrorKind::MissingKeyword("fn"), 0));
+        let err = "fn unclosed() { let a = 1;".parse::<FunctionDecl>().err().unwrap();
+        assert_eq!(err.kind, ParseErrorKind::Unbalanced('{'));
+        assert_eq!(err.offset, 14);
+    }
+
+    #[test]
+    fn test_func_generics_and_where() {
+        let test = "pub fn generic<T: Into<String>, F: Fn(T) -> u8>(t: T, f: F) -> u8 where T: Clone {
+            f(t)
+        }".to_string();
+        let funcdecl = FunctionDecl::from_string(test);
+        assert_eq!(funcdecl.fn_name, "generic");
+        assert_eq!(funcdecl.generics, "<T: Into<String>, F: Fn(T) -> u8>");
+        assert_eq!(funcdecl.fn_decl, "(t: T, f: F)");
+        assert_eq!(funcdecl.ret_decl, "u8");
+        assert_eq!(funcdecl.where_clause, "where T: Clone");
+        assert_eq!(
+            funcdecl.func_prelude(),
+            "pub fn generic<T: Into<String>, F: Fn(T) -> u8>(t: T, f: F) -> u8 where T: Clone {"
+        );
+    }
+
+    #[test]
+    fn test_const_unsafe_extern_qualifiers() {
+        let funcdecl = FunctionDecl::from_string(
+            "/// FFI entry\npub const unsafe extern \"C\" fn foo(x: u8) -> u8 { x }".to_string(),
+        );
+        assert_eq!(funcdecl.func_prologue, "/// FFI entry\n");
+        assert_eq!(funcdecl.vis, Visibility::Pub);
+        assert_eq!(funcdecl.const_str, "const");
+        assert_eq!(funcdecl.async_str, "");
+        assert_eq!(funcdecl.unsafe_str, "unsafe");
+        assert_eq!(funcdecl.abi, "extern \"C\"");
+        assert_eq!(funcdecl.fn_name, "foo");
+        assert_eq!(
+            funcdecl.func_prelude(),
+            "/// FFI entry\npub const unsafe extern \"C\" fn foo(x: u8) -> u8 {"
+        );
+
+        let funcdecl = FunctionDecl::from_string("pub(crate) async unsafe fn bar() {}".to_string());
+        assert_eq!(funcdecl.vis, Visibility::PubCrate);
+        assert_eq!(funcdecl.const_str, "");
+        assert_eq!(funcdecl.async_str, "async");
+        assert_eq!(funcdecl.unsafe_str, "unsafe");
+        assert_eq!(funcdecl.abi, "");
+
+        let funcdecl = FunctionDecl::from_string("extern fn baz() {}".to_string());
+        assert_eq!(funcdecl.abi, "extern");
+        assert_eq!(funcdecl.func_prelude(), "extern fn baz() {");
+    }
+
+    #[test]
+    fn test_body_manipulation() {
+        let mut funcdecl = FunctionDecl::from_string("fn f(x: u8) -> u8 { x + 1 }".to_string());
+        funcdecl
+            .prepend_to_body("let start = now();")
+            .append_to_body("record(start);")
+            .wrap_body("let r = {", "};\nr");
+        let expected = "fn f(x: u8) -> u8 {
+let r = {
+    let __result = {
+        let start = now();
+        x + 1
+    };
+    record(start);
+    __result
+};
+r
+}";
+        assert_eq!(funcdecl.render(), expected);
+    }
+
+    #[test]
+    fn test_signature_transformations() {
+        let mut funcdecl = FunctionDecl::from_string("pub fn load(&self) { self.read() }".to_string());
+        funcdecl
+            .make_async()
+            .rename("load_with")
+            .wrap_return_type("anyhow::Result<{}>")
+            .add_param("ctx: &Context");
+        assert_eq!(
+            funcdecl.func_prelude(),
+            "pub async fn load_with(&self, ctx: &Context) -> anyhow::Result<()> {"
+        );
+        funcdecl.wrap_return_type("Box<{}>");
+        assert_eq!(funcdecl.ret_decl, "Box<anyhow::Result<()>>");
+    }
+
+    #[test]
+    fn test_delegate() {
+        let funcdecl = FunctionDecl::from_string(
+            "/// Doc\npub async fn run(&mut self, mut n: u8, (a, b): (u8, u8)) -> u8 { n += a; n + b }".to_string(),
+        );
+        let (inner, wrapper) = funcdecl.delegate("let _g = guard();\n{call}");
+        assert_eq!(
+            inner.func_prelude(),
+            "async fn __inner_run(&mut self, mut n: u8, (a, b): (u8, u8)) -> u8 {"
+        );
+        assert_eq!(inner.fn_body, funcdecl.fn_body);
+        let expected = "/// Doc
+pub async fn run(&mut self, n: u8, __arg2: (u8, u8)) -> u8 {
+let _g = guard();
+self.__inner_run(n, __arg2).await
+}";
+        assert_eq!(wrapper.render(), expected);
+    }
+
+    #[test]
+    fn test_lifetimes() {
+        let funcdecl = FunctionDecl::from_string(
+            "pub fn pick<'a, 'b: 'a, T>(x: &'a T, y: &'b [u8]) -> Option<&'a T> where T: 'b { let c = '{'; Some(x) }"
+                .to_string(),
+        );
+        assert_eq!(funcdecl.fn_name, "pick");
+        assert_eq!(funcdecl.fn_body, "let c = '{'; Some(x)");
+        assert_eq!(funcdecl.lifetimes(), vec!["'a", "'b"]);
+        assert_eq!(funcdecl.params()[1].lifetimes(), vec!["'b"]);
+        assert!(!funcdecl.has_elided_lifetimes());
+
+        let funcdecl = FunctionDecl::from_string("fn fmt(&self, f: &mut Formatter<'_>) -> Result { Ok(()) }".to_string());
+        let params = funcdecl.params();
+        assert!(params[0].has_elided_lifetime());
+        assert_eq!(params[1].lifetimes(), vec!["'_"]);
+        assert!(funcdecl.has_elided_lifetimes());
+        assert!(funcdecl.lifetimes().is_empty());
+
+        let funcdecl = FunctionDecl::from_string("fn get<'a>(&'a self) -> &'a str { &self.s }".to_string());
+        assert_eq!(funcdecl.params()[0].lifetimes(), vec!["'a"]);
+        assert!(!funcdecl.has_elided_lifetimes());
+    }
+
+    #[test]
+    fn test_parse_many() {
+        let inputs = ["pub async fn a(x: u8) -> u8 { x }", "fn b();", "/// Doc\nunsafe fn c() {}"];
+        let parsed = FunctionDecl::parse_many(&inputs);
+        assert_eq!(parsed.len(), 3);
+        let a = parsed[0].as_ref().unwrap();
+        assert_eq!((a.fn_name.as_str(), a.async_str.as_str(), a.fn_body.as_str()), ("a", "async", "x"));
+        assert_eq!(parsed[1].as_ref().unwrap_err().kind, ParseErrorKind::Expected('{'));
+        let c = parsed[2].as_ref().unwrap();
+        assert_eq!((c.func_prologue.as_str(), c.unsafe_str.as_str()), ("/// Doc\n", "unsafe"));
+        assert_eq!(c.render(), FunctionDecl::from_string(inputs[2].to_string()).render());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let funcdecl = FunctionDecl::from_string("/// Doc\npub async fn f(a: u8) -> u8 { a }".to_string());
+        let json = serde_json::to_string(&funcdecl).unwrap();
+        let back: FunctionDecl = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.render(), funcdecl.render());
+        assert_eq!(back.params(), funcdecl.params());
+    }
+}
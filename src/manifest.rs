@@ -0,0 +1,220 @@
+use proc_macro2::{Delimiter, TokenStream, TokenTree};
+
+use crate::attr_args::unquote;
+use crate::{parse_items, ConstDecl, Item, ItemKind, ParseError, ParseErrorKind};
+
+/// One item recorded in a [`Manifest`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ManifestEntry {
+    /// The item's name; `Type::method` for a method and `<Type as Trait>` for
+    /// a trait impl.
+    pub name: String,
+    /// The item's keyword, e.g. `fn`, `struct` or `impl`; see [`Manifest::kind_name`].
+    pub kind: String,
+    /// The name of the item the macro generated it from.
+    pub source: String,
+}
+
+/// A record of the items a macro emitted, for downstream tooling and other
+/// macros to discover the generated API.
+///
+/// A macro records what it generates with [`Manifest::record`] or
+/// [`Manifest::record_items`] and may emit the result as a doc-hidden const
+/// with [`Manifest::to_const`]. That const is a
+/// `&[(&str, &str, &str)]` of `(name, kind, source)` in recording order, a
+/// format that will not change; [`Manifest::from_const`] reads it back.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Manifest {
+    pub entries: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    pub fn new() -> Self {
+        Manifest::default()
+    }
+
+    pub fn record(&mut self, name: &str, kind: ItemKind, source: &str) -> &mut Self {
+        self.entries.push(ManifestEntry {
+            name: name.to_string(),
+            kind: Manifest::kind_name(kind).to_string(),
+            source: source.to_string(),
+        });
+        self
+    }
+
+    /// Record every named item in the generated `code`, e.g. a preset's
+    /// output, as generated from `source`. Methods are recorded one by one,
+    /// and `use` declarations and macro invocations are skipped.
+    pub fn record_items(&mut self, source: &str, code: &str) -> &mut Self {
+        for item in parse_items(code) {
+            for (name, kind) in item_names(&item) {
+                self.record(&name, kind, source);
+            }
+        }
+        self
+    }
+
+    /// The entries generated from `source`.
+    pub fn generated_from<'a>(&'a self, source: &'a str) -> impl Iterator<Item = &'a ManifestEntry> {
+        self.entries.iter().filter(move |e| e.source == source)
+    }
+
+    /// Append the entries of `other`.
+    pub fn extend(&mut self, other: Manifest) {
+        self.entries.extend(other.entries);
+    }
+
+    /// The keyword recorded for `kind`: `fn`, `struct`, `enum`, `union`,
+    /// `impl`, `trait`, `use`, `const`, `static`, `type`, `mod`,
+    /// `extern crate`, `extern`, `macro`, the registered tag of a custom item,
+    /// or `item`.
+    pub fn kind_name(kind: ItemKind) -> &'static str {
+        match kind {
+            ItemKind::Fn => "fn",
+            ItemKind::Struct => "struct",
+            ItemKind::Enum => "enum",
+            ItemKind::Union => "union",
+            ItemKind::Impl => "impl",
+            ItemKind::Trait => "trait",
+            ItemKind::Use => "use",
+            ItemKind::Const => "const",
+            ItemKind::Static => "static",
+            ItemKind::TypeAlias => "type",
+            ItemKind::Mod => "mod",
+            ItemKind::ExternCrate => "extern crate",
+            ItemKind::ExternBlock => "extern",
+            ItemKind::Macro => "macro",
+            ItemKind::Custom(tag) => tag,
+            ItemKind::Other => "item",
+        }
+    }
+
+    /// Render the manifest as a `#[doc(hidden)] pub const` named `name`.
+    pub fn to_const(&self, name: &str) -> String {
+        let rows: Vec<String> = self
+            .entries
+            .iter()
+            .map(|e| format!("    ({:?}, {:?}, {:?}),\n", e.name, e.kind, e.source))
+            .collect();
+        format!(
+            "#[doc(hidden)]\npub const {}: &[(&str, &str, &str)] = &[\n{}];",
+            name,
+            rows.concat()
+        )
+    }
+
+    /// Read back a const rendered by [`Manifest::to_const`].
+    pub fn from_const(in_str: &str) -> Result<Self, ParseError> {
+        let decl = ConstDecl::try_from_string(in_str)?;
+        let invalid = |msg: &str| ParseError::new(ParseErrorKind::Invalid(msg.to_string()), 0);
+        let value: TokenStream = decl.value.parse().map_err(|_| invalid("manifest is not valid tokens"))?;
+        let rows = value
+            .into_iter()
+            .find_map(|tt| match tt {
+                TokenTree::Group(g) if g.delimiter() == Delimiter::Bracket => Some(g.stream()),
+                _ => None,
+            })
+            .ok_or_else(|| invalid("expected a slice of manifest entries"))?;
+        let mut manifest = Manifest::new();
+        for tt in rows {
+            let row = match tt {
+                TokenTree::Group(g) if g.delimiter() == Delimiter::Parenthesis => g.stream(),
+                TokenTree::Punct(p) if p.as_char() == ',' => continue,
+                _ => return Err(invalid("expected a `(name, kind, source)` entry")),
+            };
+            let fields: Vec<String> = row
+                .into_iter()
+                .filter_map(|tt| match tt {
+                    TokenTree::Literal(l) => unquote(&l.to_string()),
+                    _ => None,
+                })
+                .collect();
+            match fields.as_slice() {
+                [name, kind, source] => manifest.entries.push(ManifestEntry {
+                    name: name.clone(),
+                    kind: kind.clone(),
+                    source: source.clone(),
+                }),
+                _ => return Err(invalid("expected a `(name, kind, source)` entry")),
+            }
+        }
+        Ok(manifest)
+    }
+}
+
+/// The names `item` defines, as recorded by [`Manifest::record_items`].
+fn item_names(item: &Item) -> Vec<(String, ItemKind)> {
+    let name = |name: &str| vec![(name.to_string(), item.kind())];
+    match item {
+        Item::Fn(d) => name(&d.fn_name),
+        Item::Struct(d) => name(&d.name),
+        Item::Enum(d) => name(&d.name),
+        Item::Trait(d) => name(&d.name),
+        Item::Const(d) => name(&d.name),
+        Item::Static(d) => name(&d.name),
+        Item::TypeAlias(d) => name(&d.name),
+        Item::Mod(d) => name(&d.name),
+        Item::Impl(d) => {
+            let mut names = Vec::new();
+            if d.is_trait_impl() {
+                names.push((format!("<{} as {}>", d.self_ty, d.trait_name), ItemKind::Impl));
+            }
+            names.extend(d.methods().map(|m| (format!("{}::{}", d.self_ty, m.fn_name), ItemKind::Fn)));
+            names
+        }
+        Item::ExternBlock(d) => d.functions().map(|f| (f.fn_name.clone(), ItemKind::Fn)).collect(),
+        Item::Other(..) => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ItemKind, Manifest};
+
+    #[test]
+    fn test_manifest() {
+        let mut manifest = Manifest::new();
+        manifest
+            .record_items(
+                "get_user",
+                "pub async fn get_user_batch(ids: Vec<u32>) -> Vec<User> { todo!() }
+use std::fmt;
+struct UserKey(u32);
+impl UserKey { fn new(id: u32) -> Self { UserKey(id) } }
+impl fmt::Display for UserKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { write!(f, \"{}\", self.0) }
+}
+inventory::submit! { Route }",
+            )
+            .record("USER_ROUTE", ItemKind::Const, "routes");
+        let names: Vec<(&str, &str)> =
+            manifest.entries.iter().map(|e| (e.name.as_str(), e.kind.as_str())).collect();
+        assert_eq!(
+            names,
+            [
+                ("get_user_batch", "fn"),
+                ("UserKey", "struct"),
+                ("UserKey::new", "fn"),
+                ("<UserKey as fmt::Display>", "impl"),
+                ("UserKey::fmt", "fn"),
+                ("USER_ROUTE", "const"),
+            ]
+        );
+        assert_eq!(manifest.generated_from("routes").count(), 1);
+
+        let rendered = manifest.to_const("__USER_API");
+        assert!(rendered.starts_with(
+            "#[doc(hidden)]\npub const __USER_API: &[(&str, &str, &str)] = &[\n    (\"get_user_batch\", \"fn\", \"get_user\"),\n"
+        ));
+        assert!(crate::verify(&rendered).is_ok());
+        assert_eq!(Manifest::from_const(&rendered).unwrap(), manifest);
+        assert_eq!(Manifest::from_const("const A: &[(&str, &str, &str)] = &[];").unwrap(), Manifest::new());
+        assert!(Manifest::from_const("const A: &[&str] = &[\"x\"];").is_err());
+        assert!(Manifest::from_const("static A: u8 = 1;").is_err());
+        let mut unicode = Manifest::new();
+        unicode.record("größe", ItemKind::Fn, "\u{1b}[ansi]\u{200b}");
+        assert_eq!(Manifest::from_const(&unicode.to_const("M")).unwrap(), unicode);
+    }
+}
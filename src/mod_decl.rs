@@ -0,0 +1,416 @@
+use crate::item_parser::{custom_kind, item_parser, KindTag};
+use crate::scan::{find_close, find_keyword, find_top_level, is_ident_char, split_items, split_leading_attrs, split_visibility};
+use crate::{
+    indent, sealed, ConstDecl, Decl, EnumDecl, ExternBlockDecl, FunctionDecl, ImplBlockDecl, ParseError, ParseErrorKind, ParseOptions,
+    StaticDecl, StructDecl, TraitDecl, TypeAliasDecl, Visibility,
+};
+
+/// What kind of item a piece of source is, judged from its leading keywords.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ItemKind {
+    Fn,
+    Struct,
+    Enum,
+    Union,
+    Impl,
+    Trait,
+    Use,
+    Const,
+    Static,
+    TypeAlias,
+    Mod,
+    ExternCrate,
+    ExternBlock,
+    /// A macro invocation or `macro_rules!` definition.
+    Macro,
+    /// An item introduced by a keyword registered with
+    /// [`crate::register_item_parser`].
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "crate::item_parser::deserialize_tag"))]
+    Custom(KindTag),
+    Other,
+}
+
+impl ItemKind {
+    /// Classify an item from its source, skipping attributes, visibility and
+    /// qualifiers such as `unsafe` or `extern "C"`.
+    pub fn sniff(src: &str) -> Self {
+        let (_, rest) = split_leading_attrs(src);
+        let (_, rest) = split_visibility(rest);
+        let words: Vec<&str> = rest.split_whitespace().collect();
+        let mut idx = 0;
+        while let Some(word) = words.get(idx) {
+            let ident_len = word.find(|c: char| !is_ident_char(c)).unwrap_or(word.len());
+            let ident = &word[..ident_len];
+            if word[ident_len..].starts_with('!') {
+                return ItemKind::Macro;
+            }
+            let next = words.get(idx + 1).copied().unwrap_or("");
+            idx += 1;
+            return match ident {
+                "unsafe" | "async" | "default" | "auto" => continue,
+                "const" if matches!(next, "fn" | "unsafe" | "async" | "extern") => continue,
+                "extern" if next == "crate" => ItemKind::ExternCrate,
+                "extern" => {
+                    let (abi, after) = if next.starts_with('"') {
+                        idx += 1;
+                        (next, words.get(idx).copied().unwrap_or(""))
+                    } else {
+                        ("", next)
+                    };
+                    if abi.contains('{') || after.starts_with('{') {
+                        ItemKind::ExternBlock
+                    } else {
+                        continue;
+                    }
+                }
+                "fn" => ItemKind::Fn,
+                "struct" => ItemKind::Struct,
+                "enum" => ItemKind::Enum,
+                "union" => ItemKind::Union,
+                "impl" => ItemKind::Impl,
+                "trait" => ItemKind::Trait,
+                "use" => ItemKind::Use,
+                "const" => ItemKind::Const,
+                "static" => ItemKind::Static,
+                "type" => ItemKind::TypeAlias,
+                "mod" => ItemKind::Mod,
+                _ => custom_kind(ident, &word[ident_len..]).unwrap_or(ItemKind::Other),
+            };
+        }
+        ItemKind::Other
+    }
+}
+
+/// One top-level item of a module.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[allow(clippy::large_enum_variant)]
+pub enum Item {
+    Fn(FunctionDecl),
+    Struct(StructDecl),
+    Enum(EnumDecl),
+    Impl(ImplBlockDecl),
+    Trait(TraitDecl),
+    Const(ConstDecl),
+    Static(StaticDecl),
+    TypeAlias(TypeAliasDecl),
+    ExternBlock(ExternBlockDecl),
+    /// An inline module; `mod foo;` is kept as [`Item::Other`].
+    Mod(ModDecl),
+    /// Any other item, or one that failed to parse, kept verbatim.
+    Other(ItemKind, String),
+}
+
+impl Item {
+    /// Parse one item, falling back to [`Item::Other`] if its kind has no
+    /// decl type or it does not parse as one.
+    pub fn from_string(in_str: &str) -> Self {
+        Self::from_string_with(in_str, &ParseOptions::default())
+    }
+
+    /// Like [`Item::from_string`], reporting items kept verbatim as
+    /// configured in `options`.
+    pub fn from_string_with(in_str: &str, options: &ParseOptions) -> Self {
+        let kind = ItemKind::sniff(in_str);
+        let parsed = match Item::parse_as(kind, in_str, options) {
+            Some(parsed) => parsed,
+            None => {
+                if kind == ItemKind::Other {
+                    options.report_unsupported(kind, "unrecognised item", in_str);
+                }
+                return Item::Other(kind, in_str.to_string());
+            }
+        };
+        parsed.unwrap_or_else(|e| {
+            // `mod foo;` is expected to stay verbatim.
+            if kind != ItemKind::Mod || in_str.contains('{') {
+                options.report_unsupported(kind, &e.to_string(), in_str);
+            }
+            Item::Other(kind, in_str.to_string())
+        })
+    }
+
+    /// Parse `in_str` as the decl type for `kind`, or `None` if it has none.
+    pub(crate) fn parse_as(kind: ItemKind, in_str: &str, options: &ParseOptions) -> Option<Result<Self, ParseError>> {
+        Some(match kind {
+            ItemKind::Fn => in_str.parse().map(Item::Fn),
+            ItemKind::Struct => in_str.parse().map(Item::Struct),
+            ItemKind::Enum => in_str.parse().map(Item::Enum),
+            ItemKind::Impl => in_str.parse().map(Item::Impl),
+            ItemKind::Trait => in_str.parse().map(Item::Trait),
+            ItemKind::Const => in_str.parse().map(Item::Const),
+            ItemKind::Static => in_str.parse().map(Item::Static),
+            ItemKind::TypeAlias => in_str.parse().map(Item::TypeAlias),
+            ItemKind::ExternBlock => in_str.parse().map(Item::ExternBlock),
+            ItemKind::Mod => ModDecl::try_from_string_with(in_str, options).map(Item::Mod),
+            ItemKind::Custom(tag) => item_parser(tag)?(in_str),
+            _ => return None,
+        })
+    }
+
+    pub fn kind(&self) -> ItemKind {
+        match self {
+            Item::Fn(_) => ItemKind::Fn,
+            Item::Struct(_) => ItemKind::Struct,
+            Item::Enum(_) => ItemKind::Enum,
+            Item::Impl(_) => ItemKind::Impl,
+            Item::Trait(_) => ItemKind::Trait,
+            Item::Const(_) => ItemKind::Const,
+            Item::Static(_) => ItemKind::Static,
+            Item::TypeAlias(_) => ItemKind::TypeAlias,
+            Item::ExternBlock(_) => ItemKind::ExternBlock,
+            Item::Mod(_) => ItemKind::Mod,
+            Item::Other(kind, _) => *kind,
+        }
+    }
+
+    pub fn render(&self) -> String {
+        match self {
+            Item::Fn(d) => d.render(),
+            Item::Struct(d) => d.render(),
+            Item::Enum(d) => d.render(),
+            Item::Impl(d) => d.render(),
+            Item::Trait(d) => d.render(),
+            Item::Const(d) => d.render(),
+            Item::Static(d) => d.render(),
+            Item::TypeAlias(d) => d.render(),
+            Item::ExternBlock(d) => d.render(),
+            Item::Mod(d) => d.render(),
+            Item::Other(_, s) => s.clone(),
+        }
+    }
+}
+
+/// Split a sequence of items (a module body or a whole file) and parse each.
+pub fn parse_items(src: &str) -> Vec<Item> {
+    parse_items_with(src, &ParseOptions::default())
+}
+
+/// Like [`parse_items`], reporting items kept verbatim as configured in
+/// `options`.
+pub fn parse_items_with(src: &str, options: &ParseOptions) -> Vec<Item> {
+    split_items(src).into_iter().map(|item| Item::from_string_with(item, options)).collect()
+}
+
+/// A parsed inline `mod name { ... }`.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ModDecl {
+    pub prologue: String,
+    pub vis: Visibility,
+    pub name: String,
+    /// Inner attributes and comments at the top of the body, e.g. `#![allow(x)]`.
+    pub inner_attrs: String,
+    pub items: Vec<Item>,
+}
+
+impl ModDecl {
+    /// Parse an inline module, panicking if the input is not one.
+    pub fn from_string(in_str: String) -> Self {
+        Self::try_from_string(&in_str).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_from_string(in_str: &str) -> Result<Self, ParseError> {
+        Self::try_from_string_with(in_str, &ParseOptions::default())
+    }
+
+    /// Like [`ModDecl::try_from_string`], reporting nested items kept
+    /// verbatim as configured in `options`.
+    pub fn try_from_string_with(in_str: &str, options: &ParseOptions) -> Result<Self, ParseError> {
+        let mod_idx =
+            find_keyword(in_str, "mod").ok_or_else(|| ParseError::new(ParseErrorKind::MissingKeyword("mod"), 0))?;
+        let (prologue, head) = split_leading_attrs(&in_str[..mod_idx]);
+        let (vis, _) = split_visibility(head);
+
+        let after_mod = mod_idx + 3;
+        let body_idx = after_mod
+            + find_top_level(&in_str[after_mod..], '{', false)
+                .ok_or_else(|| ParseError::new(ParseErrorKind::Expected('{'), after_mod))?;
+        let body_end = find_close(in_str, body_idx)
+            .ok_or_else(|| ParseError::new(ParseErrorKind::Unbalanced('{'), body_idx))?;
+
+        let body = &in_str[body_idx + 1..body_end];
+        let inner_len = inner_attrs_len(body);
+        Ok(ModDecl {
+            prologue: prologue.to_string(),
+            vis: Visibility::from_string(vis),
+            name: in_str[after_mod..body_idx].trim().to_string(),
+            inner_attrs: body[..inner_len].trim().to_string(),
+            items: parse_items_with(&body[inner_len..], options),
+        })
+    }
+
+    pub fn functions(&self) -> impl Iterator<Item = &FunctionDecl> {
+        self.items.iter().filter_map(|i| match i {
+            Item::Fn(f) => Some(f),
+            _ => None,
+        })
+    }
+
+    pub fn functions_mut(&mut self) -> impl Iterator<Item = &mut FunctionDecl> {
+        self.items.iter_mut().filter_map(|i| match i {
+            Item::Fn(f) => Some(f),
+            _ => None,
+        })
+    }
+
+    pub fn structs(&self) -> impl Iterator<Item = &StructDecl> {
+        self.items.iter().filter_map(|i| match i {
+            Item::Struct(s) => Some(s),
+            _ => None,
+        })
+    }
+
+    /// Replace every item with the result of `f`, keeping the order.
+    pub fn map_items<F: FnMut(Item) -> Item>(mut self, f: F) -> Self {
+        self.items = self.items.into_iter().map(f).collect();
+        self
+    }
+}
+
+/// Length of the inner attributes and comments at the start of a module body.
+pub(crate) fn inner_attrs_len(body: &str) -> usize {
+    let mut idx = 0;
+    loop {
+        let rest = &body[idx..];
+        let trimmed = rest.trim_start();
+        let skipped = rest.len() - trimmed.len();
+        let is_inner_attr = trimmed
+            .strip_prefix('#')
+            .map(|r| r.trim_start().starts_with('!'))
+            .unwrap_or(false);
+        let is_inner_doc = trimmed.starts_with("//!") || trimmed.starts_with("/*!");
+        if !is_inner_attr && !is_inner_doc {
+            return idx;
+        }
+        // Take one attribute or comment at a time so that a following outer
+        // attribute stays with its item.
+        let first_len = if trimmed.starts_with("//!") {
+            trimmed.find('\n').map(|i| i + 1).unwrap_or(trimmed.len())
+        } else if is_inner_doc {
+            trimmed.find("*/").map(|i| i + 2).unwrap_or(trimmed.len())
+        } else {
+            match trimmed.find('[').and_then(|open| find_close(trimmed, open)) {
+                Some(close) => close + 1,
+                None => return idx,
+            }
+        };
+        idx += skipped + first_len;
+    }
+}
+
+impl std::str::FromStr for ModDecl {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, ParseError> {
+        Self::try_from_string(s)
+    }
+}
+
+impl sealed::Sealed for ModDecl {}
+
+impl Decl for ModDecl {
+    fn render(&self) -> String {
+        let mut body: Vec<String> = Vec::new();
+        if !self.inner_attrs.is_empty() {
+            body.push(indent(&self.inner_attrs));
+        }
+        body.extend(self.items.iter().map(|i| indent(&i.render())));
+        format!(
+            "{}{}{}mod {} {{\n{}\n}}",
+            self.prologue,
+            if self.prologue.is_empty() { "" } else { "\n" },
+            self.vis.prefix(),
+            self.name,
+            body.join("\n\n")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mod_decl::{Item, ItemKind, ModDecl};
+    use crate::Decl;
+
+    #[test]
+    fn test_sniff_item_kind() {
+        assert_eq!(ItemKind::sniff("#[a] pub(crate) const unsafe extern \"C\" fn f() {}"), ItemKind::Fn);
+        assert_eq!(ItemKind::sniff("const X: u8 = 1;"), ItemKind::Const);
+        assert_eq!(ItemKind::sniff("unsafe impl<T> Send for A<T> {}"), ItemKind::Impl);
+        assert_eq!(ItemKind::sniff("extern \"C\" { fn abs(x: i32) -> i32; }"), ItemKind::ExternBlock);
+        assert_eq!(ItemKind::sniff("extern crate alloc;"), ItemKind::ExternCrate);
+        assert_eq!(ItemKind::sniff("macro_rules! m { () => {} }"), ItemKind::Macro);
+        assert_eq!(ItemKind::sniff("lazy_static! { static ref A: u8 = 1; }"), ItemKind::Macro);
+        assert_eq!(ItemKind::sniff("pub type Id = u64;"), ItemKind::TypeAlias);
+        assert_eq!(ItemKind::sniff("mod inner;"), ItemKind::Mod);
+    }
+
+    const INPUT: &str = "/// Handlers
+    pub mod handlers {
+        #![allow(dead_code)]
+        use std::fmt;
+
+        const LIMIT: usize = 3;
+
+        #[derive(Debug)]
+        pub struct Req { id: u64 }
+
+        pub fn get(r: Req) -> u64 { r.id }
+
+        impl Req { fn new() -> Self { Req { id: 0 } } }
+
+        mod nested { fn deep() {} }
+    }";
+
+    #[test]
+    fn test_mod_parse() {
+        let decl: ModDecl = INPUT.parse().unwrap();
+        assert_eq!(decl.prologue, "/// Handlers");
+        assert_eq!(decl.name, "handlers");
+        assert_eq!(decl.inner_attrs, "#![allow(dead_code)]");
+        assert_eq!(
+            decl.items.iter().map(|i| i.kind()).collect::<Vec<ItemKind>>(),
+            vec![
+                ItemKind::Use,
+                ItemKind::Const,
+                ItemKind::Struct,
+                ItemKind::Fn,
+                ItemKind::Impl,
+                ItemKind::Mod
+            ]
+        );
+        assert_eq!(decl.functions().map(|f| f.fn_name.as_str()).collect::<Vec<&str>>(), vec!["get"]);
+        assert_eq!(decl.structs().next().unwrap().prologue, "#[derive(Debug)]");
+        assert!(matches!(&decl.items[1], Item::Const(c) if c.name == "LIMIT" && c.value == "3"));
+        assert!(matches!(&decl.items[5], Item::Mod(m) if m.functions().count() == 1));
+    }
+
+    #[test]
+    fn test_mod_rebuild() {
+        let mut decl = ModDecl::from_string("mod m { //! Docs\n use a::b; fn f() { 1 } }".to_string());
+        for f in decl.functions_mut() {
+            f.prepend_to_body("trace();");
+        }
+        let expected = "mod m {
+    //! Docs
+
+    use a::b;
+
+    fn f() {
+    trace();
+    1
+    }
+}";
+        assert_eq!(decl.render(), expected);
+    }
+
+    #[test]
+    fn test_mod_grouped_imports() {
+        let src = "mod m {\n    use a::{b, c};\n\n    pub use d::{self, e::{f, g}};\n\n    extern crate h;\n\n    struct S;\n}";
+        let decl = ModDecl::from_string(src.to_string());
+        assert_eq!(decl.items.len(), 4);
+        assert_eq!(decl.items[0].render(), "use a::{b, c};");
+        assert_eq!(decl.render(), src);
+    }
+}
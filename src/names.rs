@@ -0,0 +1,57 @@
+use std::collections::HashSet;
+
+/// Tracks the names already used in a scope so generated items don't clash.
+#[derive(Clone, Debug, Default)]
+pub struct NameRegistry {
+    used: HashSet<String>,
+}
+
+impl NameRegistry {
+    pub fn new() -> Self {
+        NameRegistry::default()
+    }
+
+    /// A registry with `names` already taken, e.g. the scope's existing items.
+    pub fn with_reserved<'a, I: IntoIterator<Item = &'a str>>(names: I) -> Self {
+        NameRegistry {
+            used: names.into_iter().map(|n| n.to_string()).collect(),
+        }
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.used.contains(name)
+    }
+
+    /// Mark `name` as used, returning `false` if it already was.
+    pub fn reserve(&mut self, name: &str) -> bool {
+        self.used.insert(name.to_string())
+    }
+
+    /// Take `base` if it is free, otherwise the first free `base_2`, `base_3`, ...
+    pub fn claim(&mut self, base: &str) -> String {
+        let mut name = base.to_string();
+        let mut n = 2;
+        while self.used.contains(&name) {
+            name = format!("{}_{}", base, n);
+            n += 1;
+        }
+        self.used.insert(name.clone());
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::names::NameRegistry;
+
+    #[test]
+    fn test_claim_names() {
+        let mut names = NameRegistry::with_reserved(vec!["run"]);
+        assert_eq!(names.claim("run"), "run_2");
+        assert_eq!(names.claim("run"), "run_3");
+        assert_eq!(names.claim("stop"), "stop");
+        assert!(names.contains("stop"));
+        assert!(!names.reserve("stop"));
+        assert!(names.reserve("go"));
+    }
+}
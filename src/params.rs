@@ -0,0 +1,339 @@
+use crate::scan::{find_top_level, is_ident_char, split_leading_attrs, split_top_level};
+use crate::types::{has_elided_lifetime, lifetimes_in};
+
+/// A single parameter from a function's parameter list, e.g. `mut a: String`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Param {
+    /// Attributes preceding the parameter, e.g. `#[redact]`; empty if none.
+    pub prologue: String,
+    /// `true` if the binding is declared with `ref`.
+    pub by_ref: bool,
+    /// `true` if the binding is declared with `mut`.
+    pub mutable: bool,
+    /// The bound identifier, or the full pattern text when `is_pattern` is set.
+    pub name: String,
+    /// `true` if `name` is a destructuring/wildcard pattern rather than an identifier.
+    pub is_pattern: bool,
+    /// The declared type, empty for an untyped parameter.
+    pub ty: String,
+}
+
+/// The kind of `self` receiver a method takes.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Receiver {
+    /// `self` or `mut self`.
+    Value { mutable: bool },
+    /// `&self` or `&'a self`.
+    Ref,
+    /// `&mut self` or `&'a mut self`.
+    RefMut,
+    /// An explicitly typed receiver such as `self: Arc<Self>`.
+    Typed(String),
+}
+
+impl Param {
+    pub fn new(name: &str, ty: &str) -> Self {
+        Param {
+            prologue: String::new(),
+            by_ref: false,
+            mutable: false,
+            name: name.to_string(),
+            is_pattern: !is_ident(name),
+            ty: ty.to_string(),
+        }
+    }
+
+    pub fn from_string(in_str: &str) -> Self {
+        let (prologue, in_str) = split_leading_attrs(in_str);
+        let in_str = in_str.trim();
+        let (pat, ty) = match find_top_level(in_str, ':', true) {
+            Some(idx) => (in_str[..idx].trim(), in_str[idx + 1..].trim()),
+            None => (in_str, ""),
+        };
+
+        let mut rest = pat;
+        let by_ref = strip_keyword(&mut rest, "ref");
+        let mutable = strip_keyword(&mut rest, "mut");
+
+        Param {
+            prologue: prologue.to_string(),
+            by_ref,
+            mutable,
+            name: rest.to_string(),
+            is_pattern: !is_ident(rest),
+            ty: ty.to_string(),
+        }
+    }
+
+    /// The receiver kind if this parameter is a `self` receiver.
+    pub fn receiver(&self) -> Option<Receiver> {
+        if self.name == "self" {
+            if self.ty.is_empty() {
+                Some(Receiver::Value {
+                    mutable: self.mutable,
+                })
+            } else {
+                Some(Receiver::Typed(self.ty.clone()))
+            }
+        } else if self.name.starts_with('&') && self.name.ends_with("self") {
+            let inner = self.name[1..self.name.len() - 4].trim();
+            // Drop an explicit lifetime, e.g. `&'a mut self`.
+            let inner = match inner.strip_prefix('\'') {
+                Some(lt) => lt.trim_start_matches(|c: char| c == '_' || c.is_alphanumeric()).trim(),
+                None => inner,
+            };
+            match inner {
+                "" => Some(Receiver::Ref),
+                "mut" => Some(Receiver::RefMut),
+                _ => None,
+            }
+        } else {
+            None
+        }
+    }
+
+    pub fn is_receiver(&self) -> bool {
+        self.receiver().is_some()
+    }
+
+    /// Whether this is the C-variadic `...` of a foreign function, possibly
+    /// named as in `args: ...`.
+    pub fn is_variadic(&self) -> bool {
+        self.ty == "..." || (self.ty.is_empty() && self.name == "...")
+    }
+
+    /// The expression passing this parameter's value on in a call: the name
+    /// of a plain binding, or the pattern rebuilt as an expression with `mut`
+    /// bindings dropped, so `(mut x, y): (u8, u8)` gives `(x, y)`. `None` if
+    /// the pattern discards part of the value with `_` or `..`, or borrows
+    /// from it with a `&` pattern or a `ref` binding, as the rebuilt value
+    /// would not have the parameter's type.
+    pub fn forward_arg(&self) -> Option<String> {
+        if !self.is_pattern || self.is_receiver() {
+            return Some(self.name.clone());
+        }
+        // `whole @ (a, b)` binds the value as a whole.
+        let pat = match find_top_level(&self.name, '@', true) {
+            Some(idx) => self.name[..idx].trim(),
+            None => self.name.as_str(),
+        };
+        let mut out = String::with_capacity(pat.len());
+        let mut rest = pat;
+        while let Some(c) = rest.chars().next() {
+            if (c == '.' && rest.starts_with("..")) || c == '&' {
+                return None;
+            }
+            if !is_ident_char(c) {
+                out.push(c);
+                rest = &rest[c.len_utf8()..];
+                continue;
+            }
+            let len = rest.find(|c: char| !is_ident_char(c)).unwrap_or(rest.len());
+            let word = &rest[..len];
+            rest = &rest[len..];
+            if word == "_" || word == "ref" {
+                return None;
+            } else if word == "mut" {
+                rest = rest.trim_start();
+            } else {
+                out.push_str(word);
+            }
+        }
+        Some(out)
+    }
+
+    /// The lifetimes named in the parameter's type, or in a `&'a self` receiver.
+    pub fn lifetimes(&self) -> Vec<String> {
+        lifetimes_in(self.type_or_receiver())
+    }
+
+    /// Whether the parameter borrows with an inferred lifetime, e.g. `&self`
+    /// or `f: &mut Formatter<'_>`.
+    pub fn has_elided_lifetime(&self) -> bool {
+        has_elided_lifetime(self.type_or_receiver())
+    }
+
+    fn type_or_receiver(&self) -> &str {
+        if self.ty.is_empty() && self.is_receiver() {
+            &self.name
+        } else {
+            &self.ty
+        }
+    }
+
+    /// The pattern part of the parameter, including any `ref`/`mut` modifiers.
+    pub fn pattern(&self) -> String {
+        format!(
+            "{}{}{}",
+            if self.by_ref { "ref " } else { "" },
+            if self.mutable { "mut " } else { "" },
+            self.name
+        )
+    }
+}
+
+impl std::fmt::Display for Param {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if !self.prologue.is_empty() {
+            write!(f, "{} ", self.prologue)?;
+        }
+        if self.ty.is_empty() {
+            write!(f, "{}", self.pattern())
+        } else {
+            write!(f, "{}: {}", self.pattern(), self.ty)
+        }
+    }
+}
+
+/// Parse a parenthesized parameter list, e.g. `(a: String, b: &mut Foo)`.
+pub fn parse_params(fn_decl: &str) -> Vec<Param> {
+    let inner = fn_decl.trim();
+    let inner = inner.strip_prefix('(').unwrap_or(inner);
+    let inner = inner.strip_suffix(')').unwrap_or(inner);
+    split_top_level(inner, ',', true)
+        .into_iter()
+        .filter(|p| !p.trim().is_empty())
+        .map(Param::from_string)
+        .collect()
+}
+
+/// Render a parameter list back into its parenthesized form.
+pub fn render_params(params: &[Param]) -> String {
+    format!(
+        "({})",
+        params
+            .iter()
+            .map(|p| p.to_string())
+            .collect::<Vec<String>>()
+            .join(", ")
+    )
+}
+
+pub(crate) fn is_ident(s: &str) -> bool {
+    let s = s.strip_prefix("r#").unwrap_or(s);
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) if c == '_' || c.is_alphabetic() => {
+            s != "_" && chars.all(|c| c == '_' || c.is_alphanumeric())
+        }
+        _ => false,
+    }
+}
+
+fn strip_keyword(s: &mut &str, kw: &str) -> bool {
+    match s.strip_prefix(kw) {
+        Some(rest) if rest.starts_with(char::is_whitespace) => {
+            *s = rest.trim_start();
+            true
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::params::{parse_params, render_params, Param, Receiver};
+    use crate::FunctionDecl;
+
+    #[test]
+    fn test_params_simple() {
+        let params = parse_params("(a: String, b: &mut Foo)");
+        assert_eq!(params.len(), 2);
+        assert_eq!(params[0].name, "a");
+        assert_eq!(params[0].ty, "String");
+        assert!(!params[0].mutable);
+        assert_eq!(params[1].name, "b");
+        assert_eq!(params[1].ty, "&mut Foo");
+    }
+
+    #[test]
+    fn test_params_empty() {
+        assert!(parse_params("()").is_empty());
+    }
+
+    #[test]
+    fn test_params_mut_and_patterns() {
+        let params = parse_params("(mut a: String, (x, y): (u8, u8), ref b: &Foo, _: u8)");
+        assert_eq!(params.len(), 4);
+        assert!(params[0].mutable);
+        assert_eq!(params[0].name, "a");
+        assert!(params[1].is_pattern);
+        assert_eq!(params[1].name, "(x, y)");
+        assert_eq!(params[1].ty, "(u8, u8)");
+        assert!(params[2].by_ref);
+        assert_eq!(params[2].name, "b");
+        assert!(params[3].is_pattern);
+    }
+
+    #[test]
+    fn test_params_nested_types() {
+        let params = parse_params("(m: HashMap<String, Vec<u8>>, f: impl Fn(u8, u8) -> u8, p: std::path::PathBuf)");
+        assert_eq!(params.len(), 3);
+        assert_eq!(params[0].ty, "HashMap<String, Vec<u8>>");
+        assert_eq!(params[1].ty, "impl Fn(u8, u8) -> u8");
+        assert_eq!(params[2].ty, "std::path::PathBuf");
+    }
+
+    #[test]
+    fn test_params_rebuild() {
+        let mut funcdecl = FunctionDecl::from_string("fn with_params(a: String, b: u32) {}".to_string());
+        let mut params = funcdecl.params();
+        params[0].name = "renamed".to_string();
+        params.remove(1);
+        params.push(Param::new("ctx", "&Context"));
+        funcdecl.set_params(&params);
+        assert_eq!(funcdecl.fn_decl, "(renamed: String, ctx: &Context)");
+        assert_eq!(render_params(&[]), "()");
+    }
+
+    #[test]
+    fn test_receivers() {
+        let recv = |s: &str| FunctionDecl::from_string(format!("fn method({}) {{}}", s)).receiver();
+        assert_eq!(recv(""), None);
+        assert_eq!(recv("a: u8"), None);
+        assert_eq!(recv("self"), Some(Receiver::Value { mutable: false }));
+        assert_eq!(recv("mut self, a: u8"), Some(Receiver::Value { mutable: true }));
+        assert_eq!(recv("&self"), Some(Receiver::Ref));
+        assert_eq!(recv("&'a self"), Some(Receiver::Ref));
+        assert_eq!(recv("&mut self"), Some(Receiver::RefMut));
+        assert_eq!(recv("&'a mut self"), Some(Receiver::RefMut));
+        assert_eq!(recv("self: Arc<Self>"), Some(Receiver::Typed("Arc<Self>".to_string())));
+        assert_eq!(recv("&selfish: u8"), None);
+    }
+
+    #[test]
+    fn test_forward_args() {
+        let args = |s: &str| FunctionDecl::from_string(format!("fn f({}) {{}}", s)).forward_args();
+        assert_eq!(
+            args("mut a: String, (x, mut y): (u8, u8), ref b: &Foo").as_deref(),
+            Some("a, (x, y), b")
+        );
+        assert_eq!(args("&self, Point { x, y }: Point").as_deref(), Some("Point { x, y }"));
+        assert_eq!(args("mut self, all @ [a, b]: [u8; 2]").as_deref(), Some("all"));
+        assert_eq!(args("&self, Point { x, y: ref py }: Point"), None);
+        assert_eq!(args("&mut n: &mut u8"), None);
+        assert_eq!(args("(&a, b): (&u8, u8)"), None);
+        assert_eq!(args("self: Box<Self>").as_deref(), Some(""));
+        assert_eq!(args("").as_deref(), Some(""));
+        assert_eq!(args("a: u8, _: u8"), None);
+        assert_eq!(args("S { a, .. }: S"), None);
+        let method = FunctionDecl::from_string("fn m(&self, (mut a, b): (u8, u8)) {}".to_string());
+        assert_eq!(method.delegate_call("inner").as_deref(), Some("self.inner((a, b))"));
+    }
+
+    #[test]
+    fn test_delegate_call() {
+        let method = FunctionDecl::from_string("fn method(&self, a: u8, mut b: String) {}".to_string());
+        assert_eq!(method.delegate_call("inner").as_deref(), Some("self.inner(a, b)"));
+        let free = FunctionDecl::from_string("fn free(a: u8) {}".to_string());
+        assert_eq!(free.delegate_call("inner").as_deref(), Some("inner(a)"));
+        let patterns = FunctionDecl::from_string("fn f(_: u8, (a, b): (u8, u8)) {}".to_string());
+        assert_eq!(patterns.delegate_call("inner"), None);
+        let mut bound = patterns.clone();
+        bound.set_params(&patterns.forwardable_params());
+        assert_eq!(bound.forwarding_call("inner"), "inner(__arg0, __arg1)");
+        assert_eq!(bound.delegate_call("inner").as_deref(), Some("inner(__arg0, __arg1)"));
+    }
+}
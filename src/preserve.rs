@@ -0,0 +1,175 @@
+use crate::{FunctionDecl, ParseError, ParseErrorKind, QUALIFIER_PATTERN};
+
+/// The original text of a function parsed with
+/// [`FunctionDecl::parse_preserving`], so that rendering can reuse the
+/// parts a macro left unchanged byte for byte.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SourceText {
+    text: String,
+    sig_start: usize,
+    body_open: usize,
+    body_close: usize,
+    /// The field values as parsed, to tell which parts were edited.
+    prologue: String,
+    signature: String,
+    body: String,
+}
+
+impl SourceText {
+    /// The text the function was parsed from.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Byte offset of the signature, after any attributes and doc comments.
+    pub fn signature_offset(&self) -> usize {
+        self.sig_start
+    }
+
+    /// Byte range of the original body, between its braces.
+    pub fn body_range(&self) -> (usize, usize) {
+        (self.body_open + 1, self.body_close)
+    }
+
+    /// Render `decl`, reusing the original text for the prologue, signature
+    /// and body wherever they are unchanged. A body that still contains the
+    /// original one unmodified, e.g. after [`FunctionDecl::prepend_to_body`],
+    /// keeps it verbatim with the added code at its indentation.
+    pub(crate) fn render(&self, decl: &FunctionDecl) -> String {
+        let mut out = if decl.func_prologue == self.prologue {
+            self.text[..self.sig_start].to_string()
+        } else {
+            decl.func_prologue.clone()
+        };
+        let signature = signature_of(decl);
+        if signature == self.signature {
+            out.push_str(&self.text[self.sig_start..=self.body_open]);
+        } else {
+            out.push_str(&signature);
+            out.push_str(" {");
+        }
+        let original = &self.text[self.body_open + 1..self.body_close];
+        if decl.fn_body == self.body {
+            out.push_str(original);
+        } else {
+            match self.splice_body(original, &decl.fn_body) {
+                Some(body) => out.push_str(&body),
+                None => {
+                    out.push('\n');
+                    out.push_str(&decl.fn_body);
+                    out.push('\n');
+                }
+            }
+        }
+        out.push('}');
+        out
+    }
+
+    fn splice_body(&self, original: &str, body: &str) -> Option<String> {
+        if self.body.is_empty() || body.matches(self.body.as_str()).count() != 1 {
+            return None;
+        }
+        let at = body.find(self.body.as_str())?;
+        let (before, after) = (&body[..at], &body[at + self.body.len()..]);
+        let lead = &original[..original.len() - original.trim_start().len()];
+        let trail = &original[original.trim_end().len()..];
+        let indent = lead.rsplit('\n').next().unwrap_or("");
+
+        let mut out = lead.to_string();
+        for line in before.trim_end().lines() {
+            out.push_str(line);
+            out.push('\n');
+            out.push_str(indent);
+        }
+        out.push_str(&self.body);
+        for line in after.trim_start().lines() {
+            out.push('\n');
+            out.push_str(indent);
+            out.push_str(line);
+        }
+        out.push_str(trail);
+        Some(out)
+    }
+}
+
+fn signature_of(decl: &FunctionDecl) -> String {
+    let mut sig = decl.clone();
+    sig.func_prologue = String::new();
+    sig.func_signature()
+}
+
+impl FunctionDecl {
+    /// Parse a function, keeping its original text so that
+    /// [`crate::Decl::render`] re-emits unchanged parts exactly, including
+    /// indentation, blank lines and comments.
+    pub fn parse_preserving(in_str: &str) -> Result<Self, ParseError> {
+        let (mut decl, (sig_start, body_open, body_close)) =
+            Self::parse_with_offsets(in_str, &mut QUALIFIER_PATTERN.capture_locations())?;
+        let body_close = body_close.ok_or_else(|| ParseError::new(ParseErrorKind::Expected('{'), body_open))?;
+        decl.source = Some(SourceText {
+            text: in_str[..=body_close].to_string(),
+            sig_start,
+            body_open,
+            body_close,
+            prologue: decl.func_prologue.clone(),
+            signature: signature_of(&decl),
+            body: decl.fn_body.clone(),
+        });
+        Ok(decl)
+    }
+
+    /// Stop preserving the original formatting, rendering from the fields only.
+    pub fn discard_source(&mut self) -> &mut Self {
+        self.source = None;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Decl, FunctionDecl};
+
+    const INPUT: &str = "/// Sum things
+#[inline]
+pub fn sum(a: u32,   b: u32) -> u32 {
+    // add them up
+    let total = a + b;
+
+    total
+}";
+
+    #[test]
+    fn test_preserving_round_trip() {
+        let decl = FunctionDecl::parse_preserving(INPUT).unwrap();
+        assert_eq!(decl.render(), INPUT);
+        let source = decl.source.as_ref().unwrap();
+        assert_eq!(&source.text()[source.signature_offset()..source.signature_offset() + 6], "pub fn");
+        let (start, end) = source.body_range();
+        assert_eq!(source.text()[start..end].trim(), decl.fn_body);
+    }
+
+    #[test]
+    fn test_preserving_edits() {
+        let mut decl = FunctionDecl::parse_preserving(INPUT).unwrap();
+        decl.prepend_to_body("trace!(\"sum\");");
+        let expected = "/// Sum things
+#[inline]
+pub fn sum(a: u32,   b: u32) -> u32 {
+    trace!(\"sum\");
+    // add them up
+    let total = a + b;
+
+    total
+}";
+        assert_eq!(decl.render(), expected);
+
+        decl.rename("add");
+        let rendered = decl.render();
+        assert!(rendered.starts_with("/// Sum things\n#[inline]\npub fn add(a: u32,   b: u32) -> u32 {\n    trace!"));
+
+        decl.fn_body = "0".to_string();
+        assert!(decl.render().ends_with("-> u32 {\n0\n}"));
+        assert_eq!(decl.discard_source().render(), "/// Sum things\n#[inline]\npub fn add(a: u32,   b: u32) -> u32 {\n0\n}");
+    }
+}
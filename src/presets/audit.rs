@@ -0,0 +1,90 @@
+use crate::presets::checked;
+use crate::{render_labels, Decl, FunctionDecl, LabelOptions};
+
+/// Configuration for [`audit_log`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuditLog {
+    /// A callable expression invoked at entry as `sink(labels, args)`, with
+    /// `labels: &[(&str, &str)]` from [`crate::FnSignature::metric_labels`] and
+    /// `args: &[(&str, String)]` holding the `{:?}` of each captured argument.
+    pub sink: String,
+    /// The arguments to capture by name; `None` for every named argument.
+    pub args: Option<Vec<String>>,
+    /// Parameters with this attribute are logged as `placeholder`.
+    pub redact_attr: String,
+    pub placeholder: String,
+    pub labels: LabelOptions,
+}
+
+impl AuditLog {
+    pub fn new(sink: &str) -> Self {
+        AuditLog {
+            sink: sink.to_string(),
+            args: None,
+            redact_attr: "redact".to_string(),
+            placeholder: "<redacted>".to_string(),
+            labels: LabelOptions::new(),
+        }
+    }
+
+    pub fn with_args(mut self, args: &[&str]) -> Self {
+        self.args = Some(args.iter().map(|a| a.to_string()).collect());
+        self
+    }
+}
+
+/// Emit `decl` with a call to the audit sink at entry. Redaction attributes
+/// are removed from the emitted parameters.
+pub fn audit_log(decl: &FunctionDecl, cfg: &AuditLog) -> String {
+    let mut params = decl.params();
+    let mut captures = Vec::new();
+    for param in params.iter_mut() {
+        let redacted = !param.remove_attributes(&cfg.redact_attr).is_empty();
+        if param.is_receiver() || param.is_pattern {
+            continue;
+        }
+        if let Some(selected) = &cfg.args {
+            if !selected.contains(&param.name) {
+                continue;
+            }
+        }
+        let value = if redacted {
+            format!("String::from({:?})", cfg.placeholder)
+        } else {
+            format!("format!(\"{{:?}}\", {})", param.name)
+        };
+        captures.push(format!("({:?}, {})", param.name, value));
+    }
+
+    let labels = render_labels(&decl.signature().metric_labels(&cfg.labels));
+    let mut audited = decl.clone();
+    audited.set_params(&params);
+    audited.prepend_to_body(&format!("({})({}, &[{}]);", cfg.sink, labels, captures.join(", ")));
+    checked(audited.render())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::presets::{audit_log, AuditLog};
+    use crate::FunctionDecl;
+
+    #[test]
+    fn test_audit_log_redacts() {
+        let decl = FunctionDecl::from_string(
+            "fn login(&self, user: &str, #[redact] password: &str) -> bool { self.check(user, password) }".to_string(),
+        );
+        let expected = "fn login(&self, user: &str, password: &str) -> bool {
+(audit::record)(&[(\"function\", \"login\"), (\"module\", module_path!())], &[(\"user\", format!(\"{:?}\", user)), (\"password\", String::from(\"<redacted>\"))]);
+self.check(user, password)
+}";
+        assert_eq!(audit_log(&decl, &AuditLog::new("audit::record")), expected);
+    }
+
+    #[test]
+    fn test_audit_log_selected_args() {
+        let decl = FunctionDecl::from_string("fn transfer(from: u64, to: u64, memo: String) {}".to_string());
+        let out = audit_log(&decl, &AuditLog::new("self.sink").with_args(&["to"]));
+        assert!(out.contains("&[(\"to\", format!(\"{:?}\", to))]);"));
+        assert!(!out.contains("memo\","));
+    }
+}
@@ -0,0 +1,110 @@
+use crate::presets::{checked, invalid, ParamSelector};
+use crate::{Attribute, Decl, FunctionDecl, ParseError};
+
+/// Configuration for [`authorize`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Authorize {
+    /// The permission expression passed to the check, e.g. `"admin"`.
+    pub permission: String,
+    /// Returned when the check fails, wrapped in `Err` for a `Result` fn.
+    pub or_else: String,
+    pub subject: ParamSelector,
+    /// The check; `{subject}` is the subject parameter and `{permission}`
+    /// the permission expression.
+    pub check: String,
+}
+
+impl Authorize {
+    pub fn new(permission: &str, or_else: &str) -> Self {
+        Authorize {
+            permission: permission.to_string(),
+            or_else: or_else.to_string(),
+            subject: ParamSelector::Attribute("subject".to_string()),
+            check: "{subject}.has_permission({permission})".to_string(),
+        }
+    }
+
+    /// Read the configuration from attribute arguments such as
+    /// `("admin", or_else = "deny()")`, optionally with `subject = "name"`,
+    /// `subject_type = "User"` or `check = "..."`. A bare permission is
+    /// treated as a string literal.
+    pub fn from_attribute(attr: &Attribute) -> Result<Self, ParseError> {
+        let args = attr.parse_args()?;
+        if let Some(key) = args.unknown_keys(&["or_else", "subject", "subject_type", "check"]).first() {
+            return Err(invalid(&format!("unknown authorize argument `{}`", key)));
+        }
+        let permission = args.positional().next().ok_or_else(|| invalid("expected a permission"))?;
+        let or_else = args.get_str("or_else").ok_or_else(|| invalid("expected `or_else`"))?;
+        let mut cfg = Authorize::new(permission, &or_else);
+        if let Some(name) = args.get_str("subject") {
+            cfg.subject = ParamSelector::Name(name);
+        }
+        if let Some(pattern) = args.get_str("subject_type") {
+            cfg.subject = ParamSelector::TypePattern(pattern);
+        }
+        if let Some(check) = args.get_str("check") {
+            cfg.check = check;
+        }
+        Ok(cfg)
+    }
+}
+
+/// Emit `decl` with an authorization check at entry, returning `or_else`
+/// early when it fails. Errors if no parameter matches `cfg.subject`.
+pub fn authorize(decl: &FunctionDecl, cfg: &Authorize) -> Result<String, ParseError> {
+    let mut params = decl.params();
+    let subject = cfg
+        .subject
+        .select(&mut params)
+        .map(|idx| params[idx].name.clone())
+        .ok_or_else(|| invalid(&format!("`{}` has no subject parameter", decl.fn_name)))?;
+
+    let deny = if decl.return_type().is_result() {
+        format!("Err({})", cfg.or_else)
+    } else {
+        cfg.or_else.clone()
+    };
+    let check = cfg.check.replace("{subject}", &subject).replace("{permission}", &cfg.permission);
+    let mut guarded = decl.clone();
+    guarded.set_params(&params);
+    guarded.prepend_to_body(&format!("if !({}) {{\n    return {};\n}}", check, deny));
+    Ok(checked(guarded.render()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::presets::{authorize, Authorize, ParamSelector};
+    use crate::{Attribute, FunctionDecl};
+
+    #[test]
+    fn test_authorize_attribute_subject() {
+        let attr = Attribute::from_string("#[authorize(\"admin\", or_else = \"deny()\")]".to_string());
+        let cfg = Authorize::from_attribute(&attr).unwrap();
+        assert_eq!(cfg.permission, "\"admin\"");
+        let decl = FunctionDecl::from_string(
+            "fn delete(#[subject] user: &User, id: u64) -> Result<(), ApiError> { db::delete(id) }".to_string(),
+        );
+        let expected = "fn delete(user: &User, id: u64) -> Result<(), ApiError> {
+if !(user.has_permission(\"admin\")) {
+    return Err(deny());
+}
+db::delete(id)
+}";
+        assert_eq!(authorize(&decl, &cfg).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_authorize_type_pattern() {
+        let cfg = Authorize {
+            subject: ParamSelector::TypePattern("Session".to_string()),
+            ..Authorize::new("Role::Editor", "Response::forbidden()")
+        };
+        let decl = FunctionDecl::from_string("fn edit(s: &Session, body: String) -> Response { save(body) }".to_string());
+        assert!(authorize(&decl, &cfg)
+            .unwrap()
+            .contains("if !(s.has_permission(Role::Editor)) {\n    return Response::forbidden();\n}"));
+        let decl = FunctionDecl::from_string("fn edit(body: String) -> Response { save(body) }".to_string());
+        assert!(authorize(&decl, &cfg).is_err());
+        assert!(Authorize::from_attribute(&Attribute::new("authorize", "\"admin\"")).is_err());
+    }
+}
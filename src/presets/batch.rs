@@ -0,0 +1,102 @@
+use crate::presets::checked;
+use crate::{Decl, FunctionDecl, Param};
+
+/// How [`batch_variant`] runs the single-item function over a batch.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BatchMode {
+    /// One call after another in a loop (awaiting each call in an async fn).
+    Sequential,
+    /// All calls concurrently via `futures::future::join_all`; async fns only.
+    Join,
+}
+
+/// Emit `{name}_batch`, a sibling of single-argument `decl` taking a `Vec`
+/// of its argument and returning a `Vec` of its results in the same order.
+///
+/// The batch parameter is named after the original one with an `s` added.
+/// Returns `None` unless `decl` takes exactly one argument besides `self`,
+/// or for [`BatchMode::Join`] on a non-async fn.
+pub fn batch_variant(decl: &FunctionDecl, mode: BatchMode) -> Option<String> {
+    let params = decl.params();
+    let (receiver, args): (Vec<Param>, Vec<Param>) = params.into_iter().partition(|p| p.is_receiver());
+    if args.len() != 1 || (mode == BatchMode::Join && decl.async_str.is_empty()) {
+        return None;
+    }
+    let item = &args[0];
+    let item_name = if item.is_pattern { "item".to_string() } else { item.name.clone() };
+    let items_name = format!("{}s", item_name);
+    let call = format!(
+        "{}{}({})",
+        if receiver.is_empty() { "" } else { "self." },
+        decl.fn_name,
+        item_name
+    );
+    let ret = if decl.ret_decl.is_empty() { "()" } else { &decl.ret_decl };
+
+    let mut batch = decl.clone();
+    batch.func_prologue = String::new();
+    decl.copy_cfgs_to(&mut batch);
+    batch.fn_name = format!("{}_batch", decl.fn_name);
+    batch.ret_decl = format!("Vec<{}>", ret);
+    batch.set_params(
+        &receiver
+            .into_iter()
+            .chain(std::iter::once(Param::new(&items_name, &format!("Vec<{}>", item.ty))))
+            .collect::<Vec<Param>>(),
+    );
+    batch.fn_body = match mode {
+        BatchMode::Sequential => format!(
+            "let mut __results = Vec::with_capacity({items}.len());\nfor {item} in {items} {{\n    __results.push({call}{await_});\n}}\n__results",
+            items = items_name,
+            item = item_name,
+            call = call,
+            await_ = if decl.async_str.is_empty() { "" } else { ".await" }
+        ),
+        BatchMode::Join => format!(
+            "futures::future::join_all({}.into_iter().map(|{}| {})).await",
+            items_name, item_name, call
+        ),
+    };
+    Some(checked(batch.render()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::presets::{batch_variant, BatchMode};
+    use crate::FunctionDecl;
+
+    #[test]
+    fn test_batch_sequential() {
+        let decl = FunctionDecl::from_string(
+            "/// Handle one\npub fn handle<T: Into<Job>>(&self, job: T) -> Result<Done, Error> where T: Send { self.run(job.into()) }"
+                .to_string(),
+        );
+        let expected = "pub fn handle_batch<T: Into<Job>>(&self, jobs: Vec<T>) -> Vec<Result<Done, Error>> where T: Send {
+let mut __results = Vec::with_capacity(jobs.len());
+for job in jobs {
+    __results.push(self.handle(job));
+}
+__results
+}";
+        assert_eq!(batch_variant(&decl, BatchMode::Sequential).unwrap(), expected);
+        assert!(batch_variant(&decl, BatchMode::Join).is_none());
+    }
+
+    #[test]
+    fn test_batch_join() {
+        let decl = FunctionDecl::from_string("async fn fetch(id: u64) -> Item { get(id).await }".to_string());
+        let expected = "async fn fetch_batch(ids: Vec<u64>) -> Vec<Item> {
+futures::future::join_all(ids.into_iter().map(|id| fetch(id))).await
+}";
+        assert_eq!(batch_variant(&decl, BatchMode::Join).unwrap(), expected);
+        let two = FunctionDecl::from_string("fn f(a: u8, b: u8) {}".to_string());
+        assert!(batch_variant(&two, BatchMode::Sequential).is_none());
+    }
+
+    #[test]
+    fn test_batch_param_named_results() {
+        let decl = FunctionDecl::from_string("fn tally(results: Score) -> u32 { 0 }".to_string());
+        let out = batch_variant(&decl, BatchMode::Sequential).unwrap();
+        assert!(out.contains("for results in resultss {\n    __results.push(tally(results));\n}\n__results"));
+    }
+}
@@ -0,0 +1,212 @@
+use std::collections::BTreeMap;
+
+use crate::presets::TypeMap;
+use crate::{Attribute, FunctionDecl, Manifest};
+
+/// What generated code should favour where a preset has a choice.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum OptimizeFor {
+    /// Inline everything at each call site.
+    #[default]
+    Speed,
+    /// Move shared and cold code into out-of-line helper fns emitted once.
+    ///
+    /// Honored by the `_in` variants of the presets that generate panics:
+    /// [`crate::presets::rate_limited_in`], [`crate::presets::dispatch_table_in`],
+    /// [`crate::presets::timeout_in`] and [`crate::presets::unwrap_variant_in`].
+    CodeSize,
+}
+
+/// Which `#[inline]` hint presets put on the small forwarding fns they generate.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum InlineHint {
+    /// No annotation.
+    #[default]
+    None,
+    /// `#[inline]`
+    Inline,
+    /// `#[inline(always)]`
+    Always,
+}
+
+/// State shared by the presets run within one macro expansion: the
+/// optimization goal, the type tables, the helper fns registered so far and
+/// the manifest of generated items.
+///
+/// Presets taking a context (the `_in` variants) call [`GenContext::helper`]
+/// for code they would otherwise duplicate; the macro emits
+/// [`GenContext::take_helpers`] once next to its output, and optionally
+/// [`GenContext::take_manifest`] rendered with [`Manifest::to_const`].
+#[derive(Clone, Debug, Default)]
+pub struct GenContext {
+    optimize: OptimizeFor,
+    inline: InlineHint,
+    inline_max_stmts: usize,
+    types: BTreeMap<String, TypeMap>,
+    helpers: BTreeMap<String, String>,
+    manifest: Manifest,
+}
+
+impl GenContext {
+    pub fn new() -> Self {
+        GenContext::default()
+    }
+
+    pub fn optimize_for(mut self, goal: OptimizeFor) -> Self {
+        self.optimize = goal;
+        self
+    }
+
+    /// Annotate generated forwarding fns of at most `max_stmts` statements
+    /// (see [`crate::Body::stmt_count`]) with `hint`; larger ones are never
+    /// annotated.
+    pub fn inline_shims(mut self, hint: InlineHint, max_stmts: usize) -> Self {
+        self.inline = hint;
+        self.inline_max_stmts = max_stmts;
+        self
+    }
+
+    /// Add `map` to the type table `table`, e.g. [`crate::presets::DummyValues::TABLE`],
+    /// its entries replacing any already configured for the same type.
+    pub fn with_types(mut self, table: &str, map: TypeMap) -> Self {
+        self.types.entry(table.to_string()).or_default().extend(map);
+        self
+    }
+
+    pub fn types(&self, table: &str) -> Option<&TypeMap> {
+        self.types.get(table)
+    }
+
+    /// Add the configured inline hint to the generated `shim` if it is small
+    /// enough and has no `#[inline]` of its own.
+    pub fn annotate_inline(&self, shim: &mut FunctionDecl) {
+        let attr = match self.inline {
+            InlineHint::None => return,
+            InlineHint::Inline => Attribute::new("inline", ""),
+            InlineHint::Always => Attribute::new("inline", "always"),
+        };
+        if shim.body().stmt_count() <= self.inline_max_stmts && !shim.attributes().iter().any(|a| a.is("inline")) {
+            shim.insert_attribute(&attr);
+        }
+    }
+
+    pub fn optimizing_for(&self) -> OptimizeFor {
+        self.optimize
+    }
+
+    pub fn is_code_size(&self) -> bool {
+        self.optimize == OptimizeFor::CodeSize
+    }
+
+    /// Register the helper fn `name`, rendering its source with `source` the
+    /// first time only, and return `name` for use in a call.
+    pub fn helper<F: FnOnce() -> String>(&mut self, name: &str, source: F) -> String {
+        self.helpers.entry(name.to_string()).or_insert_with(source);
+        name.to_string()
+    }
+
+    /// An expression panicking with `msg`. For [`OptimizeFor::CodeSize`] this
+    /// calls a shared `#[cold]` helper so the formatting machinery is not
+    /// repeated at each site.
+    pub fn panic_expr(&mut self, msg: &str) -> String {
+        if !self.is_code_size() {
+            return format!("panic!({:?})", msg);
+        }
+        let helper = self.helper("__pmt_panic", || {
+            "#[cold]\n#[inline(never)]\nfn __pmt_panic(msg: &'static str) -> ! {\n    panic!(\"{}\", msg)\n}".to_string()
+        });
+        format!("{}({:?})", helper, msg)
+    }
+
+    /// Like [`GenContext::panic_expr`] for a formatted message: `fmt` is a
+    /// format string literal, quotes included, and `args` its arguments, each
+    /// starting with `, `.
+    pub fn panic_fmt_expr(&mut self, fmt: &str, args: &str) -> String {
+        if !self.is_code_size() {
+            return format!("panic!({}{})", fmt, args);
+        }
+        let helper = self.helper("__pmt_panic_fmt", || {
+            "#[cold]\n#[inline(never)]\nfn __pmt_panic_fmt(args: ::core::fmt::Arguments) -> ! {\n    panic!(\"{}\", args)\n}"
+                .to_string()
+        });
+        format!("{}(format_args!({}{}))", helper, fmt, args)
+    }
+
+    /// The registered helpers' source, clearing them.
+    pub fn take_helpers(&mut self) -> String {
+        std::mem::take(&mut self.helpers).into_values().collect::<Vec<String>>().join("\n\n")
+    }
+
+    /// Record the items in the generated `code` as generated from `source`,
+    /// returning `code`; see [`Manifest::record_items`].
+    pub fn record_output(&mut self, source: &str, code: String) -> String {
+        self.manifest.record_items(source, &code);
+        code
+    }
+
+    pub fn manifest(&self) -> &Manifest {
+        &self.manifest
+    }
+
+    pub fn manifest_mut(&mut self) -> &mut Manifest {
+        &mut self.manifest
+    }
+
+    /// The recorded manifest, clearing it.
+    pub fn take_manifest(&mut self) -> Manifest {
+        std::mem::take(&mut self.manifest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::presets::{GenContext, InlineHint, OptimizeFor};
+    use crate::{FunctionDecl, ItemKind};
+
+    #[test]
+    fn test_gen_context_helpers() {
+        let mut ctx = GenContext::new();
+        assert_eq!(ctx.panic_expr("boom"), "panic!(\"boom\")");
+        assert!(ctx.take_helpers().is_empty());
+
+        let mut ctx = GenContext::new().optimize_for(OptimizeFor::CodeSize);
+        assert_eq!(ctx.panic_expr("a"), "__pmt_panic(\"a\")");
+        assert_eq!(ctx.panic_expr("b"), "__pmt_panic(\"b\")");
+        assert_eq!(ctx.helper("h", || "fn h() {}".to_string()), "h");
+        assert_eq!(ctx.helper("h", || unreachable!()), "h");
+        let helpers = ctx.take_helpers();
+        assert_eq!(helpers.matches("fn __pmt_panic(").count(), 1);
+        assert!(helpers.ends_with("fn h() {}"));
+        assert!(ctx.take_helpers().is_empty());
+    }
+
+    #[test]
+    fn test_gen_context_manifest() {
+        let mut ctx = GenContext::new();
+        let code = ctx.record_output("get", "fn get_batch() {}\nfn get_traced() {}".to_string());
+        assert_eq!(code, "fn get_batch() {}\nfn get_traced() {}");
+        ctx.manifest_mut().record("Cache", ItemKind::Struct, "get");
+        assert_eq!(ctx.manifest().generated_from("get").count(), 3);
+        let manifest = ctx.take_manifest();
+        assert_eq!(manifest.entries[1].name, "get_traced");
+        assert_eq!(manifest.entries[2].kind, "struct");
+        assert!(ctx.manifest().entries.is_empty());
+    }
+
+    #[test]
+    fn test_annotate_inline() {
+        let ctx = GenContext::new().inline_shims(InlineHint::Always, 1);
+        let mut shim = FunctionDecl::from_string("fn f(x: u8) -> u8 { g(x) }".to_string());
+        ctx.annotate_inline(&mut shim);
+        assert_eq!(shim.func_prologue, "#[inline(always)]\n");
+        ctx.annotate_inline(&mut shim);
+        assert_eq!(shim.attributes().len(), 1);
+
+        let mut large = FunctionDecl::from_string("fn f(x: u8) -> u8 { let y = g(x); y }".to_string());
+        ctx.annotate_inline(&mut large);
+        assert!(large.func_prologue.is_empty());
+        let mut plain = FunctionDecl::from_string("fn f() { g() }".to_string());
+        GenContext::new().annotate_inline(&mut plain);
+        assert!(plain.func_prologue.is_empty());
+    }
+}
@@ -0,0 +1,209 @@
+use std::collections::BTreeMap;
+
+use crate::presets::{checked, invalid, GenContext};
+use crate::{indent, Decl, FunctionDecl, ParseError, Visibility};
+
+/// How a [`dispatch_table`] lookup finds the handler for a key.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Lookup {
+    /// Scan a slice of entries in order.
+    Linear,
+    /// Binary search over a slice sorted by key.
+    BinarySearch,
+    /// Hash the key (FNV-1a) and `match` on the hash, comparing the key only
+    /// within the matching arm.
+    Hashed,
+    /// Pick by entry count using the thresholds in [`DispatchTable`].
+    Auto,
+}
+
+/// Configuration for [`dispatch_table`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct DispatchTable {
+    /// Name of the generated lookup function.
+    pub fn_name: String,
+    /// Type of every handler, e.g. `fn(&[String]) -> Result<(), Error>`.
+    pub handler_ty: String,
+    pub lookup: Lookup,
+    /// With [`Lookup::Auto`], use binary search from this many entries.
+    pub binary_search_from: usize,
+    /// With [`Lookup::Auto`], use hashing from this many entries.
+    pub hashed_from: usize,
+}
+
+impl DispatchTable {
+    pub fn new(fn_name: &str, handler_ty: &str) -> Self {
+        DispatchTable {
+            fn_name: fn_name.to_string(),
+            handler_ty: handler_ty.to_string(),
+            lookup: Lookup::Auto,
+            binary_search_from: 16,
+            hashed_from: 64,
+        }
+    }
+
+    /// The strategy used for `count` entries.
+    pub fn lookup_for(&self, count: usize) -> Lookup {
+        match self.lookup {
+            Lookup::Auto if count >= self.hashed_from => Lookup::Hashed,
+            Lookup::Auto if count >= self.binary_search_from => Lookup::BinarySearch,
+            Lookup::Auto => Lookup::Linear,
+            other => other,
+        }
+    }
+}
+
+/// Emit `pub fn {fn_name}(key: &str) -> Option<{handler_ty}>` mapping each
+/// key of `entries` to its handler expression (typically a function path).
+/// Errors on a duplicate key.
+pub fn dispatch_table(cfg: &DispatchTable, entries: &[(&str, &str)]) -> Result<String, ParseError> {
+    dispatch_table_in(&mut GenContext::new(), cfg, entries)
+}
+
+/// [`dispatch_table`] honoring `ctx`; for code size every hashed table shares
+/// one out-of-line hash fn instead of nesting its own.
+pub fn dispatch_table_in(
+    ctx: &mut GenContext,
+    cfg: &DispatchTable,
+    entries: &[(&str, &str)],
+) -> Result<String, ParseError> {
+    let mut sorted = BTreeMap::new();
+    for (key, handler) in entries {
+        if sorted.insert(*key, *handler).is_some() {
+            return Err(invalid(&format!("duplicate dispatch key {:?}", key)));
+        }
+    }
+    let table = |entries: &mut dyn Iterator<Item = (&&str, &&str)>| {
+        format!(
+            "const TABLE: &[(&str, {})] = &[\n{}\n];",
+            cfg.handler_ty,
+            entries
+                .map(|(k, h)| format!("    ({:?}, {}),", k, h))
+                .collect::<Vec<String>>()
+                .join("\n")
+        )
+    };
+    let body = match cfg.lookup_for(entries.len()) {
+        Lookup::Linear | Lookup::Auto => format!(
+            "{}\nTABLE.iter().find(|(k, _)| *k == key).map(|(_, h)| *h)",
+            table(&mut entries.iter().map(|(k, h)| (k, h)))
+        ),
+        Lookup::BinarySearch => format!(
+            "{}\nTABLE.binary_search_by(|(k, _)| (*k).cmp(key)).ok().map(|idx| TABLE[idx].1)",
+            table(&mut sorted.iter())
+        ),
+        Lookup::Hashed => {
+            let mut arms: BTreeMap<u64, Vec<(&str, &str)>> = BTreeMap::new();
+            for (key, handler) in entries {
+                arms.entry(fnv1a(key.as_bytes())).or_default().push((key, handler));
+            }
+            let arms = arms
+                .iter()
+                .map(|(hash, keys)| {
+                    let checks = keys
+                        .iter()
+                        .map(|(k, h)| format!("if key == {:?} {{\n    Some({})\n}}", k, h))
+                        .collect::<Vec<String>>()
+                        .join(" else ");
+                    format!("{:#018x} => {} else {{\n    None\n}},", hash, checks)
+                })
+                .collect::<Vec<String>>()
+                .join("\n");
+            let hash_fn = |name: &str| {
+                format!(
+                    "fn {}(bytes: &[u8]) -> u64 {{\n    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |h, b| (h ^ *b as u64).wrapping_mul(0x100_0000_01b3))\n}}",
+                    name
+                )
+            };
+            let (local, hash) = if ctx.is_code_size() {
+                (String::new(), ctx.helper("__pmt_fnv1a", || hash_fn("__pmt_fnv1a")))
+            } else {
+                (format!("{}\n", hash_fn("fnv1a")), "fnv1a".to_string())
+            };
+            format!(
+                "{}match {}(key.as_bytes()) {{\n{}\n    _ => None,\n}}",
+                local,
+                hash,
+                indent(&arms)
+            )
+        }
+    };
+
+    let lookup = FunctionDecl {
+        vis: Visibility::Pub,
+        fn_name: cfg.fn_name.clone(),
+        fn_decl: "(key: &str)".to_string(),
+        ret_decl: format!("Option<{}>", cfg.handler_ty),
+        fn_body: body,
+        ..FunctionDecl::default()
+    };
+    Ok(checked(lookup.render()))
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(0xcbf2_9ce4_8422_2325, |h, b| (h ^ *b as u64).wrapping_mul(0x100_0000_01b3))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::presets::{dispatch_table, dispatch_table_in, DispatchTable, GenContext, Lookup, OptimizeFor};
+
+    const ENTRIES: &[(&str, &str)] = &[("stop", "cmd::stop"), ("go", "cmd::go")];
+
+    #[test]
+    fn test_dispatch_linear_and_sorted() {
+        let cfg = DispatchTable::new("command", "fn()");
+        let expected = "pub fn command(key: &str) -> Option<fn()> {
+const TABLE: &[(&str, fn())] = &[
+    (\"stop\", cmd::stop),
+    (\"go\", cmd::go),
+];
+TABLE.iter().find(|(k, _)| *k == key).map(|(_, h)| *h)
+}";
+        assert_eq!(dispatch_table(&cfg, ENTRIES).unwrap(), expected);
+
+        let cfg = DispatchTable {
+            binary_search_from: 2,
+            ..DispatchTable::new("command", "fn()")
+        };
+        assert_eq!(cfg.lookup_for(2), Lookup::BinarySearch);
+        let out = dispatch_table(&cfg, ENTRIES).unwrap();
+        assert!(out.contains("(\"go\", cmd::go),\n    (\"stop\", cmd::stop),"));
+        assert!(out.contains("TABLE.binary_search_by(|(k, _)| (*k).cmp(key))"));
+        assert!(dispatch_table(&cfg, &[("a", "x"), ("a", "y")]).is_err());
+    }
+
+    #[test]
+    fn test_dispatch_hashed() {
+        let cfg = DispatchTable {
+            lookup: Lookup::Hashed,
+            ..DispatchTable::new("route", "Handler")
+        };
+        let out = dispatch_table(&cfg, &[("a", "h_a")]).unwrap();
+        let expected = "match fnv1a(key.as_bytes()) {
+    0xaf63dc4c8601ec8c => if key == \"a\" {
+        Some(h_a)
+    } else {
+        None
+    },
+    _ => None,
+}";
+        assert!(out.contains(expected));
+        assert_eq!(DispatchTable::new("r", "H").lookup_for(100), Lookup::Hashed);
+    }
+
+    #[test]
+    fn test_dispatch_hashed_code_size() {
+        let mut ctx = GenContext::new().optimize_for(OptimizeFor::CodeSize);
+        let cfg = DispatchTable {
+            lookup: Lookup::Hashed,
+            ..DispatchTable::new("route", "Handler")
+        };
+        let out = dispatch_table_in(&mut ctx, &cfg, ENTRIES).unwrap();
+        assert!(out.starts_with("pub fn route(key: &str) -> Option<Handler> {\nmatch __pmt_fnv1a(key.as_bytes()) {"));
+        dispatch_table_in(&mut ctx, &DispatchTable { fn_name: "other".to_string(), ..cfg }, ENTRIES).unwrap();
+        assert_eq!(ctx.take_helpers().matches("fn __pmt_fnv1a").count(), 1);
+    }
+}
@@ -0,0 +1,116 @@
+use crate::presets::{checked, GenContext, TypeMap};
+use crate::types::TypeKind;
+use crate::StructDecl;
+
+/// Placeholder value expressions keyed by type, used by [`dummy_constructor`].
+///
+/// Entries are a [`TypeMap`], so patterns like `Option<_>` may refer to the
+/// value for the wrapped type as `{0}`. Types without an entry get a value
+/// derived from their [`TypeKind`] (`0`, `false`, `None`, `Vec::new()`, ...)
+/// and finally `Default::default()`.
+#[derive(Clone, Debug, Default)]
+pub struct DummyValues {
+    map: TypeMap,
+}
+
+impl DummyValues {
+    /// The [`GenContext`] type table read by [`DummyValues::from_context`].
+    pub const TABLE: &'static str = "dummy";
+
+    pub fn new() -> Self {
+        DummyValues::default()
+    }
+
+    /// The values configured in `ctx`'s [`DummyValues::TABLE`] table.
+    pub fn from_context(ctx: &GenContext) -> Self {
+        DummyValues::from(ctx.types(Self::TABLE).cloned().unwrap_or_default())
+    }
+
+    pub fn with(mut self, ty: &str, value_expr: &str) -> Self {
+        self.map = self.map.with(ty, value_expr);
+        self
+    }
+
+    pub fn value_for(&self, ty: &str) -> String {
+        if let Some(value) = self.map.lookup_with(ty, |inner| Some(self.value_for(inner))) {
+            return value;
+        }
+        match TypeKind::classify(ty) {
+            TypeKind::Bool => "false".to_string(),
+            TypeKind::Char => "'a'".to_string(),
+            TypeKind::Integer => "0".to_string(),
+            TypeKind::Float => "0.0".to_string(),
+            TypeKind::String if ty.trim().starts_with('&') => "\"dummy\"".to_string(),
+            TypeKind::String => "String::from(\"dummy\")".to_string(),
+            TypeKind::Option(_) => "None".to_string(),
+            TypeKind::Vec(_) => "Vec::new()".to_string(),
+            TypeKind::Box(inner) => format!("Box::new({})", self.value_for(&inner)),
+            TypeKind::Tuple(elems) if elems.len() == 1 => format!("({},)", self.value_for(&elems[0])),
+            TypeKind::Tuple(elems) => format!(
+                "({})",
+                elems
+                    .iter()
+                    .map(|e| self.value_for(e))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            TypeKind::Array(elem, len) => format!("[{}; {}]", self.value_for(&elem), len),
+            TypeKind::Result(_, _) | TypeKind::Reference(_) | TypeKind::Other => "Default::default()".to_string(),
+        }
+    }
+}
+
+impl From<TypeMap> for DummyValues {
+    fn from(map: TypeMap) -> Self {
+        DummyValues { map }
+    }
+}
+
+/// Emit an `impl` block with a `pub fn dummy() -> Self` filling every field
+/// of the struct from `values`.
+pub fn dummy_constructor(struct_decl: &StructDecl, values: &DummyValues) -> String {
+    let field_values: Vec<String> = struct_decl.fields.iter().map(|f| values.value_for(&f.ty)).collect();
+    checked(format!(
+        "{} {{\n    pub fn dummy() -> Self {{\n        {}\n    }}\n}}",
+        struct_decl.impl_header(),
+        struct_decl.self_constructor(&field_values)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::presets::{dummy_constructor, DummyValues, GenContext, TypeMap};
+    use crate::StructDecl;
+
+    #[test]
+    fn test_dummy_values() {
+        let values = DummyValues::new().with("Uuid", "Uuid::nil()");
+        assert_eq!(values.value_for("u32"), "0");
+        assert_eq!(values.value_for("&'static str"), "\"dummy\"");
+        assert_eq!(values.value_for("Option<u8>"), "None");
+        assert_eq!(values.value_for("Box<(u8, bool)>"), "Box::new((0, false))");
+        assert_eq!(values.value_for("(u8,)"), "(0,)");
+        assert_eq!(values.value_for("[f64; 3]"), "[0.0; 3]");
+        assert_eq!(values.value_for("Uuid"), "Uuid::nil()");
+        assert_eq!(values.value_for("HashMap<u8, u8>"), "Default::default()");
+
+        let ctx = GenContext::new().with_types(DummyValues::TABLE, TypeMap::new().with("Arc<_>", "Arc::new({0})"));
+        let values = DummyValues::from_context(&ctx).with("Uuid", "Uuid::nil()");
+        assert_eq!(values.value_for("Arc<Uuid>"), "Arc::new(Uuid::nil())");
+        assert_eq!(values.value_for("Arc<(u8,)>"), "Arc::new((0,))");
+    }
+
+    #[test]
+    fn test_dummy_constructor() {
+        let decl = StructDecl::from_string(
+            "pub struct User<T> where T: Default { id: Uuid, name: String, tags: Vec<String>, extra: T }".to_string(),
+        );
+        let out = dummy_constructor(&decl, &DummyValues::new().with("Uuid", "Uuid::nil()"));
+        let expected = "impl<T> User<T> where T: Default {
+    pub fn dummy() -> Self {
+        Self { id: Uuid::nil(), name: String::from(\"dummy\"), tags: Vec::new(), extra: Default::default() }
+    }
+}";
+        assert_eq!(out, expected);
+    }
+}
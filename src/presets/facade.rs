@@ -0,0 +1,171 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::presets::{checked, to_snake_case, GenContext};
+use crate::{Decl, FunctionDecl, ImplBlockDecl, Item, ItemKind, ModDecl, NameRegistry, Param, Receiver, Visibility};
+
+lazy_static! {
+    static ref SELF_TYPE: Regex = Regex::new(r"\bSelf\b").unwrap();
+}
+
+/// Emit `pub mod {name}` holding one free function per public method of
+/// `impls` and per function in `fns`, each delegating to the original.
+///
+/// A method's receiver becomes a leading `obj` parameter, so `x.run(a)` is
+/// exposed as `name::run(&x, a)`. Functions are named after the original,
+/// falling back to `{type}_{method}` (then a numeric suffix from `names`)
+/// when that is already taken.
+pub fn facade_module(name: &str, impls: &[ImplBlockDecl], fns: &[FunctionDecl], names: &mut NameRegistry) -> String {
+    facade_module_in(&GenContext::new(), name, impls, fns, names)
+}
+
+/// [`facade_module`] with each function annotated per `ctx`'s inline policy.
+pub fn facade_module_in(
+    ctx: &GenContext,
+    name: &str,
+    impls: &[ImplBlockDecl],
+    fns: &[FunctionDecl],
+    names: &mut NameRegistry,
+) -> String {
+    let mut items = vec![Item::Other(ItemKind::Use, "use super::*;".to_string())];
+    for block in impls {
+        let type_name = block.self_ty.split('<').next().unwrap_or("").trim();
+        for method in block.methods().filter(|m| !m.vis.is_private()) {
+            let fn_name = if names.reserve(&method.fn_name) {
+                method.fn_name.clone()
+            } else {
+                names.claim(&format!("{}_{}", to_snake_case(type_name), method.fn_name))
+            };
+            let mut facade = facade_method(block, method, &fn_name);
+            ctx.annotate_inline(&mut facade);
+            items.push(Item::Fn(facade));
+        }
+    }
+    for func in fns {
+        let fn_name = names.claim(&func.fn_name);
+        let mut facade = facade_fn(func, &fn_name);
+        facade.fn_body = format!("super::{}{}", facade.forwarding_call(&func.fn_name), awaited(func));
+        ctx.annotate_inline(&mut facade);
+        items.push(Item::Fn(facade));
+    }
+    let module = ModDecl {
+        prologue: String::new(),
+        vis: Visibility::Pub,
+        name: name.to_string(),
+        inner_attrs: String::new(),
+        items,
+    };
+    checked(module.render())
+}
+
+fn facade_method(block: &ImplBlockDecl, method: &FunctionDecl, fn_name: &str) -> FunctionDecl {
+    let self_ty = block.self_ty.as_str();
+    let mut facade = facade_fn(method, fn_name);
+    let params: Vec<Param> = facade
+        .params()
+        .into_iter()
+        .map(|p| match p.receiver() {
+            Some(Receiver::Value { .. }) => Param::new("obj", self_ty),
+            Some(Receiver::Typed(ty)) => Param::new("obj", &SELF_TYPE.replace_all(&ty, self_ty)),
+            Some(_) => Param::new("obj", &p.name.replacen("self", self_ty, 1)),
+            None => Param::new(&p.name, &SELF_TYPE.replace_all(&p.ty, self_ty)),
+        })
+        .collect();
+    let args: Vec<&str> = params.iter().map(|p| p.name.as_str()).collect();
+    facade.fn_body = format!(
+        "{}::{}({}){}",
+        if self_ty.contains('<') { format!("<{}>", self_ty) } else { self_ty.to_string() },
+        method.fn_name,
+        args.join(", "),
+        awaited(method)
+    );
+    facade.set_params(&params);
+    facade.ret_decl = SELF_TYPE.replace_all(&facade.ret_decl, self_ty).to_string();
+    facade.generics = merge_generics(&block.generics, &facade.generics);
+    facade.where_clause = match (block.where_clause.as_str(), facade.where_clause.as_str()) {
+        ("", own) => own.to_string(),
+        (outer, "") => outer.to_string(),
+        (outer, own) => format!("{}, {}", outer.trim_end_matches(','), own.trim_start_matches("where").trim()),
+    };
+    facade
+}
+
+/// A public copy of `func`'s signature named `fn_name`, keeping only doc comments.
+fn facade_fn(func: &FunctionDecl, fn_name: &str) -> FunctionDecl {
+    let mut facade = func.clone();
+    facade.func_prologue = func
+        .func_prologue
+        .lines()
+        .filter(|l| l.trim_start().starts_with("///"))
+        .map(|l| format!("{}\n", l.trim()))
+        .collect();
+    facade.vis = Visibility::Pub;
+    facade.fn_name = fn_name.to_string();
+    facade.set_params(&func.forwardable_params());
+    facade
+}
+
+fn awaited(func: &FunctionDecl) -> &'static str {
+    if func.async_str.is_empty() {
+        ""
+    } else {
+        ".await"
+    }
+}
+
+fn merge_generics(outer: &str, own: &str) -> String {
+    match (outer, own) {
+        ("", g) | (g, "") => g.to_string(),
+        (outer, own) => format!(
+            "<{}, {}>",
+            outer.trim_start_matches('<').trim_end_matches('>'),
+            own.trim_start_matches('<').trim_end_matches('>')
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::presets::facade_module;
+    use crate::{FunctionDecl, ImplBlockDecl, NameRegistry};
+
+    #[test]
+    fn test_facade_module() {
+        let counter = ImplBlockDecl::from_string(
+            "impl Counter {
+                /// Bump the count
+                pub fn incr(&mut self, by: u32) -> u32 { self.n += by; self.n }
+                pub fn new() -> Self { Counter { n: 0 } }
+                fn hidden(&self) {}
+            }"
+            .to_string(),
+        );
+        let store = ImplBlockDecl::from_string(
+            "impl<T: Clone> Store<T> { pub async fn new(items: Vec<T>) -> Self { Store { items } } }".to_string(),
+        );
+        let reset = FunctionDecl::from_string("fn reset(mut level: u8, _: Force) { level = 0; }".to_string());
+        let mut names = NameRegistry::new();
+        let expected = "pub mod ffi {
+    use super::*;
+
+    /// Bump the count
+    pub fn incr(obj: &mut Counter, by: u32) -> u32 {
+    Counter::incr(obj, by)
+    }
+
+    pub fn new() -> Counter {
+    Counter::new()
+    }
+
+    pub async fn store_new<T: Clone>(items: Vec<T>) -> Store<T> {
+    <Store<T>>::new(items).await
+    }
+
+    pub fn reset(level: u8, __arg1: Force) {
+    super::reset(level, __arg1)
+    }
+}";
+        assert_eq!(facade_module("ffi", &[counter, store], &[reset], &mut names), expected);
+        assert!(names.contains("store_new"));
+    }
+}
@@ -0,0 +1,75 @@
+use crate::presets::checked;
+use crate::{Decl, FunctionDecl};
+
+/// What the disabled side of a [`feature_gated`] pair does.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FeatureStub {
+    /// Return `Err(expr)`, where `expr` may use `{fn}` and `{feature}`, e.g.
+    /// `Error::Disabled("{feature}")`. Functions that don't return a `Result`
+    /// get an `unimplemented!` body instead.
+    Error(String),
+    /// Fail the build with `compile_error!` if the stub is ever compiled in.
+    CompileError,
+}
+
+/// Emit `decl` gated on `feature`, followed by a stub with the same signature
+/// for when the feature is disabled.
+pub fn feature_gated(decl: &FunctionDecl, feature: &str, stub: &FeatureStub) -> String {
+    let message = format!("`{}` requires the `{}` feature", decl.fn_name, feature);
+
+    let mut enabled = decl.clone();
+    enabled.func_prologue = format!("#[cfg(feature = \"{}\")]\n{}", feature, decl.func_prologue);
+
+    let mut disabled = decl.clone();
+    disabled.func_prologue = format!(
+        "#[cfg(not(feature = \"{}\"))]\n#[allow(unused_variables)]\n{}",
+        feature, decl.func_prologue
+    );
+    disabled.fn_body = match stub {
+        FeatureStub::Error(template) if decl.return_type().is_result() => format!(
+            "Err({})",
+            template.replace("{fn}", &decl.fn_name).replace("{feature}", feature)
+        ),
+        FeatureStub::Error(_) => format!("unimplemented!({:?})", message),
+        FeatureStub::CompileError => format!("compile_error!({:?})", message),
+    };
+
+    checked(format!("{}\n\n{}", enabled.render(), disabled.render()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::presets::{feature_gated, FeatureStub};
+    use crate::FunctionDecl;
+
+    #[test]
+    fn test_feature_gated_error() {
+        let decl = FunctionDecl::from_string(
+            "/// Connect\npub fn connect(url: &str) -> Result<Conn, Error> { Conn::open(url) }".to_string(),
+        );
+        let expected = "#[cfg(feature = \"postgres\")]
+/// Connect
+pub fn connect(url: &str) -> Result<Conn, Error> {
+Conn::open(url)
+}
+
+#[cfg(not(feature = \"postgres\"))]
+#[allow(unused_variables)]
+/// Connect
+pub fn connect(url: &str) -> Result<Conn, Error> {
+Err(Error::Disabled(\"connect\", \"postgres\"))
+}";
+        let stub = FeatureStub::Error("Error::Disabled(\"{fn}\", \"{feature}\")".to_string());
+        assert_eq!(feature_gated(&decl, "postgres", &stub), expected);
+
+        let plain = FunctionDecl::from_string("fn count() -> u8 { 1 }".to_string());
+        assert!(feature_gated(&plain, "x", &stub).ends_with("unimplemented!(\"`count` requires the `x` feature\")\n}"));
+    }
+
+    #[test]
+    fn test_feature_gated_compile_error() {
+        let decl = FunctionDecl::from_string("fn f() { g() }".to_string());
+        assert!(feature_gated(&decl, "fast", &FeatureStub::CompileError)
+            .ends_with("fn f() {\ncompile_error!(\"`f` requires the `fast` feature\")\n}"));
+    }
+}
@@ -0,0 +1,64 @@
+use crate::presets::checked;
+use crate::{Decl, FunctionDecl, ImplBlockDecl, ImplItem, ParseError};
+
+/// Emit `block` with every method replaced by the output of `transform`,
+/// such as another preset. Methods marked `#[skip]` are left as they are,
+/// minus the marker; other items are kept in place. The first error from
+/// `transform` is returned.
+pub fn instrument_impl<F>(block: &ImplBlockDecl, mut transform: F) -> Result<String, ParseError>
+where
+    F: FnMut(&FunctionDecl) -> Result<String, ParseError>,
+{
+    let mut instrumented = block.clone();
+    for item in instrumented.items.iter_mut() {
+        if let ImplItem::Fn(method) = item {
+            if method.remove_attributes("skip").is_empty() {
+                *item = ImplItem::Other(transform(method)?);
+            }
+        }
+    }
+    Ok(checked(instrumented.render()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::presets::{instrument_impl, invalid};
+    use crate::{Decl, ImplBlockDecl};
+
+    #[test]
+    fn test_instrument_impl() {
+        let block = ImplBlockDecl::from_string(
+            "impl Store {
+    const CAP: usize = 8;
+
+    pub fn get(&self, k: u8) -> u8 { self.map[k] }
+
+    #[skip]
+    #[inline]
+    fn raw(&self) -> &[u8] { &self.map }
+}"
+            .to_string(),
+        );
+        let out = instrument_impl(&block, |f| {
+            let mut traced = f.clone();
+            traced.prepend_to_body(&format!("trace!(\"{}\");", f.fn_name));
+            Ok(traced.render())
+        })
+        .unwrap();
+        let expected = "impl Store {
+    const CAP: usize = 8;
+
+    pub fn get(&self, k: u8) -> u8 {
+    trace!(\"get\");
+    self.map[k]
+    }
+
+    #[inline]
+    fn raw(&self) -> &[u8] {
+    &self.map
+    }
+}";
+        assert_eq!(out, expected);
+        assert!(instrument_impl(&block, |_| Err(invalid("no"))).is_err());
+    }
+}
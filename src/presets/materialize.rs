@@ -0,0 +1,96 @@
+use crate::presets::{checked, invalid};
+use crate::{Decl, ImplBlockDecl, ImplItem, ParseError, TraitDecl, TraitMethod};
+
+/// How [`materialize_methods`] fills in a method the impl leaves out.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Materialize {
+    /// Copy the trait's default body. Required methods are an error.
+    CopyDefault,
+    /// Forward to `template` with `{name}` replaced by the method name, e.g.
+    /// `inner.{name}` to call the same method on `self.inner`. Methods
+    /// without a receiver call the path as is, e.g. `Inner::{name}`.
+    Delegate(String),
+}
+
+/// The methods of `trait_decl` not defined in `block`, in trait order.
+pub fn missing_methods<'a>(trait_decl: &'a TraitDecl, block: &ImplBlockDecl) -> Vec<&'a TraitMethod> {
+    trait_decl.methods().filter(|m| block.method(m.name()).is_none()).collect()
+}
+
+/// Emit `block` with an explicit definition appended for every method of
+/// `trait_decl` it leaves out, so that a later pass sees every method.
+/// Default bodies are copied as written, so they must not name the trait's
+/// generic parameters.
+pub fn materialize_methods(
+    trait_decl: &TraitDecl,
+    block: &ImplBlockDecl,
+    how: &Materialize,
+) -> Result<String, ParseError> {
+    let mut complete = block.clone();
+    for method in missing_methods(trait_decl, block) {
+        let decl = match how {
+            Materialize::CopyDefault => match &method.body {
+                Some(body) => method.with_body(body),
+                None => {
+                    return Err(invalid(&format!(
+                        "`{}` has no default body and is not implemented for `{}`",
+                        method.name(),
+                        block.self_ty
+                    )))
+                }
+            },
+            Materialize::Delegate(template) => {
+                let mut stub = method.sig.clone();
+                stub.set_params(&method.sig.forwardable_params());
+                let call = stub.forwarding_call(&template.replace("{name}", method.name()));
+                stub.fn_body = if stub.async_str.is_empty() { call } else { format!("{}.await", call) };
+                stub
+            }
+        };
+        complete.items.push(ImplItem::Fn(decl));
+    }
+    Ok(checked(complete.render()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::presets::{materialize_methods, missing_methods, Materialize};
+    use crate::{ImplBlockDecl, TraitDecl};
+
+    const TRAIT: &str = "trait Store {
+    fn get(&self, k: u8) -> u8;
+    fn contains(&self, k: u8) -> bool { self.get(k) != 0 }
+    async fn flush(&mut self, (a, b): (u8, u8));
+}";
+
+    #[test]
+    fn test_materialize_methods() {
+        let trait_decl = TraitDecl::from_string(TRAIT.to_string());
+        let block = ImplBlockDecl::from_string("impl Store for Mem { fn get(&self, k: u8) -> u8 { self.0[k] } }".to_string());
+        let missing: Vec<&str> = missing_methods(&trait_decl, &block).iter().map(|m| m.name()).collect();
+        assert_eq!(missing, vec!["contains", "flush"]);
+        assert!(materialize_methods(&trait_decl, &block, &Materialize::CopyDefault).is_err());
+
+        let expected = "impl Store for Mem {
+    fn get(&self, k: u8) -> u8 {
+    self.0[k]
+    }
+
+    fn contains(&self, k: u8) -> bool {
+    self.inner.contains(k)
+    }
+
+    async fn flush(&mut self, __arg1: (u8, u8)) {
+    self.inner.flush(__arg1).await
+    }
+}";
+        let how = Materialize::Delegate("inner.{name}".to_string());
+        assert_eq!(materialize_methods(&trait_decl, &block, &how).unwrap(), expected);
+
+        let block = ImplBlockDecl::from_string(
+            "impl Store for Mem { fn get(&self, k: u8) -> u8 { 0 } async fn flush(&mut self, _: (u8, u8)) {} }".to_string(),
+        );
+        let out = materialize_methods(&trait_decl, &block, &Materialize::CopyDefault).unwrap();
+        assert!(out.ends_with("    fn contains(&self, k: u8) -> bool {\n    self.get(k) != 0\n    }\n}"));
+    }
+}
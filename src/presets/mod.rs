@@ -0,0 +1,120 @@
+//! Experimental code generators built on top of the decl types.
+//!
+//! Only available with the `unstable` feature; nothing in here is covered by
+//! semver guarantees.
+
+use crate::scan::is_ident_char;
+use crate::{Param, ParseError, ParseErrorKind};
+
+mod audit;
+mod authorize;
+mod batch;
+mod context;
+mod dispatch;
+mod dummy;
+mod facade;
+mod feature_gate;
+mod instrument;
+mod materialize;
+mod owned;
+mod paginate;
+mod per_target;
+mod proptest;
+mod rate_limit;
+mod shim;
+mod test_cases;
+mod test_twin;
+mod timeout;
+mod trace;
+mod transaction;
+mod type_map;
+mod unwind;
+mod unwrap;
+
+pub use audit::{audit_log, AuditLog};
+pub use authorize::{authorize, Authorize};
+pub use batch::{batch_variant, BatchMode};
+pub use context::{GenContext, InlineHint, OptimizeFor};
+pub use dispatch::{dispatch_table, dispatch_table_in, DispatchTable, Lookup};
+pub use dummy::{dummy_constructor, DummyValues};
+pub use facade::{facade_module, facade_module_in};
+pub use feature_gate::{feature_gated, FeatureStub};
+pub use instrument::instrument_impl;
+pub use materialize::{materialize_methods, missing_methods, Materialize};
+pub use owned::{owned_variant, owned_variant_in, OwnedTypes};
+pub use paginate::{paginated, Pagination};
+pub use per_target::per_target;
+pub use self::proptest::{proptest_roundtrip, Strategies};
+pub use rate_limit::{rate_limited, rate_limited_in, RateLimitBehavior};
+pub use shim::{versioned_shim, versioned_shim_in, ShimMapping};
+pub use test_cases::{test_cases, test_cases_from_attrs, TestHarness};
+pub use test_twin::{test_twin_mod_name, with_test_twin, TestCase};
+pub use timeout::{timeout, timeout_in, timeout_with};
+pub use trace::{trace_context, ContextSource, TraceContext};
+pub use transaction::transactional;
+pub use type_map::TypeMap;
+pub use unwind::catch_unwind_body;
+pub use unwrap::{unwrap_variant, unwrap_variant_in};
+
+pub(crate) use crate::verify::checked;
+
+/// How a preset picks out one of a function's parameters.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParamSelector {
+    /// The parameter with this attribute, e.g. `#[subject] user: &User`; the
+    /// attribute is removed from the output.
+    Attribute(String),
+    /// The parameter with this name.
+    Name(String),
+    /// The first parameter whose type contains this text, e.g. `User`.
+    TypePattern(String),
+}
+
+impl ParamSelector {
+    /// Index of the first named, non-receiver parameter matching, removing
+    /// the attribute for [`ParamSelector::Attribute`].
+    pub fn select(&self, params: &mut [Param]) -> Option<usize> {
+        params.iter_mut().position(|p| {
+            if p.is_receiver() || p.is_pattern {
+                return false;
+            }
+            match self {
+                ParamSelector::Attribute(path) => !p.remove_attributes(path).is_empty(),
+                ParamSelector::Name(name) => p.name == *name,
+                ParamSelector::TypePattern(pattern) => p.ty.contains(pattern.as_str()),
+            }
+        })
+    }
+}
+
+pub(crate) fn to_snake_case(name: &str) -> String {
+    let mut out = String::new();
+    for (idx, c) in name.char_indices() {
+        if c.is_uppercase() {
+            if idx > 0 && !out.ends_with('_') {
+                out.push('_');
+            }
+            out.extend(c.to_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Strip whitespace from a type so lookups don't depend on formatting,
+/// keeping one space between words, as in `&mut _` or `dyn Trait`.
+pub(crate) fn normalize_type(ty: &str) -> String {
+    let mut out = String::with_capacity(ty.len());
+    for word in ty.split_whitespace() {
+        if out.ends_with(is_ident_char) && word.starts_with(is_ident_char) {
+            out.push(' ');
+        }
+        out.push_str(word);
+    }
+    out
+}
+
+pub(crate) fn invalid(msg: &str) -> ParseError {
+    ParseError::new(ParseErrorKind::Invalid(msg.to_string()), 0)
+}
@@ -0,0 +1,191 @@
+use crate::presets::{checked, normalize_type, GenContext, TypeMap};
+use crate::{Decl, FunctionDecl, Param};
+
+/// Borrowed parameter types and the owned types that replace them in
+/// [`owned_variant`], with the conversion back (`{}` is the argument).
+///
+/// Both are [`TypeMap`]s keyed by the borrowed type, so a pattern such as
+/// `&[_]` may name the type its `_` matched as `{0}`, e.g. `Vec<{0}>`.
+#[derive(Clone, Debug)]
+pub struct OwnedTypes {
+    owned: TypeMap,
+    conversions: TypeMap,
+}
+
+impl Default for OwnedTypes {
+    fn default() -> Self {
+        OwnedTypes::empty()
+            .with("&str", "String", "{}.as_str()")
+            .with("&[u8]", "Vec<u8>", "{}.as_slice()")
+            .with("&Path", "PathBuf", "{}.as_path()")
+            .with("&OsStr", "OsString", "{}.as_os_str()")
+    }
+}
+
+impl OwnedTypes {
+    /// The [`GenContext`] type table of owned types read by
+    /// [`OwnedTypes::from_context`].
+    pub const TABLE: &'static str = "owned";
+    /// The [`GenContext`] type table of conversions back to the borrowed type.
+    pub const CONVERSION_TABLE: &'static str = "owned_conversion";
+
+    pub fn new() -> Self {
+        OwnedTypes::default()
+    }
+
+    /// An empty map with none of the default entries.
+    pub fn empty() -> Self {
+        OwnedTypes {
+            owned: TypeMap::new(),
+            conversions: TypeMap::new(),
+        }
+    }
+
+    /// The default entries extended with those configured in `ctx`'s
+    /// [`OwnedTypes::TABLE`] and [`OwnedTypes::CONVERSION_TABLE`] tables.
+    pub fn from_context(ctx: &GenContext) -> Self {
+        let mut types = OwnedTypes::default();
+        if let Some(owned) = ctx.types(Self::TABLE) {
+            types.owned.extend(owned.clone());
+        }
+        if let Some(conversions) = ctx.types(Self::CONVERSION_TABLE) {
+            types.conversions.extend(conversions.clone());
+        }
+        types
+    }
+
+    pub fn with(mut self, borrowed: &str, owned: &str, conversion: &str) -> Self {
+        let borrowed = normalize_borrow(borrowed);
+        self.owned = self.owned.with(&borrowed, owned);
+        self.conversions = self.conversions.with(&borrowed, conversion);
+        self
+    }
+
+    /// The owned type and conversion template for a borrowed type, if mapped.
+    pub fn lookup(&self, borrowed: &str) -> Option<(String, String)> {
+        let borrowed = normalize_borrow(borrowed);
+        // A pattern's `{0}` is the matched type itself.
+        let same = |ty: &str| Some(ty.to_string());
+        let owned = self.owned.lookup_with(&borrowed, same)?;
+        let conversion = self.conversions.lookup_with(&borrowed, same)?;
+        Some((owned, conversion))
+    }
+}
+
+/// Normalize whitespace and drop reference lifetimes (`&'a str` is `&str`).
+fn normalize_borrow(ty: &str) -> String {
+    let ty = ty.trim();
+    match ty.strip_prefix("&'") {
+        Some(rest) => normalize_type(&format!(
+            "&{}",
+            rest.trim_start_matches(|c: char| c == '_' || c.is_alphanumeric())
+        )),
+        None => normalize_type(ty),
+    }
+}
+
+/// Emit `{name}_owned`, a sibling of `decl` taking owned arguments in place of
+/// every mapped borrowed parameter and forwarding to the original.
+///
+/// Pattern parameters are bound to `__arg{index}` so they can be forwarded.
+/// Returns `None` if no parameter has a mapped borrowed type.
+pub fn owned_variant(decl: &FunctionDecl, types: &OwnedTypes) -> Option<String> {
+    let mut any_mapped = false;
+    let mut params = Vec::new();
+    let mut args = Vec::new();
+    for param in decl.forwardable_params() {
+        if param.is_receiver() {
+            params.push(param);
+            continue;
+        }
+        match types.lookup(&param.ty) {
+            Some((owned, conversion)) => {
+                any_mapped = true;
+                args.push(conversion.replace("{}", &param.name));
+                params.push(Param {
+                    ty: owned,
+                    ..param
+                });
+            }
+            _ => {
+                args.push(param.name.clone());
+                params.push(param);
+            }
+        }
+    }
+    if !any_mapped {
+        return None;
+    }
+
+    let mut variant = decl.clone();
+    variant.func_prologue = String::new();
+    decl.copy_cfgs_to(&mut variant);
+    variant.fn_name = format!("{}_owned", decl.fn_name);
+    variant.set_params(&params);
+    variant.fn_body = format!(
+        "{}{}({}){}",
+        if decl.receiver().is_some() { "self." } else { "" },
+        decl.fn_name,
+        args.join(", "),
+        if decl.async_str.is_empty() { "" } else { ".await" }
+    );
+    Some(checked(variant.render()))
+}
+
+/// [`owned_variant`] with the types configured in `ctx`; see
+/// [`OwnedTypes::from_context`].
+pub fn owned_variant_in(ctx: &GenContext, decl: &FunctionDecl) -> Option<String> {
+    owned_variant(decl, &OwnedTypes::from_context(ctx))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::presets::{owned_variant, owned_variant_in, GenContext, OwnedTypes, TypeMap};
+    use crate::FunctionDecl;
+
+    #[test]
+    fn test_owned_variant() {
+        let decl = FunctionDecl::from_string(
+            "#[my_macro]\npub fn lookup<'a>(&self, key: &'a str, data: &[u8], n: u32) -> Option<u8> { None }".to_string(),
+        );
+        let expected = "pub fn lookup_owned<'a>(&self, key: String, data: Vec<u8>, n: u32) -> Option<u8> {
+self.lookup(key.as_str(), data.as_slice(), n)
+}";
+        assert_eq!(owned_variant(&decl, &OwnedTypes::new()).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_owned_variant_async_custom_map() {
+        let decl = FunctionDecl::from_string("async fn store(item: &Item) {}".to_string());
+        assert!(owned_variant(&decl, &OwnedTypes::new()).is_none());
+        let types = OwnedTypes::empty().with("&Item", "Item", "&{}");
+        assert_eq!(
+            owned_variant(&decl, &types).unwrap(),
+            "async fn store_owned(item: Item) {\nstore(&item).await\n}"
+        );
+    }
+
+    #[test]
+    fn test_owned_variant_in_context() {
+        let ctx = GenContext::new()
+            .with_types(OwnedTypes::TABLE, TypeMap::new().with("&[_]", "Vec<{0}>").with("&Item", "Item"))
+            .with_types(OwnedTypes::CONVERSION_TABLE, TypeMap::new().with("&[_]", "&{}").with("&Item", "&{}"));
+        let decl = FunctionDecl::from_string("fn put(ids: &[u32], item: &'a Item, name: &str) {}".to_string());
+        assert_eq!(
+            owned_variant_in(&ctx, &decl).unwrap(),
+            "fn put_owned(ids: Vec<u32>, item: Item, name: String) {\nput(&ids, &item, name.as_str())\n}"
+        );
+        let types = OwnedTypes::from_context(&ctx);
+        assert_eq!(types.lookup("&[u8]"), Some(("Vec<u8>".to_string(), "{}.as_slice()".to_string())));
+        assert_eq!(OwnedTypes::new().lookup("&Item"), None);
+    }
+
+    #[test]
+    fn test_owned_variant_patterns() {
+        let decl = FunctionDecl::from_string("fn f(_: u8, (a, b): (u8, u8), mut s: &str) {}".to_string());
+        assert_eq!(
+            owned_variant(&decl, &OwnedTypes::new()).unwrap(),
+            "fn f_owned(__arg0: u8, __arg1: (u8, u8), s: String) {\nf(__arg0, __arg1, s.as_str())\n}"
+        );
+    }
+}
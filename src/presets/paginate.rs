@@ -0,0 +1,285 @@
+use crate::presets::{checked, invalid, ParamSelector};
+use crate::scan::{is_ident_char, split_top_level};
+use crate::{lifetimes_in, Decl, FunctionDecl, Param, ParseError, ReturnType, TypeKind};
+
+/// Configuration for [`paginated`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pagination {
+    pub limit: ParamSelector,
+    pub offset: ParamSelector,
+    /// The limit passed on every call; a short page ends the iteration.
+    pub page_size: String,
+}
+
+impl Default for Pagination {
+    fn default() -> Self {
+        Pagination {
+            limit: ParamSelector::Name("limit".to_string()),
+            offset: ParamSelector::Name("offset".to_string()),
+            page_size: "100".to_string(),
+        }
+    }
+}
+
+/// Emit `decl` (with any selector attributes removed) followed by a sibling
+/// yielding every item across pages: `{name}_iter` returning an `Iterator`
+/// for a sync fn, or `{name}_stream` returning a `futures::Stream` for an
+/// async one.
+///
+/// `decl` must return `Vec<T>` and take integer limit and offset parameters.
+/// The sibling takes the remaining parameters and passes them to each call
+/// through `Clone::clone`, pattern parameters bound to `__arg{index}`. If any
+/// of them borrows, the sibling declares a lifetime `'__p` that every
+/// borrow and generic parameter outlives and the returned iterator is bounded by.
+pub fn paginated(decl: &FunctionDecl, cfg: &Pagination) -> Result<String, ParseError> {
+    let item_ty = match decl.return_type().awaited() {
+        ReturnType::Other(ret) => match TypeKind::classify(&ret) {
+            TypeKind::Vec(item) => item,
+            _ => return Err(invalid(&format!("`{}` does not return a `Vec`", decl.fn_name))),
+        },
+        _ => return Err(invalid(&format!("`{}` does not return a `Vec`", decl.fn_name))),
+    };
+    let mut params = decl.params();
+    let limit = cfg.limit.select(&mut params);
+    let offset = cfg.offset.select(&mut params);
+    let (limit, offset) = match (limit, offset) {
+        (Some(l), Some(o)) if l != o => (l, o),
+        _ => return Err(invalid(&format!("`{}` has no limit and offset parameters", decl.fn_name))),
+    };
+    let offset_ty = params[offset].ty.clone();
+
+    let mut original = decl.clone();
+    original.set_params(&params);
+
+    // The sibling binds pattern parameters to `__arg{index}` so it can pass
+    // them on by name.
+    let params: Vec<Param> = params
+        .into_iter()
+        .enumerate()
+        .map(|(idx, p)| {
+            if p.is_pattern && !p.is_receiver() {
+                Param::new(&format!("__arg{}", idx), &p.ty)
+            } else {
+                p
+            }
+        })
+        .collect();
+    let args: Vec<String> = params
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| !p.is_receiver())
+        .map(|(idx, p)| {
+            if idx == limit {
+                cfg.page_size.clone()
+            } else if idx == offset {
+                "offset".to_string()
+            } else {
+                format!("Clone::clone(&{})", p.name)
+            }
+        })
+        .collect();
+    let call = format!(
+        "{}{}({})",
+        if decl.receiver().is_some() { "self." } else { "" },
+        decl.fn_name,
+        args.join(", ")
+    );
+
+    let mut sibling = original.clone();
+    sibling.func_prologue = String::new();
+    original.copy_cfgs_to(&mut sibling);
+    let mut kept: Vec<Param> = params
+        .iter()
+        .enumerate()
+        .filter(|(idx, _)| *idx != limit && *idx != offset)
+        .map(|(_, p)| p.clone())
+        .collect();
+    let borrows = kept
+        .iter()
+        .any(|p| p.has_elided_lifetime() || !lifetimes_in(&p.ty).is_empty());
+    let lifetime = if borrows {
+        // Every borrowed input must outlive the returned iterator, so they
+        // all get one named lifetime that the return type is bounded by.
+        for p in kept.iter_mut() {
+            if p.ty.is_empty() && p.is_receiver() {
+                p.name = name_elided_lifetimes(&p.name);
+            } else {
+                p.ty = name_elided_lifetimes(&p.ty);
+            }
+        }
+        outlive_iter_lifetime(&mut sibling);
+        format!(" + {}", ITER_LIFETIME)
+    } else {
+        String::new()
+    };
+    sibling.set_params(&kept);
+    if decl.async_str.is_empty() {
+        sibling.fn_name = format!("{}_iter", decl.fn_name);
+        sibling.ret_decl = format!("impl Iterator<Item = {}>{}", item_ty, lifetime);
+        sibling.fn_body = format!(
+            "let mut offset: {ty} = 0;
+let mut done = false;
+let mut page = Vec::new().into_iter();
+std::iter::from_fn(move || loop {{
+    if let Some(item) = page.next() {{
+        return Some(item);
+    }}
+    if done {{
+        return None;
+    }}
+    let next = {call};
+    done = next.len() < ({size}) as usize;
+    offset += next.len() as {ty};
+    page = next.into_iter();
+}})",
+            ty = offset_ty,
+            call = call,
+            size = cfg.page_size
+        );
+    } else {
+        sibling.async_str = String::new();
+        sibling.fn_name = format!("{}_stream", decl.fn_name);
+        sibling.ret_decl = format!("impl futures::Stream<Item = {}>{}", item_ty, lifetime);
+        let clones: String = params
+            .iter()
+            .enumerate()
+            .filter(|(idx, p)| !p.is_receiver() && *idx != limit && *idx != offset)
+            .map(|(_, p)| format!("    let {0} = Clone::clone(&{0});\n", p.name))
+            .collect();
+        sibling.fn_body = format!(
+            "futures::StreamExt::flatten(futures::stream::unfold((0 as {ty}, false), move |(offset, done)| {{
+{clones}    async move {{
+        if done {{
+            return None;
+        }}
+        let next = {call}.await;
+        let done = next.len() < ({size}) as usize;
+        let offset = offset + next.len() as {ty};
+        Some((futures::stream::iter(next), (offset, done)))
+    }}
+}}))",
+            ty = offset_ty,
+            clones = clones,
+            call = call,
+            size = cfg.page_size
+        );
+    }
+    Ok(checked(format!("{}\n\n{}", original.render(), sibling.render())))
+}
+
+const ITER_LIFETIME: &str = "'__p";
+
+/// `ty` with every elided lifetime (`&T`, `'_`) named [`ITER_LIFETIME`].
+fn name_elided_lifetimes(ty: &str) -> String {
+    let mut out = String::with_capacity(ty.len());
+    let mut rest = ty;
+    while let Some(c) = rest.chars().next() {
+        rest = &rest[c.len_utf8()..];
+        if c == '&' && !rest.trim_start().starts_with('\'') {
+            out.push_str(&format!("&{} ", ITER_LIFETIME));
+            rest = rest.trim_start();
+        } else if c == '\'' && rest.starts_with('_') && !rest[1..].starts_with(is_ident_char) {
+            out.push_str(ITER_LIFETIME);
+            rest = &rest[1..];
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Declare [`ITER_LIFETIME`] on `decl` and require every other generic
+/// parameter to outlive it.
+fn outlive_iter_lifetime(decl: &mut FunctionDecl) {
+    let inner = decl.generics.trim().trim_start_matches('<').trim_end_matches('>').trim();
+    let bounds: Vec<String> = split_top_level(inner, ',', true)
+        .into_iter()
+        .map(|p| p.trim())
+        .filter(|p| !p.is_empty() && !p.starts_with("const "))
+        .map(|p| {
+            let name = p.split(|c: char| c == ':' || c == '=' || c.is_whitespace()).next().unwrap_or(p);
+            format!("{}: {}", name, ITER_LIFETIME)
+        })
+        .collect();
+    decl.generics = if inner.is_empty() {
+        format!("<{}>", ITER_LIFETIME)
+    } else {
+        format!("<{}, {}>", ITER_LIFETIME, inner)
+    };
+    if bounds.is_empty() {
+        return;
+    }
+    decl.where_clause = if decl.where_clause.is_empty() {
+        format!("where {}", bounds.join(", "))
+    } else {
+        format!("{}, {}", decl.where_clause.trim_end().trim_end_matches(','), bounds.join(", "))
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::presets::{paginated, Pagination, ParamSelector};
+    use crate::FunctionDecl;
+
+    #[test]
+    fn test_paginated_iter() {
+        let decl = FunctionDecl::from_string(
+            "pub fn users(&self, filter: &Filter, limit: u32, offset: u64) -> Vec<User> { self.db.users(filter, limit, offset) }"
+                .to_string(),
+        );
+        let out = paginated(&decl, &Pagination::default()).unwrap();
+        let expected = "pub fn users_iter<'__p>(&'__p self, filter: &'__p Filter) -> impl Iterator<Item = User> + '__p {
+let mut offset: u64 = 0;
+let mut done = false;
+let mut page = Vec::new().into_iter();
+std::iter::from_fn(move || loop {
+    if let Some(item) = page.next() {
+        return Some(item);
+    }
+    if done {
+        return None;
+    }
+    let next = self.users(Clone::clone(&filter), 100, offset);
+    done = next.len() < (100) as usize;
+    offset += next.len() as u64;
+    page = next.into_iter();
+})
+}";
+        assert!(out.starts_with("pub fn users(&self, filter: &Filter, limit: u32, offset: u64) -> Vec<User> {"));
+        assert!(out.ends_with(expected));
+    }
+
+    #[test]
+    fn test_paginated_borrows() {
+        let decl = FunctionDecl::from_string(
+            "fn rows<'a, T: Key, const N: usize>(&mut self, keys: &'a [T; N], (lo, hi): (u8, u8), tag: Option<&str>, limit: u32, offset: u64) -> Vec<Row> where T: Clone { todo!() }"
+                .to_string(),
+        );
+        let out = paginated(&decl, &Pagination::default()).unwrap();
+        assert!(out.contains(
+            "fn rows_iter<'__p, 'a, T: Key, const N: usize>(&'__p mut self, keys: &'a [T; N], __arg2: (u8, u8), tag: Option<&'__p str>) -> impl Iterator<Item = Row> + '__p where T: Clone, 'a: '__p, T: '__p {"
+        ));
+        assert!(out.contains("let next = self.rows(Clone::clone(&keys), Clone::clone(&__arg2), Clone::clone(&tag), 100, offset);"));
+    }
+
+    #[test]
+    fn test_paginated_stream_by_attribute() {
+        let decl = FunctionDecl::from_string(
+            "async fn page(q: String, #[skip] from: usize, #[take] n: usize) -> Vec<Row> { fetch(q, from, n).await }"
+                .to_string(),
+        );
+        let cfg = Pagination {
+            limit: ParamSelector::Attribute("take".to_string()),
+            offset: ParamSelector::Attribute("skip".to_string()),
+            page_size: "PAGE".to_string(),
+        };
+        let out = paginated(&decl, &cfg).unwrap();
+        assert!(out.starts_with("async fn page(q: String, from: usize, n: usize) -> Vec<Row> {"));
+        assert!(out.contains("fn page_stream(q: String) -> impl futures::Stream<Item = Row> {"));
+        assert!(out.contains("unfold((0 as usize, false), move |(offset, done)| {\n    let q = Clone::clone(&q);\n"));
+        assert!(out.contains("let next = page(Clone::clone(&q), offset, PAGE).await;"));
+
+        let plain = FunctionDecl::from_string("fn count(limit: u8, offset: u8) -> u8 { 0 }".to_string());
+        assert!(paginated(&plain, &Pagination::default()).is_err());
+    }
+}
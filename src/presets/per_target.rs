@@ -0,0 +1,80 @@
+use std::collections::BTreeMap;
+
+use crate::presets::checked;
+use crate::{indent, Decl, FunctionDecl, SignatureMismatch, Visibility};
+
+/// Emit one public function dispatching to a per-OS body through
+/// `#[cfg(target_os = "...")]` blocks, keyed by `target_os` value.
+///
+/// Every decl must be signature-compatible with the others (see
+/// [`FunctionDecl::check_compatible`]); the first one's attributes are kept.
+/// Building for any other OS fails with a `compile_error!`. Returns `None` if
+/// `decls` is empty.
+pub fn per_target(decls: &BTreeMap<String, FunctionDecl>) -> Option<Result<String, SignatureMismatch>> {
+    let (_, first) = decls.iter().next()?;
+    for decl in decls.values() {
+        if let Err(e) = first.check_compatible(decl) {
+            return Some(Err(e));
+        }
+    }
+
+    let cfgs: Vec<String> = decls.keys().map(|os| format!("target_os = {:?}", os)).collect();
+    let mut blocks: Vec<String> = decls
+        .iter()
+        .zip(&cfgs)
+        .map(|((_, decl), cfg)| format!("#[cfg({})]\n{{\n{}\n}}", cfg, indent(&decl.fn_body)))
+        .collect();
+    blocks.push(format!(
+        "#[cfg(not(any({})))]\n{{\n    compile_error!({:?})\n}}",
+        cfgs.join(", "),
+        format!("`{}` is not supported on this target", first.fn_name)
+    ));
+
+    let mut dispatch = first.clone();
+    dispatch.vis = Visibility::Pub;
+    dispatch.fn_body = blocks.join("\n");
+    Some(Ok(checked(dispatch.render())))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::presets::per_target;
+    use crate::FunctionDecl;
+
+    #[test]
+    fn test_per_target() {
+        let mut decls = BTreeMap::new();
+        decls.insert(
+            "linux".to_string(),
+            FunctionDecl::from_string("fn page_size() -> usize { linux::page_size() }".to_string()),
+        );
+        decls.insert(
+            "macos".to_string(),
+            FunctionDecl::from_string("fn page_size() -> usize {\n    mac::vm_page_size()\n}".to_string()),
+        );
+        let expected = "pub fn page_size() -> usize {
+#[cfg(target_os = \"linux\")]
+{
+    linux::page_size()
+}
+#[cfg(target_os = \"macos\")]
+{
+    mac::vm_page_size()
+}
+#[cfg(not(any(target_os = \"linux\", target_os = \"macos\")))]
+{
+    compile_error!(\"`page_size` is not supported on this target\")
+}
+}";
+        assert_eq!(per_target(&decls).unwrap().unwrap(), expected);
+
+        decls.insert(
+            "windows".to_string(),
+            FunctionDecl::from_string("fn page_size() -> u32 { win::page_size() }".to_string()),
+        );
+        assert_eq!(per_target(&decls).unwrap().unwrap_err().part, "return type");
+        assert!(per_target(&BTreeMap::new()).is_none());
+    }
+}
@@ -0,0 +1,129 @@
+use crate::presets::{checked, to_snake_case, GenContext, TypeMap};
+use crate::StructDecl;
+
+/// Proptest strategy expressions keyed by field type.
+///
+/// Entries are a [`TypeMap`], so `Option<_>` may map to
+/// `proptest::option::of({0})`. Types without an entry fall back to
+/// `proptest::prelude::any::<T>()`.
+#[derive(Clone, Debug, Default)]
+pub struct Strategies {
+    map: TypeMap,
+}
+
+impl Strategies {
+    /// The [`GenContext`] type table read by [`Strategies::from_context`].
+    pub const TABLE: &'static str = "proptest";
+
+    pub fn new() -> Self {
+        Strategies::default()
+    }
+
+    /// The strategies configured in `ctx`'s [`Strategies::TABLE`] table.
+    pub fn from_context(ctx: &GenContext) -> Self {
+        Strategies::from(ctx.types(Self::TABLE).cloned().unwrap_or_default())
+    }
+
+    pub fn with(mut self, ty: &str, strategy_expr: &str) -> Self {
+        self.map = self.map.with(ty, strategy_expr);
+        self
+    }
+
+    pub fn strategy_for(&self, ty: &str) -> String {
+        self.map
+            .lookup_with(ty, |inner| Some(self.strategy_for(inner)))
+            .unwrap_or_else(|| format!("proptest::prelude::any::<{}>()", ty.trim()))
+    }
+}
+
+impl From<TypeMap> for Strategies {
+    fn from(map: TypeMap) -> Self {
+        Strategies { map }
+    }
+}
+
+/// Emit a `proptest!` block checking that every generated value of the
+/// struct survives `decode_expr(encode_expr(&value))` unchanged.
+///
+/// `encode_expr` is called with a reference to the value and `decode_expr`
+/// with whatever `encode_expr` returned; both may be paths or closures.
+pub fn proptest_roundtrip(
+    struct_decl: &StructDecl,
+    encode_expr: &str,
+    decode_expr: &str,
+    strategies: &Strategies,
+) -> String {
+    let bindings: Vec<String> = struct_decl
+        .fields
+        .iter()
+        .enumerate()
+        .map(|(idx, f)| {
+            if f.name.is_empty() {
+                format!("f{}", idx)
+            } else {
+                f.name.clone()
+            }
+        })
+        .collect();
+    let args = struct_decl
+        .fields
+        .iter()
+        .zip(&bindings)
+        .map(|(f, b)| format!("{} in {}", b, strategies.strategy_for(&f.ty)))
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    checked(format!(
+        "proptest::proptest! {{
+    #[test]
+    fn {}_roundtrip({}) {{
+        let value = {};
+        let encoded = ({})(&value);
+        let decoded = ({})(encoded);
+        proptest::prop_assert_eq!(decoded, value);
+    }}
+}}",
+        to_snake_case(&struct_decl.name),
+        args,
+        struct_decl.constructor(&bindings),
+        encode_expr,
+        decode_expr
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::presets::{proptest_roundtrip, Strategies};
+    use crate::StructDecl;
+
+    #[test]
+    fn test_proptest_roundtrip() {
+        let decl = StructDecl::from_string("struct UserRecord { id: u64, name: String }".to_string());
+        let strategies = Strategies::new().with("String", "\"[a-z]{1,8}\"");
+        assert_eq!(
+            strategies.clone().with("Option<_>", "proptest::option::of({0})").strategy_for("Option<String>"),
+            "proptest::option::of(\"[a-z]{1,8}\")"
+        );
+        let out = proptest_roundtrip(&decl, "encode", "|e| decode(e).unwrap()", &strategies);
+        let expected = "proptest::proptest! {
+    #[test]
+    fn user_record_roundtrip(id in proptest::prelude::any::<u64>(), name in \"[a-z]{1,8}\") {
+        let value = UserRecord { id, name };
+        let encoded = (encode)(&value);
+        let decoded = (|e| decode(e).unwrap())(encoded);
+        proptest::prop_assert_eq!(decoded, value);
+    }
+}";
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_proptest_roundtrip_tuple() {
+        let decl = StructDecl::from_string("struct Pair(u8, Vec<u8>);".to_string());
+        let out = proptest_roundtrip(&decl, "enc", "dec", &Strategies::new());
+        assert!(out.contains(
+            "fn pair_roundtrip(f0 in proptest::prelude::any::<u8>(), f1 in proptest::prelude::any::<Vec<u8>>())"
+        ));
+        assert!(out.contains("let value = Pair(f0, f1);"));
+    }
+}
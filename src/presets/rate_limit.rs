@@ -0,0 +1,98 @@
+use crate::presets::{checked, GenContext};
+use crate::{Decl, FunctionDecl, ReturnType};
+
+/// What a [`rate_limited`] function does when no permit is available.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RateLimitBehavior {
+    /// Block (or, in an async fn, await) until a permit is granted.
+    Wait,
+    /// Reject the call: return `Err(expr)` from a `Result` fn, `None` from an
+    /// `Option` fn, and panic otherwise.
+    Error(String),
+}
+
+/// Emit `decl` with its body guarded by a permit from `limiter_expr`.
+///
+/// The limiter must provide `acquire()`, returning a guard (awaited in async
+/// fns), for [`RateLimitBehavior::Wait`], and `try_acquire()`, returning a
+/// `Result` with the guard, for [`RateLimitBehavior::Error`]. The guard is held
+/// until the function returns.
+pub fn rate_limited(decl: &FunctionDecl, limiter_expr: &str, behavior: &RateLimitBehavior) -> String {
+    rate_limited_in(&mut GenContext::new(), decl, limiter_expr, behavior)
+}
+
+/// [`rate_limited`] honoring `ctx`; for code size the rejection panic goes
+/// through a shared helper.
+pub fn rate_limited_in(
+    ctx: &mut GenContext,
+    decl: &FunctionDecl,
+    limiter_expr: &str,
+    behavior: &RateLimitBehavior,
+) -> String {
+    let acquire = match behavior {
+        RateLimitBehavior::Wait => format!(
+            "let _permit = {}.acquire(){};",
+            limiter_expr,
+            if decl.async_str.is_empty() { "" } else { ".await" }
+        ),
+        RateLimitBehavior::Error(expr) => {
+            let reject = match decl.return_type() {
+                ReturnType::Result { .. } => format!("return Err({})", expr),
+                ReturnType::Option(_) => "return None".to_string(),
+                _ => ctx.panic_expr(&format!("`{}` rejected by rate limiter", decl.fn_name)),
+            };
+            format!(
+                "let _permit = match {}.try_acquire() {{\n    Ok(permit) => permit,\n    Err(_) => {},\n}};",
+                limiter_expr, reject
+            )
+        }
+    };
+    let mut limited = decl.clone();
+    limited.prepend_to_body(&acquire);
+    checked(limited.render())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::presets::{rate_limited, rate_limited_in, GenContext, OptimizeFor, RateLimitBehavior};
+    use crate::FunctionDecl;
+
+    #[test]
+    fn test_rate_limited_wait() {
+        let decl = FunctionDecl::from_string("async fn send(&self, m: Msg) { self.tx.send(m).await }".to_string());
+        let expected = "async fn send(&self, m: Msg) {
+let _permit = self.limiter.acquire().await;
+self.tx.send(m).await
+}";
+        assert_eq!(rate_limited(&decl, "self.limiter", &RateLimitBehavior::Wait), expected);
+    }
+
+    #[test]
+    fn test_rate_limited_reject() {
+        let decl = FunctionDecl::from_string("fn get(id: u8) -> Result<Item, ApiError> { fetch(id) }".to_string());
+        let expected = "fn get(id: u8) -> Result<Item, ApiError> {
+let _permit = match LIMITER.try_acquire() {
+    Ok(permit) => permit,
+    Err(_) => return Err(ApiError::RateLimited),
+};
+fetch(id)
+}";
+        let behavior = RateLimitBehavior::Error("ApiError::RateLimited".to_string());
+        assert_eq!(rate_limited(&decl, "LIMITER", &behavior), expected);
+
+        let decl = FunctionDecl::from_string("fn ping() -> u8 { 0 }".to_string());
+        assert!(rate_limited(&decl, "LIMITER", &behavior).contains("Err(_) => panic!(\"`ping` rejected by rate limiter\"),"));
+    }
+
+    #[test]
+    fn test_rate_limited_code_size() {
+        let mut ctx = GenContext::new().optimize_for(OptimizeFor::CodeSize);
+        let behavior = RateLimitBehavior::Error(String::new());
+        for name in ["a", "b"] {
+            let decl = FunctionDecl::from_string(format!("fn {}() {{}}", name));
+            let out = rate_limited_in(&mut ctx, &decl, "L", &behavior);
+            assert!(out.contains(&format!("Err(_) => __pmt_panic(\"`{}` rejected by rate limiter\"),", name)));
+        }
+        assert_eq!(ctx.take_helpers().matches("#[cold]").count(), 1);
+    }
+}
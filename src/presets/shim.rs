@@ -0,0 +1,143 @@
+use crate::presets::{checked, invalid, GenContext};
+use crate::{Attribute, Decl, FunctionDecl, ParseError};
+
+/// Configuration for [`versioned_shim`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ShimMapping {
+    /// An expression for each parameter of the new function, by name, in
+    /// terms of the old function's parameters, e.g. `("timeout", "Duration::from_secs(secs)")`.
+    /// Parameters not listed are passed through from an old parameter of the same name.
+    pub args: Vec<(String, String)>,
+    /// Maps the new function's result to the old return type; `{}` is the
+    /// call. Empty passes the result through unchanged.
+    pub ret: String,
+    /// Mark the shim `#[deprecated]` with this note.
+    pub deprecated: Option<String>,
+}
+
+impl ShimMapping {
+    pub fn new() -> Self {
+        ShimMapping::default()
+    }
+
+    pub fn with_arg(mut self, name: &str, expr: &str) -> Self {
+        self.args.push((name.to_string(), expr.to_string()));
+        self
+    }
+
+    pub fn with_ret(mut self, template: &str) -> Self {
+        self.ret = template.to_string();
+        self
+    }
+
+    pub fn deprecated(mut self, note: &str) -> Self {
+        self.deprecated = Some(note.to_string());
+        self
+    }
+}
+
+/// Emit `old` with its body replaced by a call to `new`, converting each
+/// argument and the result as described by `mapping`.
+///
+/// Errors if a parameter of `new` has neither a mapping nor an old parameter
+/// of the same name, if `new` takes `self` but `old` does not, or if `new` is
+/// async and `old` is not.
+pub fn versioned_shim(old: &FunctionDecl, new: &FunctionDecl, mapping: &ShimMapping) -> Result<String, ParseError> {
+    versioned_shim_in(&GenContext::new(), old, new, mapping)
+}
+
+/// [`versioned_shim`] with the shim annotated per `ctx`'s inline policy.
+pub fn versioned_shim_in(
+    ctx: &GenContext,
+    old: &FunctionDecl,
+    new: &FunctionDecl,
+    mapping: &ShimMapping,
+) -> Result<String, ParseError> {
+    if !new.async_str.is_empty() && old.async_str.is_empty() {
+        return Err(invalid(&format!("`{}` is async but `{}` is not", new.fn_name, old.fn_name)));
+    }
+    let old_params = old.params();
+    let new_params = new.params();
+    let new_has_self = new_params.iter().any(|p| p.is_receiver());
+    if new_has_self && !old_params.iter().any(|p| p.is_receiver()) {
+        return Err(invalid(&format!("`{}` takes `self` but `{}` does not", new.fn_name, old.fn_name)));
+    }
+
+    let mut args = Vec::new();
+    for param in new_params.iter().filter(|p| !p.is_receiver()) {
+        let mapped = mapping.args.iter().find(|(name, _)| *name == param.name).map(|(_, expr)| expr.clone());
+        let passed = old_params
+            .iter()
+            .find(|p| !p.is_pattern && !p.is_receiver() && p.name == param.name)
+            .map(|p| p.name.clone());
+        args.push(
+            mapped
+                .or(passed)
+                .ok_or_else(|| invalid(&format!("no mapping for parameter `{}` of `{}`", param.name, new.fn_name)))?,
+        );
+    }
+
+    let call = format!(
+        "{}{}({}){}",
+        if new_has_self { "self." } else { "" },
+        new.fn_name,
+        args.join(", "),
+        if new.async_str.is_empty() { "" } else { ".await" }
+    );
+    let mut shim = old.clone();
+    shim.fn_body = if mapping.ret.is_empty() {
+        call
+    } else {
+        mapping.ret.replace("{}", &call)
+    };
+    if let Some(note) = &mapping.deprecated {
+        let attr = Attribute::new("deprecated", &format!("note = {:?}", note));
+        if !shim.replace_attribute("deprecated", &attr) {
+            shim.insert_attribute(&attr);
+        }
+    }
+    ctx.annotate_inline(&mut shim);
+    Ok(checked(shim.render()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::presets::{versioned_shim, versioned_shim_in, GenContext, InlineHint, ShimMapping};
+    use crate::FunctionDecl;
+
+    #[test]
+    fn test_versioned_shim() {
+        let old = FunctionDecl::from_string(
+            "/// Connect\npub fn connect(&self, host: &str, secs: u64) -> Option<Conn> { todo!() }".to_string(),
+        );
+        let new = FunctionDecl::from_string(
+            "pub fn connect_v2(&self, host: &str, timeout: Duration, tls: bool) -> Result<Conn, Error> { todo!() }"
+                .to_string(),
+        );
+        let mapping = ShimMapping::new()
+            .with_arg("timeout", "Duration::from_secs(secs)")
+            .with_arg("tls", "false")
+            .with_ret("{}.ok()")
+            .deprecated("use connect_v2");
+        let expected = "/// Connect
+#[deprecated(note = \"use connect_v2\")]
+pub fn connect(&self, host: &str, secs: u64) -> Option<Conn> {
+self.connect_v2(host, Duration::from_secs(secs), false).ok()
+}";
+        assert_eq!(versioned_shim(&old, &new, &mapping).unwrap(), expected);
+        assert!(versioned_shim(&old, &new, &ShimMapping::new()).is_err());
+        let ctx = GenContext::new().inline_shims(InlineHint::Inline, 1);
+        assert!(versioned_shim_in(&ctx, &old, &new, &mapping)
+            .unwrap()
+            .starts_with("/// Connect\n#[deprecated(note = \"use connect_v2\")]\n#[inline]\npub fn connect("));
+    }
+
+    #[test]
+    fn test_versioned_shim_async() {
+        let old = FunctionDecl::from_string("async fn load(id: u32) -> Item { todo!() }".to_string());
+        let new = FunctionDecl::from_string("async fn load_v2(id: u32) -> Item { todo!() }".to_string());
+        assert!(versioned_shim(&old, &new, &ShimMapping::new()).unwrap().contains("load_v2(id).await"));
+        let sync_old = FunctionDecl::from_string("fn load(id: u32) -> Item { todo!() }".to_string());
+        assert!(versioned_shim(&sync_old, &new, &ShimMapping::new()).is_err());
+    }
+}
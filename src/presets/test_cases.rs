@@ -0,0 +1,169 @@
+use crate::presets::{checked, invalid};
+use crate::scan::{is_ident_char, split_top_level};
+use crate::{Attribute, Decl, FunctionDecl, Param, ParseError};
+
+/// The test attribute placed on each case generated by [`test_cases`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum TestHarness {
+    /// `#[test]`; async functions are rejected.
+    Std,
+    /// `#[tokio::test]`; the generated tests are always `async`.
+    Tokio,
+    /// Any other test attribute, e.g. `async_std::test`.
+    Custom { attr: String, is_async: bool },
+}
+
+impl TestHarness {
+    fn attribute(&self) -> Attribute {
+        match self {
+            TestHarness::Std => Attribute::new("test", ""),
+            TestHarness::Tokio => Attribute::new("tokio::test", ""),
+            TestHarness::Custom { attr, .. } => Attribute::new(attr, ""),
+        }
+    }
+
+    fn is_async(&self) -> bool {
+        match self {
+            TestHarness::Std => false,
+            TestHarness::Tokio => true,
+            TestHarness::Custom { is_async, .. } => *is_async,
+        }
+    }
+}
+
+/// Expand `decl` into one test per entry of `cases`, named `{fn}_case_1`,
+/// `{fn}_case_2` and so on. Each case gives one argument expression per
+/// parameter, bound with `let` at the top of a copy of the original body.
+///
+/// Errors if `decl` is generic, including through an `impl Trait`
+/// parameter, or a method, if a case has the wrong number of arguments, or
+/// if `decl` is async and `harness` is not.
+pub fn test_cases(decl: &FunctionDecl, cases: &[Vec<String>], harness: &TestHarness) -> Result<String, ParseError> {
+    let params = decl.params();
+    let impl_trait = |p: &Param| p.ty.split(|c: char| !is_ident_char(c)).any(|word| word == "impl");
+    if !decl.generics.is_empty() || params.iter().any(|p| p.is_receiver() || impl_trait(p)) {
+        return Err(invalid(&format!("`{}` must be a non-generic free function", decl.fn_name)));
+    }
+    if !decl.async_str.is_empty() && !harness.is_async() {
+        return Err(invalid(&format!("`{}` is async; use an async test harness", decl.fn_name)));
+    }
+    cases
+        .iter()
+        .enumerate()
+        .map(|(idx, args)| {
+            if args.len() != params.len() {
+                return Err(invalid(&format!(
+                    "case {} of `{}` has {} arguments, expected {}",
+                    idx + 1,
+                    decl.fn_name,
+                    args.len(),
+                    params.len()
+                )));
+            }
+            let mut test = decl.clone();
+            test.fn_name = format!("{}_case_{}", decl.fn_name, idx + 1);
+            test.fn_decl = "()".to_string();
+            test.async_str = if harness.is_async() { "async".to_string() } else { String::new() };
+            test.insert_attribute(&harness.attribute());
+            let bindings = params
+                .iter()
+                .zip(args)
+                .map(|(p, arg)| format!("let {}: {} = {};", p.pattern(), p.ty, arg.trim()))
+                .collect::<Vec<String>>();
+            if !bindings.is_empty() {
+                test.prepend_to_body(&bindings.join("\n"));
+            }
+            Ok(checked(test.render()))
+        })
+        .collect::<Result<Vec<String>, ParseError>>()
+        .map(|tests| tests.join("\n\n"))
+}
+
+/// Like [`test_cases`], taking one case per `#[attr_path(...)]` attribute
+/// on `decl`, e.g. `#[case(1, "one")]`. The remaining attributes, such as
+/// `#[should_panic]`, are kept on every generated test.
+pub fn test_cases_from_attrs(decl: &FunctionDecl, attr_path: &str, harness: &TestHarness) -> Result<String, ParseError> {
+    let mut decl = decl.clone();
+    let cases = decl
+        .remove_attributes(attr_path)
+        .iter()
+        .map(|attr| {
+            split_top_level(&attr.args, ',', true)
+                .into_iter()
+                .map(str::trim)
+                .filter(|a| !a.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .collect::<Vec<Vec<String>>>();
+    if cases.is_empty() {
+        return Err(invalid(&format!("`{}` has no `#[{}]` cases", decl.fn_name, attr_path)));
+    }
+    test_cases(&decl, &cases, harness)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::presets::{test_cases, test_cases_from_attrs, TestHarness};
+    use crate::FunctionDecl;
+
+    #[test]
+    fn test_cases_std() {
+        let decl = FunctionDecl::from_string(
+            "#[case(2, \"ab\")]\n#[case(0, \"\")]\n#[ignore]\nfn len_matches(n: usize, mut s: &str) { assert_eq!(s.len(), n); }"
+                .to_string(),
+        );
+        let expected = "#[ignore]
+#[test]
+fn len_matches_case_1() {
+let n: usize = 2;
+let mut s: &str = \"ab\";
+assert_eq!(s.len(), n);
+}
+
+#[ignore]
+#[test]
+fn len_matches_case_2() {
+let n: usize = 0;
+let mut s: &str = \"\";
+assert_eq!(s.len(), n);
+}";
+        assert_eq!(test_cases_from_attrs(&decl, "case", &TestHarness::Std).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_cases_async() {
+        let decl = FunctionDecl::from_string(
+            "async fn fetches(id: u64) -> Result<(), Error> { get(id).await?; Ok(()) }".to_string(),
+        );
+        let cases = vec![vec!["7".to_string()]];
+        assert!(test_cases(&decl, &cases, &TestHarness::Std).is_err());
+        let expected = "#[tokio::test]
+async fn fetches_case_1() -> Result<(), Error> {
+let id: u64 = 7;
+get(id).await?; Ok(())
+}";
+        assert_eq!(test_cases(&decl, &cases, &TestHarness::Tokio).unwrap(), expected);
+
+        let harness = TestHarness::Custom {
+            attr: "async_std::test".to_string(),
+            is_async: true,
+        };
+        let decl = FunctionDecl::from_string("fn no_args() { run(); }".to_string());
+        assert_eq!(
+            test_cases(&decl, &[vec![]], &harness).unwrap(),
+            "#[async_std::test]\nasync fn no_args_case_1() {\nrun();\n}"
+        );
+        assert!(test_cases(&decl, &[vec!["1".to_string()]], &harness).is_err());
+        assert!(test_cases_from_attrs(&decl, "case", &harness).is_err());
+    }
+
+    #[test]
+    fn test_cases_impl_trait() {
+        let cases = vec![vec!["\"a\"".to_string()]];
+        for src in ["fn shows(s: impl Display) {}", "fn shows(s: Box<impl Display>) {}"] {
+            let decl = FunctionDecl::from_string(src.to_string());
+            assert!(test_cases(&decl, &cases, &TestHarness::Std).is_err());
+        }
+    }
+}
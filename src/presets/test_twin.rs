@@ -0,0 +1,100 @@
+use crate::presets::{checked, to_snake_case};
+
+/// A single generated unit test for a `#[cfg(test)]` twin module.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TestCase {
+    pub name: String,
+    pub body: String,
+}
+
+impl TestCase {
+    pub fn new(name: &str, body: &str) -> Self {
+        TestCase {
+            name: name.to_string(),
+            body: body.to_string(),
+        }
+    }
+
+    /// A test asserting that `value_expr` survives a trip through `there_fn`
+    /// and `back_fn` (any callable expressions, e.g. `Bar::from`) unchanged.
+    pub fn roundtrip(name: &str, value_expr: &str, there_fn: &str, back_fn: &str) -> Self {
+        TestCase::new(
+            name,
+            &format!(
+                "let value = {};\nlet there = ({})(value.clone());\nlet back = ({})(there);\nassert_eq!(back, value);",
+                value_expr, there_fn, back_fn
+            ),
+        )
+    }
+}
+
+/// Name of the twin test module generated for `item_name`.
+pub fn test_twin_mod_name(item_name: &str) -> String {
+    format!("__{}_generated_tests", to_snake_case(item_name))
+}
+
+/// Emit `item` followed by a `#[cfg(test)]` module of `tests` for it.
+pub fn with_test_twin(item_name: &str, item: &str, tests: &[TestCase]) -> String {
+    if tests.is_empty() {
+        return item.to_string();
+    }
+    let tests = tests
+        .iter()
+        .map(|t| {
+            format!(
+                "    #[test]\n    fn {}() {{\n{}\n    }}\n",
+                t.name,
+                t.body
+                    .lines()
+                    .map(|l| format!("        {}", l))
+                    .collect::<Vec<String>>()
+                    .join("\n")
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+    checked(format!(
+        "{}\n\n#[cfg(test)]\nmod {} {{\n    use super::*;\n\n{}}}\n",
+        item.trim_end(),
+        test_twin_mod_name(item_name),
+        tests
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::presets::test_twin::{test_twin_mod_name, with_test_twin, TestCase};
+
+    #[test]
+    fn test_twin_mod_naming() {
+        assert_eq!(test_twin_mod_name("MyType"), "__my_type_generated_tests");
+        assert_eq!(test_twin_mod_name("do_thing"), "__do_thing_generated_tests");
+    }
+
+    #[test]
+    fn test_with_test_twin() {
+        let item = "impl From<Foo> for Bar { fn from(f: Foo) -> Bar { Bar(f.0) } }";
+        let out = with_test_twin(
+            "Bar",
+            item,
+            &[TestCase::roundtrip("foo_bar_roundtrip", "Foo(1)", "Bar::from", "Foo::from")],
+        );
+        let expected = "impl From<Foo> for Bar { fn from(f: Foo) -> Bar { Bar(f.0) } }
+
+#[cfg(test)]
+mod __bar_generated_tests {
+    use super::*;
+
+    #[test]
+    fn foo_bar_roundtrip() {
+        let value = Foo(1);
+        let there = (Bar::from)(value.clone());
+        let back = (Foo::from)(there);
+        assert_eq!(back, value);
+    }
+}
+";
+        assert_eq!(out, expected);
+        assert_eq!(with_test_twin("Bar", item, &[]), item);
+    }
+}
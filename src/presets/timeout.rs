@@ -0,0 +1,93 @@
+use crate::presets::{checked, GenContext};
+use crate::{indent, Decl, FunctionDecl};
+
+/// Emit async `decl` with its body raced against `tokio::time::timeout`; see
+/// [`timeout_with`].
+pub fn timeout(decl: &FunctionDecl, duration_expr: &str, on_timeout_expr: &str) -> Option<String> {
+    timeout_with(decl, "tokio::time::timeout", duration_expr, on_timeout_expr)
+}
+
+/// Emit async `decl` with its body turned into an `async move` block and run
+/// under `combinator`, called as `combinator(duration, future)` and resolving
+/// to `Err` on timeout.
+///
+/// On timeout a `Result` fn returns `Err(on_timeout_expr)`; any other fn
+/// panics. Returns `None` if `decl` is not async.
+pub fn timeout_with(
+    decl: &FunctionDecl,
+    combinator: &str,
+    duration_expr: &str,
+    on_timeout_expr: &str,
+) -> Option<String> {
+    timeout_in(&mut GenContext::new(), decl, combinator, duration_expr, on_timeout_expr)
+}
+
+/// [`timeout_with`] honoring `ctx`; for code size the timeout panic goes
+/// through a shared helper.
+pub fn timeout_in(
+    ctx: &mut GenContext,
+    decl: &FunctionDecl,
+    combinator: &str,
+    duration_expr: &str,
+    on_timeout_expr: &str,
+) -> Option<String> {
+    if decl.async_str.is_empty() {
+        return None;
+    }
+    let on_timeout = if decl.return_type().is_result() {
+        format!("return Err({})", on_timeout_expr)
+    } else {
+        ctx.panic_expr(&format!("`{}` timed out", decl.fn_name))
+    };
+    let mut limited = decl.clone();
+    limited.fn_body = format!(
+        "match {}({}, async move {{\n{}\n}})\n.await\n{{\n    Ok(v) => v,\n    Err(_) => {},\n}}",
+        combinator,
+        duration_expr,
+        indent(&decl.fn_body),
+        on_timeout
+    );
+    Some(checked(limited.render()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::presets::{timeout, timeout_in, timeout_with, GenContext, OptimizeFor};
+    use crate::FunctionDecl;
+
+    #[test]
+    fn test_timeout_result() {
+        let decl = FunctionDecl::from_string(
+            "async fn fetch(&self, id: u8) -> Result<Item, Error> { self.db.get(id).await }".to_string(),
+        );
+        let expected = "async fn fetch(&self, id: u8) -> Result<Item, Error> {
+match tokio::time::timeout(Duration::from_secs(5), async move {
+    self.db.get(id).await
+})
+.await
+{
+    Ok(v) => v,
+    Err(_) => return Err(Error::Timeout),
+}
+}";
+        assert_eq!(
+            timeout(&decl, "Duration::from_secs(5)", "Error::Timeout").unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_timeout_panic_and_sync() {
+        let decl = FunctionDecl::from_string("async fn tick() { step().await }".to_string());
+        let out = timeout_with(&decl, "async_std::future::timeout", "D", "E").unwrap();
+        assert!(out.contains("match async_std::future::timeout(D, async move {"));
+        assert!(out.contains("Err(_) => panic!(\"`tick` timed out\"),"));
+        let sync = FunctionDecl::from_string("fn tick() {}".to_string());
+        assert!(timeout(&sync, "D", "E").is_none());
+
+        let mut ctx = GenContext::new().optimize_for(OptimizeFor::CodeSize);
+        let out = timeout_in(&mut ctx, &decl, "timeout", "D", "E").unwrap();
+        assert!(out.contains("Err(_) => __pmt_panic(\"`tick` timed out\"),"));
+        assert!(ctx.take_helpers().contains("fn __pmt_panic("));
+    }
+}
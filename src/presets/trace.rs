@@ -0,0 +1,174 @@
+use crate::presets::{checked, invalid};
+use crate::{indent, Attribute, Decl, FunctionDecl, Param, ParseError};
+
+/// Where an instrumented function gets its trace context from.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ContextSource {
+    /// An extra parameter inserted after any receiver.
+    Param { name: String, ty: String },
+    /// A tokio `task_local!` key, read at entry and scoped around the body.
+    TaskLocal { key: String },
+}
+
+/// Configuration for [`trace_context`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct TraceContext {
+    pub source: ContextSource,
+    /// Expression deriving the function's own context from its caller's;
+    /// `{ctx}` is the caller's context and `{fn}` the function name. Empty to
+    /// pass the context through unchanged.
+    pub child: String,
+}
+
+impl TraceContext {
+    pub fn param(name: &str, ty: &str) -> Self {
+        TraceContext {
+            source: ContextSource::Param {
+                name: name.to_string(),
+                ty: ty.to_string(),
+            },
+            child: String::new(),
+        }
+    }
+
+    pub fn task_local(key: &str) -> Self {
+        TraceContext {
+            source: ContextSource::TaskLocal { key: key.to_string() },
+            child: String::new(),
+        }
+    }
+
+    pub fn with_child(mut self, template: &str) -> Self {
+        self.child = template.to_string();
+        self
+    }
+
+    /// Read the configuration from attribute arguments, either
+    /// `param = "ctx", ty = "&Ctx"` or `task_local = "KEY"`, optionally with
+    /// `child = "..."`.
+    pub fn from_attribute(attr: &Attribute) -> Result<Self, ParseError> {
+        let args = attr.parse_args()?;
+        if let Some(key) = args.unknown_keys(&["param", "ty", "task_local", "child"]).first() {
+            return Err(invalid(&format!("unknown trace argument `{}`", key)));
+        }
+        let source = match (args.get_str("param"), args.get_str("ty"), args.get_str("task_local")) {
+            (Some(name), Some(ty), None) => ContextSource::Param { name, ty },
+            (None, None, Some(key)) => ContextSource::TaskLocal { key },
+            _ => return Err(invalid("expected either `param` and `ty`, or `task_local`")),
+        };
+        Ok(TraceContext {
+            source,
+            child: args.get_str("child").unwrap_or_default(),
+        })
+    }
+
+    fn child_of(&self, ctx: &str, fn_name: &str) -> String {
+        self.child.replace("{ctx}", ctx).replace("{fn}", fn_name)
+    }
+
+    /// A call from `decl` (an instrumented function) to the instrumented
+    /// function `target`, passing the context along. `None` if a parameter
+    /// pattern of `decl` discards part of its value; see [`Param::forward_arg`].
+    pub fn forward_call(&self, decl: &FunctionDecl, target: &str) -> Option<String> {
+        let args = match &self.source {
+            ContextSource::Param { name, .. } => std::iter::once(Some(name.clone()))
+                .chain(
+                    decl.params()
+                        .iter()
+                        .filter(|p| !p.is_receiver() && p.name != *name)
+                        .map(|p| p.forward_arg()),
+                )
+                .collect::<Option<Vec<String>>>()?
+                .join(", "),
+            ContextSource::TaskLocal { .. } => decl.forward_args()?,
+        };
+        let path = if decl.receiver().is_some() { "self." } else { "" };
+        let await_str = if decl.async_str.is_empty() { "" } else { ".await" };
+        Some(format!("{}{}({}){}", path, target, args, await_str))
+    }
+}
+
+/// Instrument `decl` to receive a trace context on entry, per `cfg`.
+///
+/// With [`ContextSource::Param`] the context parameter is added to the
+/// signature and rebound to its child at entry. With
+/// [`ContextSource::TaskLocal`] the current value (or its `Default`) is read
+/// and the body runs inside `scope`/`sync_scope` with the child context.
+/// Use [`TraceContext::forward_call`] to generate calls between instrumented
+/// functions.
+pub fn trace_context(decl: &FunctionDecl, cfg: &TraceContext) -> String {
+    let mut traced = decl.clone();
+    match &cfg.source {
+        ContextSource::Param { name, ty } => {
+            let mut params = decl.params();
+            let at = params.iter().take_while(|p| p.is_receiver()).count();
+            params.insert(at, Param::new(name, ty));
+            traced.set_params(&params);
+            if !cfg.child.is_empty() {
+                traced.fn_body = format!("let {} = {};\n{}", name, cfg.child_of(name, &decl.fn_name), decl.fn_body);
+            }
+        }
+        ContextSource::TaskLocal { key } => {
+            let mut entry = format!("let __trace_ctx = {}.try_with(|c| c.clone()).unwrap_or_default();\n", key);
+            if !cfg.child.is_empty() {
+                entry.push_str(&format!(
+                    "let __trace_ctx = {};\n",
+                    cfg.child_of("__trace_ctx", &decl.fn_name)
+                ));
+            }
+            traced.fn_body = if decl.async_str.is_empty() {
+                format!("{}{}.sync_scope(__trace_ctx, || {{\n{}\n}})", entry, key, indent(&decl.fn_body))
+            } else {
+                format!(
+                    "{}{}.scope(__trace_ctx, async move {{\n{}\n}})\n.await",
+                    entry,
+                    key,
+                    indent(&decl.fn_body)
+                )
+            };
+        }
+    }
+    checked(traced.render())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::presets::{trace_context, TraceContext};
+    use crate::{Attribute, FunctionDecl};
+
+    #[test]
+    fn test_trace_context_param() {
+        let attr = Attribute::from_string("#[traced(param = \"ctx\", ty = \"&Span\", child = \"{ctx}.child(\\\"{fn}\\\")\")]".to_string());
+        let cfg = TraceContext::from_attribute(&attr).unwrap();
+        assert_eq!(cfg, TraceContext::param("ctx", "&Span").with_child("{ctx}.child(\"{fn}\")"));
+
+        let decl = FunctionDecl::from_string("async fn fetch(&self, id: u64) -> Item { self.load(id).await }".to_string());
+        let expected = "async fn fetch(&self, ctx: &Span, id: u64) -> Item {
+let ctx = ctx.child(\"fetch\");
+self.load(id).await
+}";
+        assert_eq!(trace_context(&decl, &cfg), expected);
+        let traced = FunctionDecl::from_string(trace_context(&decl, &cfg));
+        assert_eq!(cfg.forward_call(&traced, "load").as_deref(), Some("self.load(ctx, id).await"));
+    }
+
+    #[test]
+    fn test_trace_context_task_local() {
+        let cfg = TraceContext::task_local("TRACE");
+        let decl = FunctionDecl::from_string("fn step(n: u8) -> u8 { n + 1 }".to_string());
+        let expected = "fn step(n: u8) -> u8 {
+let __trace_ctx = TRACE.try_with(|c| c.clone()).unwrap_or_default();
+TRACE.sync_scope(__trace_ctx, || {
+    n + 1
+})
+}";
+        assert_eq!(trace_context(&decl, &cfg), expected);
+        assert_eq!(cfg.forward_call(&decl, "next").as_deref(), Some("next(n)"));
+        let pats = FunctionDecl::from_string("fn f((mut a, b): (u8, u8)) {}".to_string());
+        assert_eq!(cfg.forward_call(&pats, "g").as_deref(), Some("g((a, b))"));
+        let wild = FunctionDecl::from_string("fn f(ctx: &Span, _: u8) {}".to_string());
+        assert_eq!(cfg.forward_call(&wild, "g"), None);
+        assert_eq!(TraceContext::param("ctx", "&Span").forward_call(&wild, "g"), None);
+        assert!(TraceContext::from_attribute(&Attribute::new("traced", "param = \"ctx\"")).is_err());
+    }
+}
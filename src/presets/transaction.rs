@@ -0,0 +1,66 @@
+use crate::presets::{catch_unwind_body, checked};
+use crate::{Decl, FunctionDecl};
+
+/// Emit `decl` with its body run between `begin_expr` and `commit_expr`.
+///
+/// `rollback_expr` runs instead of the commit if the body returns `Err` (for
+/// a `Result` fn) or panics; the panic is then resumed. Each expression is
+/// used as a statement, so async ones should include their own `.await`.
+pub fn transactional(decl: &FunctionDecl, begin_expr: &str, commit_expr: &str, rollback_expr: &str) -> String {
+    let arms = if decl.return_type().is_result() {
+        format!(
+            "    Ok(Ok(v)) => {{\n        {};\n        Ok(v)\n    }}\n    Ok(Err(e)) => {{\n        {};\n        Err(e)\n    }}\n",
+            commit_expr, rollback_expr
+        )
+    } else {
+        format!("    Ok(v) => {{\n        {};\n        v\n    }}\n", commit_expr)
+    };
+    let mut tx = decl.clone();
+    tx.fn_body = format!(
+        "{};\nmatch {} {{\n{}    Err(panic) => {{\n        {};\n        std::panic::resume_unwind(panic)\n    }}\n}}",
+        begin_expr,
+        catch_unwind_body(decl),
+        arms,
+        rollback_expr
+    );
+    checked(tx.render())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::presets::transactional;
+    use crate::FunctionDecl;
+
+    #[test]
+    fn test_transactional_result() {
+        let decl = FunctionDecl::from_string("fn save(db: &Db, row: Row) -> Result<(), Error> { db.insert(row) }".to_string());
+        let expected = "fn save(db: &Db, row: Row) -> Result<(), Error> {
+db.begin();
+match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+    db.insert(row)
+})) {
+    Ok(Ok(v)) => {
+        db.commit();
+        Ok(v)
+    }
+    Ok(Err(e)) => {
+        db.rollback();
+        Err(e)
+    }
+    Err(panic) => {
+        db.rollback();
+        std::panic::resume_unwind(panic)
+    }
+}
+}";
+        assert_eq!(transactional(&decl, "db.begin()", "db.commit()", "db.rollback()"), expected);
+    }
+
+    #[test]
+    fn test_transactional_plain() {
+        let decl = FunctionDecl::from_string("fn bump(db: &Db) -> u8 { db.incr() }".to_string());
+        let out = transactional(&decl, "db.begin()", "db.commit()", "db.rollback()");
+        assert!(out.contains("    Ok(v) => {\n        db.commit();\n        v\n    }\n    Err(panic) => {"));
+        assert!(!out.contains("Ok(Err(e))"));
+    }
+}
@@ -0,0 +1,229 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::presets::normalize_type;
+use crate::scan::is_ident_char;
+
+type Fallback = Arc<dyn Fn(&str) -> Option<String> + Send + Sync>;
+
+/// A type-to-expression table shared by the presets that need per-type
+/// code: [`crate::presets::DummyValues`], [`crate::presets::Strategies`] and
+/// [`crate::presets::OwnedTypes`].
+///
+/// Lookups try, in order:
+/// - exact entries, matched ignoring whitespace;
+/// - patterns, whose `_` stands for any one type, e.g. `Option<_>` or
+///   `HashMap<String, _>`; later patterns win over earlier ones;
+/// - fallback handlers, in the order added.
+///
+/// In a template, `{ty}` is replaced by the looked-up type and `{0}`, `{1}`,
+/// ... by the expression for the type each `_` matched, resolved by the
+/// consumer (see [`TypeMap::lookup_with`]).
+///
+/// Configure the tables once on a [`crate::presets::GenContext`] with
+/// [`crate::presets::GenContext::with_types`] and every preset built from
+/// that context uses them.
+#[derive(Clone, Default)]
+pub struct TypeMap {
+    exact: HashMap<String, String>,
+    patterns: Vec<(String, String)>,
+    fallbacks: Vec<Fallback>,
+}
+
+impl TypeMap {
+    pub fn new() -> Self {
+        TypeMap::default()
+    }
+
+    /// Map `ty` to `template`; `ty` is a pattern if it contains a `_` type.
+    pub fn with(mut self, ty: &str, template: &str) -> Self {
+        let ty = normalize_type(ty);
+        if wildcards(&ty).next().is_some() {
+            self.patterns.retain(|(p, _)| *p != ty);
+            self.patterns.push((ty, template.to_string()));
+        } else {
+            self.exact.insert(ty, template.to_string());
+        }
+        self
+    }
+
+    /// Add a handler for types no entry or pattern matches, returning the
+    /// expression or `None` to pass.
+    pub fn with_fallback<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&str) -> Option<String> + Send + Sync + 'static,
+    {
+        self.fallbacks.push(Arc::new(handler));
+        self
+    }
+
+    /// Add every entry, pattern and fallback of `other`, its entries
+    /// replacing any for the same type.
+    pub fn extend(&mut self, other: TypeMap) {
+        self.exact.extend(other.exact);
+        for (pattern, template) in other.patterns {
+            self.patterns.retain(|(p, _)| *p != pattern);
+            self.patterns.push((pattern, template));
+        }
+        self.fallbacks.extend(other.fallbacks);
+    }
+
+    /// The expression for `ty`, resolving pattern captures with this map only.
+    pub fn lookup(&self, ty: &str) -> Option<String> {
+        self.lookup_with(ty, |inner| self.lookup(inner))
+    }
+
+    /// The expression for `ty`, resolving the types captured by a pattern
+    /// with `resolve`; a pattern whose captures do not resolve is skipped.
+    /// A capture of the whole type, as by a bare `_`, is never resolved, so
+    /// such a pattern only matches if its template does not use it.
+    pub fn lookup_with<F>(&self, ty: &str, resolve: F) -> Option<String>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let ty = ty.trim();
+        let normalized = normalize_type(ty);
+        if let Some(template) = self.exact.get(&normalized) {
+            return Some(template.replace("{ty}", ty));
+        }
+        for (pattern, template) in self.patterns.iter().rev() {
+            let captures = match match_pattern(pattern, &normalized) {
+                Some(captures) => captures,
+                None => continue,
+            };
+            let mut out = template.replace("{ty}", ty);
+            let mut resolved = true;
+            for (idx, capture) in captures.iter().enumerate() {
+                let placeholder = format!("{{{}}}", idx);
+                if !out.contains(&placeholder) {
+                    continue;
+                }
+                let expr = if *capture == normalized { None } else { resolve(capture) };
+                match expr {
+                    Some(expr) => out = out.replace(&placeholder, &expr),
+                    None => resolved = false,
+                }
+            }
+            if resolved {
+                return Some(out);
+            }
+        }
+        self.fallbacks.iter().find_map(|handler| handler(ty))
+    }
+}
+
+impl fmt::Debug for TypeMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TypeMap")
+            .field("exact", &self.exact)
+            .field("patterns", &self.patterns)
+            .field("fallbacks", &self.fallbacks.len())
+            .finish()
+    }
+}
+
+/// Byte indices of the `_` types in a normalized pattern.
+fn wildcards(pattern: &str) -> impl Iterator<Item = usize> + '_ {
+    pattern.match_indices('_').map(|(idx, _)| idx).filter(move |&idx| {
+        let before = pattern[..idx].chars().next_back();
+        let after = pattern[idx + 1..].chars().next();
+        !matches!(before, Some(c) if is_ident_char(c)) && !matches!(after, Some(c) if is_ident_char(c))
+    })
+}
+
+/// The types matched by each `_` of `pattern` in `ty`, both normalized.
+fn match_pattern(pattern: &str, ty: &str) -> Option<Vec<String>> {
+    let mut captures = Vec::new();
+    let (mut p, mut t) = (0, 0);
+    for wildcard in wildcards(pattern) {
+        let literal = &pattern[p..wildcard];
+        if !ty[t..].starts_with(literal) {
+            return None;
+        }
+        t += literal.len();
+        let len = type_len(&ty[t..]);
+        if len == 0 {
+            return None;
+        }
+        captures.push(ty[t..t + len].to_string());
+        t += len;
+        p = wildcard + 1;
+    }
+    if pattern[p..] == ty[t..] {
+        Some(captures)
+    } else {
+        None
+    }
+}
+
+/// Length of the type at the start of `src`, up to the first top-level `,`,
+/// `;`, `>`, `)` or `]`.
+fn type_len(src: &str) -> usize {
+    let mut depth = 0i32;
+    let mut prev = ' ';
+    for (idx, c) in src.char_indices() {
+        match c {
+            '<' | '(' | '[' => depth += 1,
+            '>' if prev == '-' => {}
+            '>' | ')' | ']' if depth == 0 => return idx,
+            ',' | ';' if depth == 0 => return idx,
+            '>' | ')' | ']' => depth -= 1,
+            _ => {}
+        }
+        prev = c;
+    }
+    src.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::presets::TypeMap;
+
+    #[test]
+    fn test_type_map() {
+        let map = TypeMap::new()
+            .with("String", "{ty}::new()")
+            .with("u8", "0")
+            .with("Option<_>", "Some({0})")
+            .with("HashMap<String, _>", "map_of({0})")
+            .with("Vec<_>", "vec![{0}]")
+            .with_fallback(|ty| if ty.starts_with('&') { Some("todo!()".to_string()) } else { None });
+        assert_eq!(map.lookup("String").as_deref(), Some("String::new()"));
+        assert_eq!(map.lookup("Option<u8>").as_deref(), Some("Some(0)"));
+        assert_eq!(map.lookup("Option< Vec<u8> >").as_deref(), Some("Some(vec![0])"));
+        assert_eq!(map.lookup("HashMap<String, Option<u8>>").as_deref(), Some("map_of(Some(0))"));
+        assert_eq!(map.lookup("HashMap<u8, u8>"), None);
+        assert_eq!(map.lookup("Option<Uuid>"), None);
+        assert_eq!(map.lookup("Option<fn() -> u8>"), None);
+        assert_eq!(map.lookup("&str").as_deref(), Some("todo!()"));
+        assert_eq!(map.lookup("my_type"), None);
+        assert_eq!(map.lookup_with("Vec<Uuid>", |_| Some("x".to_string())).as_deref(), Some("vec![x]"));
+
+        let refs = TypeMap::new()
+            .with("u8", "0")
+            .with("&mut _", "&mut {0}")
+            .with("Box<dyn _>", "boxed::<dyn {0}>()")
+            .with("&mut String", "&mut s");
+        assert_eq!(refs.lookup("&mut u8").as_deref(), Some("&mut 0"));
+        assert_eq!(refs.lookup("& mut  String").as_deref(), Some("&mut s"));
+        assert_eq!(refs.lookup("Box<dyn Fn()>"), None);
+        assert_eq!(
+            refs.lookup_with("Box< dyn Any >", |ty| Some(ty.to_string())).as_deref(),
+            Some("boxed::<dyn Any>()")
+        );
+
+        let mut merged = TypeMap::new().with("u8", "1").with("Option<_>", "None");
+        merged.extend(map);
+        assert_eq!(merged.lookup("u8").as_deref(), Some("0"));
+        assert_eq!(merged.lookup("Option<u8>").as_deref(), Some("Some(0)"));
+
+        let catch_all = TypeMap::new().with("u8", "0").with("_", "wrap({0})").with("Vec<_>", "vec![{0}]");
+        assert_eq!(catch_all.lookup("Uuid"), None);
+        assert_eq!(catch_all.lookup("Vec<Uuid>"), None);
+        assert_eq!(catch_all.lookup("Vec<u8>").as_deref(), Some("vec![0]"));
+        let default = TypeMap::new().with("_", "Default::default()").with("Vec<_>", "vec![{0}]");
+        assert_eq!(default.lookup("Uuid").as_deref(), Some("Default::default()"));
+        assert_eq!(default.lookup("Vec<Uuid>").as_deref(), Some("vec![Default::default()]"));
+    }
+}
@@ -0,0 +1,38 @@
+use crate::{indent, FunctionDecl};
+
+/// An expression running `decl`'s body and catching any panic, evaluating to
+/// `Result<T, Box<dyn Any + Send>>` where `T` is the body's value.
+///
+/// Async bodies go through `futures::FutureExt::catch_unwind`, so the
+/// generated code needs the `futures` crate. `return` inside the body returns
+/// from the caught block only, which keeps the value but skips what follows.
+pub fn catch_unwind_body(decl: &FunctionDecl) -> String {
+    if decl.async_str.is_empty() {
+        format!(
+            "std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {{\n{}\n}}))",
+            indent(&decl.fn_body)
+        )
+    } else {
+        format!(
+            "futures::FutureExt::catch_unwind(std::panic::AssertUnwindSafe(async move {{\n{}\n}}))\n.await",
+            indent(&decl.fn_body)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::presets::catch_unwind_body;
+    use crate::FunctionDecl;
+
+    #[test]
+    fn test_catch_unwind_body() {
+        let decl = FunctionDecl::from_string("fn f() -> u8 { g() }".to_string());
+        assert_eq!(
+            catch_unwind_body(&decl),
+            "std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {\n    g()\n}))"
+        );
+        let decl = FunctionDecl::from_string("async fn f() -> u8 { g().await }".to_string());
+        assert!(catch_unwind_body(&decl).ends_with("async move {\n    g().await\n}))\n.await"));
+    }
+}
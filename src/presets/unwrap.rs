@@ -0,0 +1,150 @@
+use crate::presets::{checked, GenContext};
+use crate::{Decl, FunctionDecl};
+
+/// Emit an infallible sibling of a `Result`-returning function that panics
+/// with a contextual message on `Err`.
+///
+/// A function named `try_x` gets a sibling `x`; any other name gets an
+/// `_unwrap` suffix. `panic_msg_template` may use `{fn}` (the original
+/// function name), `{args}` (each named argument as `name = {:?}`) and `{err}`
+/// (the error, `{:?}`-formatted); all other braces are literal. Arguments and
+/// errors must therefore implement `Debug`. Pattern parameters are bound to
+/// `__arg{index}` so they can be forwarded, and left out of `{args}`.
+///
+/// Returns `None` if the function does not return a `Result`.
+pub fn unwrap_variant(decl: &FunctionDecl, panic_msg_template: &str) -> Option<String> {
+    unwrap_variant_in(&mut GenContext::new(), decl, panic_msg_template)
+}
+
+/// [`unwrap_variant`] honoring `ctx`; for code size the panic goes through a
+/// shared helper.
+pub fn unwrap_variant_in(ctx: &mut GenContext, decl: &FunctionDecl, panic_msg_template: &str) -> Option<String> {
+    let ok_ty = decl.return_type().ok_type()?.to_string();
+
+    let named: Vec<String> = decl
+        .params()
+        .into_iter()
+        .filter(|p| !p.is_receiver() && !p.is_pattern)
+        .map(|p| p.name)
+        .collect();
+    let uses_args = panic_msg_template.contains("{args}");
+
+    let mut fmt = String::new();
+    let mut fmt_args = Vec::new();
+    let mut rest = panic_msg_template;
+    while let Some(idx) = rest.find('{') {
+        fmt.push_str(&escape_braces(&rest[..idx]));
+        let tail = &rest[idx..];
+        if let Some(after) = tail.strip_prefix("{fn}") {
+            fmt.push_str(&escape_braces(&decl.fn_name));
+            rest = after;
+        } else if let Some(after) = tail.strip_prefix("{args}") {
+            fmt.push_str("{}");
+            fmt_args.push("__args");
+            rest = after;
+        } else if let Some(after) = tail.strip_prefix("{err}") {
+            fmt.push_str("{:?}");
+            fmt_args.push("e");
+            rest = after;
+        } else {
+            fmt.push_str("{{");
+            rest = &tail[1..];
+        }
+    }
+    fmt.push_str(&escape_braces(rest));
+
+    let capture = if uses_args {
+        format!(
+            "let __args = format!(\"{}\"{});\n",
+            named.iter().map(|n| format!("{} = {{:?}}", n)).collect::<Vec<String>>().join(", "),
+            named.iter().map(|n| format!(", {}", n)).collect::<String>()
+        )
+    } else {
+        String::new()
+    };
+
+    let mut variant = decl.clone();
+    variant.func_prologue = String::new();
+    decl.copy_cfgs_to(&mut variant);
+    variant.set_params(&decl.forwardable_params());
+    let call = format!(
+        "{}{}",
+        variant.forwarding_call(&decl.fn_name),
+        if decl.async_str.is_empty() { "" } else { ".await" }
+    );
+    variant.fn_name = match decl.fn_name.strip_prefix("try_") {
+        Some(base) => base.to_string(),
+        None => format!("{}_unwrap", decl.fn_name),
+    };
+    variant.ret_decl = ok_ty;
+    let panic = ctx.panic_fmt_expr(
+        &format!("\"{}\"", fmt),
+        &fmt_args.iter().map(|a| format!(", {}", a)).collect::<String>(),
+    );
+    variant.fn_body = format!("{}match {} {{\n    Ok(v) => v,\n    Err(e) => {},\n}}", capture, call, panic);
+    Some(checked(variant.render()))
+}
+
+fn escape_braces(s: &str) -> String {
+    s.replace('{', "{{").replace('}', "}}").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::presets::{unwrap_variant, unwrap_variant_in, GenContext, OptimizeFor};
+    use crate::FunctionDecl;
+
+    #[test]
+    fn test_unwrap_variant_try_prefix() {
+        let decl = FunctionDecl::from_string(
+            "pub fn try_parse(&self, input: &str, radix: u32) -> Result<u64, ParseIntError> { todo!() }".to_string(),
+        );
+        let expected = "pub fn parse(&self, input: &str, radix: u32) -> u64 {
+let __args = format!(\"input = {:?}, radix = {:?}\", input, radix);
+match self.try_parse(input, radix) {
+    Ok(v) => v,
+    Err(e) => panic!(\"{{x}} parse failed: try_parse({}): {:?}\", __args, e),
+}
+}";
+        assert_eq!(unwrap_variant(&decl, "{x} parse failed: {fn}({args}): {err}").unwrap(), expected);
+    }
+
+    #[test]
+    fn test_unwrap_variant_async_no_args() {
+        let decl = FunctionDecl::from_string("async fn load(id: u8) -> Result<Vec<u8>, Error> { todo!() }".to_string());
+        let expected = "async fn load_unwrap(id: u8) -> Vec<u8> {
+match load(id).await {
+    Ok(v) => v,
+    Err(e) => panic!(\"load failed: {:?}\", e),
+}
+}";
+        assert_eq!(unwrap_variant(&decl, "{fn} failed: {err}").unwrap(), expected);
+        let plain = FunctionDecl::from_string("fn plain() -> u8 { 0 }".to_string());
+        assert!(unwrap_variant(&plain, "{fn}").is_none());
+    }
+
+    #[test]
+    fn test_unwrap_variant_code_size() {
+        let mut ctx = GenContext::new().optimize_for(OptimizeFor::CodeSize);
+        for name in ["try_a", "try_b"] {
+            let decl = FunctionDecl::from_string(format!("fn {}() -> Result<u8, E> {{ todo!() }}", name));
+            let out = unwrap_variant_in(&mut ctx, &decl, "{fn}: {err}").unwrap();
+            assert!(out.contains(&format!("Err(e) => __pmt_panic_fmt(format_args!(\"{}: {{:?}}\", e)),", name)));
+        }
+        assert_eq!(ctx.take_helpers().matches("#[cold]").count(), 1);
+    }
+
+    #[test]
+    fn test_unwrap_variant_patterns() {
+        let decl =
+            FunctionDecl::from_string("fn f(_: u8, (a, b): (u8, u8), mut n: u8) -> Result<u8, E> { todo!() }".to_string());
+        let expected = "fn f_unwrap(__arg0: u8, __arg1: (u8, u8), n: u8) -> u8 {
+let __args = format!(\"n = {:?}\", n);
+match f(__arg0, __arg1, n) {
+    Ok(v) => v,
+    Err(e) => panic!(\"{}: {:?}\", __args, e),
+}
+}";
+        assert_eq!(unwrap_variant(&decl, "{args}: {err}").unwrap(), expected);
+    }
+}
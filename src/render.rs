@@ -0,0 +1,166 @@
+use crate::scan::CodeChars;
+use crate::FunctionDecl;
+
+/// Where the opening brace of a function body goes.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum BraceStyle {
+    /// `fn a() {`
+    #[default]
+    SameLine,
+    /// `fn a()` then `{` on its own line.
+    NextLine,
+}
+
+/// Layout options for [`FunctionDecl::render_with`] and friends. The default
+/// reproduces the plain layout of [`crate::Decl::render`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenderOptions {
+    /// Spaces per indentation level, used when `reindent_body` is set.
+    pub indent_width: usize,
+    pub brace_style: BraceStyle,
+    /// End the output with a newline after the closing brace.
+    pub trailing_newline: bool,
+    /// Strip the body's common indentation and indent it one level. Lines
+    /// inside multi-line string literals and block comments are left alone.
+    pub reindent_body: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            indent_width: 4,
+            brace_style: BraceStyle::SameLine,
+            trailing_newline: false,
+            reindent_body: false,
+        }
+    }
+}
+
+impl RenderOptions {
+    pub fn new() -> Self {
+        RenderOptions::default()
+    }
+
+    /// rustfmt-like output: an indented body and a trailing newline.
+    pub fn pretty() -> Self {
+        RenderOptions {
+            trailing_newline: true,
+            reindent_body: true,
+            ..RenderOptions::default()
+        }
+    }
+}
+
+impl FunctionDecl {
+    pub fn func_prelude_with(&self, options: &RenderOptions) -> String {
+        match options.brace_style {
+            BraceStyle::SameLine => format!("{} {{", self.func_signature()),
+            BraceStyle::NextLine => format!("{}\n{{", self.func_signature()),
+        }
+    }
+
+    pub fn into_func_body_with(self, body_add: String, options: &RenderOptions) -> String {
+        self.layout(&body_add, options)
+    }
+
+    /// Render the function with the given layout. Unlike
+    /// [`crate::Decl::render`] this always regenerates from the fields, even
+    /// for a decl parsed with [`FunctionDecl::parse_preserving`].
+    pub fn render_with(&self, options: &RenderOptions) -> String {
+        self.layout(&self.fn_body, options)
+    }
+
+    fn layout(&self, body: &str, options: &RenderOptions) -> String {
+        let body = if options.reindent_body {
+            reindent(body, &" ".repeat(options.indent_width))
+        } else {
+            body.to_string()
+        };
+        format!(
+            "{}\n{}\n{}{}",
+            self.func_prelude_with(options),
+            body,
+            self.func_end(),
+            if options.trailing_newline { "\n" } else { "" }
+        )
+    }
+}
+
+/// Re-indent `body` one level of `indent`, after stripping the indentation
+/// common to its lines. The first line is taken as already unindented, as it
+/// is in a trimmed `fn_body`.
+pub(crate) fn reindent(body: &str, indent: &str) -> String {
+    let code_newlines: Vec<usize> = CodeChars::new(body).filter(|(_, c)| *c == '\n').map(|(idx, _)| idx).collect();
+    // (line start, whether the line is code rather than literal/comment continuation)
+    let mut lines = vec![(0, true)];
+    for (idx, _) in body.match_indices('\n') {
+        lines.push((idx + 1, code_newlines.contains(&idx)));
+    }
+    let line_text = |i: usize| {
+        let start = lines[i].0;
+        let end = lines.get(i + 1).map(|l| l.0 - 1).unwrap_or(body.len());
+        &body[start..end]
+    };
+    let common = (1..lines.len())
+        .filter(|&i| lines[i].1 && !line_text(i).trim().is_empty())
+        .map(|i| line_text(i).len() - line_text(i).trim_start().len())
+        .min()
+        .unwrap_or(0);
+
+    (0..lines.len())
+        .map(|i| {
+            let line = line_text(i);
+            if !lines[i].1 {
+                line.to_string()
+            } else if line.trim().is_empty() {
+                String::new()
+            } else if i == 0 {
+                format!("{}{}", indent, line)
+            } else {
+                format!("{}{}", indent, &line[common.min(line.len() - line.trim_start().len())..])
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::render::{BraceStyle, RenderOptions};
+    use crate::{Decl, FunctionDecl};
+
+    #[test]
+    fn test_render_with_default_matches_render() {
+        let decl = FunctionDecl::from_string("pub fn a(x: u8) -> u8 {\n        x + 1\n    }".to_string());
+        assert_eq!(decl.render_with(&RenderOptions::new()), decl.render());
+    }
+
+    #[test]
+    fn test_render_with_reindent() {
+        let decl = FunctionDecl::from_string(
+            "fn a() {\n        let s = \"line one\n  line two\";\n        if s.is_empty() {\n            return;\n        }\n\n        print(s)\n    }"
+                .to_string(),
+        );
+        let options = RenderOptions {
+            indent_width: 2,
+            brace_style: BraceStyle::NextLine,
+            ..RenderOptions::pretty()
+        };
+        let expected = "fn a()
+{
+  let s = \"line one
+  line two\";
+  if s.is_empty() {
+      return;
+  }
+
+  print(s)
+}
+";
+        assert_eq!(decl.render_with(&options), expected);
+        assert_eq!(
+            decl.clone().into_func_body_with("x();\n    y();".to_string(), &RenderOptions::pretty()),
+            "fn a() {\n    x();\n    y();\n}\n"
+        );
+    }
+}
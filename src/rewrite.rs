@@ -0,0 +1,326 @@
+use std::collections::HashMap;
+
+use crate::scan::{find_close, is_ident_char, CodeChars};
+use crate::Body;
+
+impl Body {
+    /// Replace every call of `path` with `f(args)`, where `args` is the text
+    /// between the call's parentheses, trimmed. A path ending in `!` matches macro
+    /// invocations with any delimiter, e.g. `log!` matches `log!(...)`,
+    /// `log![...]` and `log! {...}`.
+    ///
+    /// The path is matched token by token as written, so `info!` does not
+    /// match `tracing::info!` nor a method `x.info()`. Strings and comments
+    /// are skipped, and calls nested in the arguments of a match are
+    /// replaced first.
+    pub fn replace_calls<F: FnMut(&str) -> String>(&mut self, path: &str, mut f: F) -> &mut Self {
+        let segments = path_segments(path);
+        for stmt in self.stmts.iter_mut() {
+            stmt.text = replace_calls_in(&stmt.text, &segments, &mut f);
+        }
+        self
+    }
+
+    /// Replace every `expr.await` with `f(expr)`, where `expr` is the whole
+    /// postfix expression awaited, e.g. `self.client.get(url)` or
+    /// `async move { ... }`. Awaits are rewritten left to right, so in a
+    /// chain such as `a.await.b().await` the second receiver already
+    /// contains the first rewrite.
+    pub fn replace_await<F: FnMut(&str) -> String>(&mut self, mut f: F) -> &mut Self {
+        for stmt in self.stmts.iter_mut() {
+            stmt.text = replace_await_in(&stmt.text, &mut f);
+        }
+        self
+    }
+}
+
+/// Split a path into identifiers and punctuation, e.g. `tracing::info!`
+/// into `tracing`, `::`, `info`, `!`.
+fn path_segments(path: &str) -> Vec<&str> {
+    let mut segments = Vec::new();
+    let mut rest = path.trim();
+    while let Some(c) = rest.chars().next() {
+        let len = if is_ident_char(c) {
+            rest.find(|c: char| !is_ident_char(c)).unwrap_or(rest.len())
+        } else if rest.starts_with("::") {
+            2
+        } else {
+            c.len_utf8()
+        };
+        segments.push(&rest[..len]);
+        rest = rest[len..].trim_start();
+    }
+    segments
+}
+
+fn replace_calls_in(src: &str, segments: &[&str], f: &mut dyn FnMut(&str) -> String) -> String {
+    let mut out = src.to_string();
+    let mut from = 0;
+    while let Some((start, open)) = find_call(&out, from, segments) {
+        let close = match find_close(&out, open) {
+            Some(close) => close,
+            None => break,
+        };
+        let args = replace_calls_in(&out[open + 1..close], segments, f);
+        let replacement = f(args.trim());
+        out.replace_range(start..=close, &replacement);
+        from = start + replacement.len();
+    }
+    out
+}
+
+/// Start of the first call of the path `segments` at or after `from`, and
+/// the index of its opening delimiter.
+fn find_call(src: &str, from: usize, segments: &[&str]) -> Option<(usize, usize)> {
+    let is_macro = segments.last() == Some(&"!");
+    let first = *segments.first()?;
+    let mut prev = None;
+    for (idx, c) in CodeChars::new(src) {
+        let before = prev;
+        if !c.is_whitespace() {
+            prev = Some(c);
+        }
+        if idx < from || !src[idx..].starts_with(first) || src[..idx].ends_with(is_ident_char) {
+            continue;
+        }
+        // Skip `x.path(...)` and `other::path(...)`.
+        if matches!(before, Some('.' | ':')) {
+            continue;
+        }
+        let mut pos = idx;
+        let matched = segments.iter().all(|segment| {
+            let rest = &src[pos..];
+            let rest_trimmed = rest.trim_start();
+            pos += rest.len() - rest_trimmed.len();
+            let ok = rest_trimmed.starts_with(segment)
+                && !(segment.starts_with(is_ident_char) && rest_trimmed[segment.len()..].starts_with(is_ident_char));
+            pos += segment.len();
+            ok
+        });
+        if !matched {
+            continue;
+        }
+        let rest = &src[pos..];
+        let open = pos + rest.len() - rest.trim_start().len();
+        match src[open..].chars().next() {
+            Some('(') => return Some((idx, open)),
+            Some('[' | '{') if is_macro => return Some((idx, open)),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn replace_await_in(src: &str, f: &mut dyn FnMut(&str) -> String) -> String {
+    let mut out = src.to_string();
+    let mut from = 0;
+    while let Some(dot) = find_await(&out, from) {
+        let start = receiver_start(&out, dot);
+        let end = dot + out[dot..].find("await").map_or(0, |i| i + 5);
+        let replacement = f(out[start..dot].trim_end());
+        out.replace_range(start..end, &replacement);
+        from = start + replacement.len();
+    }
+    out
+}
+
+/// Index of the `.` of the first `.await` at or after `from`.
+fn find_await(src: &str, from: usize) -> Option<usize> {
+    let chars: Vec<(usize, char)> = CodeChars::new(src).filter(|(idx, _)| *idx >= from).collect();
+    chars.iter().find_map(|&(idx, c)| {
+        if c != '.' {
+            return None;
+        }
+        let rest = src[idx + 1..].trim_start();
+        let is_await = rest.starts_with("await") && !rest[5..].starts_with(is_ident_char);
+        if is_await {
+            Some(idx)
+        } else {
+            None
+        }
+    })
+}
+
+/// Start of the postfix expression ending just before `end`: a path or
+/// literal followed by any chain of calls, indexing, field accesses, `?` and
+/// `.await`.
+fn receiver_start(src: &str, end: usize) -> usize {
+    let opens = open_brackets(src);
+    let mut pos = end;
+    loop {
+        let trimmed = src[..pos].trim_end();
+        let c = match trimmed.chars().next_back() {
+            Some(c) => c,
+            None => return pos,
+        };
+        let at = trimmed.len() - c.len_utf8();
+        match c {
+            ')' | ']' | '}' => {
+                let open = match opens.get(&at) {
+                    Some(&open) => open,
+                    None => return pos,
+                };
+                let before = src[..open].trim_end();
+                if c == '}' {
+                    // An `async` block, possibly `move`, or a struct literal.
+                    return match last_word(before) {
+                        "move" if last_word(before[..before.len() - 4].trim_end()) == "async" => {
+                            before[..before.len() - 4].trim_end().len() - 5
+                        }
+                        "async" => before.len() - 5,
+                        word if word.is_empty() || is_keyword(word) => open,
+                        _ => path_start(src, before.len()),
+                    };
+                }
+                pos = open;
+                if before.ends_with('!') {
+                    pos = path_start(src, before.len() - 1);
+                }
+                // A call or index of whatever comes before.
+                continue;
+            }
+            '?' => {
+                pos = at;
+                continue;
+            }
+            '>' => match turbofish_start(trimmed) {
+                Some(start) => pos = path_start(src, start),
+                None => return pos,
+            },
+            c if is_ident_char(c) => {
+                if is_keyword(last_word(trimmed)) {
+                    return pos;
+                }
+                pos = path_start(src, trimmed.len());
+            }
+            _ => return pos,
+        }
+        let before = src[..pos].trim_end();
+        if before.ends_with('.') && !before.ends_with("..") {
+            pos = before.len() - 1;
+        } else {
+            return pos;
+        }
+    }
+}
+
+/// Start of the `::` before the generic arguments `src` ends with, as in
+/// `collect::<Vec<_>>`.
+fn turbofish_start(src: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (idx, c) in src.char_indices().rev() {
+        match c {
+            '>' => depth += 1,
+            '<' => depth -= 1,
+            _ => {}
+        }
+        if depth == 0 {
+            return src[..idx].strip_suffix("::").map(str::len);
+        }
+    }
+    None
+}
+
+/// Start of the path ending at `end`, e.g. `Vec::<u8>::new` or `self.x`'s `x`.
+fn path_start(src: &str, end: usize) -> usize {
+    let mut pos = end;
+    loop {
+        let word = last_word(&src[..pos]);
+        pos -= word.len();
+        match src[..pos].strip_suffix("::") {
+            Some(before) if !last_word(before).is_empty() => pos = before.len(),
+            Some(before) if before.ends_with('>') => match turbofish_start(before) {
+                Some(start) => pos = start,
+                None => return pos,
+            },
+            _ => return pos,
+        }
+    }
+}
+
+fn last_word(src: &str) -> &str {
+    let start = src.rfind(|c: char| !is_ident_char(c)).map_or(0, |i| i + 1);
+    &src[start..]
+}
+
+/// Keywords that may precede an expression, ending the receiver.
+fn is_keyword(word: &str) -> bool {
+    matches!(
+        word,
+        "if" | "else" | "match" | "loop" | "while" | "for" | "in" | "unsafe" | "let" | "const" | "return" | "break"
+    )
+}
+
+/// Index of the opening bracket for every closing one in `src`.
+fn open_brackets(src: &str) -> HashMap<usize, usize> {
+    let mut opens = HashMap::new();
+    let mut stack = Vec::new();
+    for (idx, c) in CodeChars::new(src) {
+        match c {
+            '(' | '[' | '{' => stack.push(idx),
+            ')' | ']' | '}' => {
+                if let Some(open) = stack.pop() {
+                    opens.insert(idx, open);
+                }
+            }
+            _ => {}
+        }
+    }
+    opens
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Body;
+
+    #[test]
+    fn test_replace_calls() {
+        let mut body = Body::parse(
+            "log!(\"start {}\", id);
+    let s = \"log!(x)\"; // log!(y)
+    self.log!(z);
+    tracing::log!(w);
+    log! { \"nested {}\", log!(inner) };
+    catalog(1)",
+        );
+        body.replace_calls("log!", |args| format!("trace!({})", args));
+        assert_eq!(
+            body.render(),
+            "trace!(\"start {}\", id);
+    let s = \"log!(x)\"; // log!(y)
+    self.log!(z);
+    tracing::log!(w);
+    trace!(\"nested {}\", trace!(inner));
+    catalog(1)"
+        );
+
+        let mut body = Body::parse("let a = std::mem::take(&mut x);\nstd :: mem :: take(y)");
+        body.replace_calls("std::mem::take", |args| format!("take_traced({})", args));
+        assert_eq!(body.render(), "let a = take_traced(&mut x);\ntake_traced(y)");
+    }
+
+    #[test]
+    fn test_replace_await() {
+        let mut body = Body::parse(
+            "let user = self.db.user(id).await?;
+    let s = \".await\";
+    let page = fetch::<Page>(url)
+        .await
+        .unwrap();
+    let both = join(a, b.await).await;
+    async move { go().await }.await;
+    return load(user).await.items().await",
+        );
+        body.replace_await(|expr| format!("timed({}).await", expr));
+        assert_eq!(
+            body.render(),
+            "let user = timed(self.db.user(id)).await?;
+    let s = \".await\";
+    let page = timed(fetch::<Page>(url)).await
+        .unwrap();
+    let both = timed(join(a, timed(b).await)).await;
+    timed(async move { timed(go()).await }).await;
+    return timed(timed(load(user)).await.items()).await"
+        );
+    }
+}
@@ -0,0 +1,444 @@
+//! A small lexical scanner over Rust source text.
+//!
+//! It does not tokenize fully; it only knows enough to skip string, raw string,
+//! byte string and char literals as well as line and (nested) block comments,
+//! so callers can match brackets and find separators without being fooled by
+//! a `}` inside a literal or comment.
+
+/// Iterator over the `(byte_index, char)` pairs of `src` that are code, i.e.
+/// not inside a literal or comment.
+pub(crate) struct CodeChars<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> CodeChars<'a> {
+    pub(crate) fn new(src: &'a str) -> Self {
+        CodeChars { src, pos: 0 }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+
+    fn prev_is_ident(&self) -> bool {
+        self.src[..self.pos]
+            .chars()
+            .next_back()
+            .map(is_ident_char)
+            .unwrap_or(false)
+    }
+
+    /// Length of a literal or comment starting at the current position, if any.
+    fn skip_len(&self) -> Option<usize> {
+        let rest = self.rest();
+        if rest.starts_with("//") {
+            return Some(rest.find('\n').unwrap_or(rest.len()));
+        }
+        if rest.starts_with("/*") {
+            return Some(block_comment_len(rest));
+        }
+        if self.prev_is_ident() {
+            return None;
+        }
+        let unprefixed = rest.strip_prefix('b').unwrap_or(rest);
+        let prefix = rest.len() - unprefixed.len();
+        if let Some(raw) = unprefixed.strip_prefix('r') {
+            let hashes = raw.len() - raw.trim_start_matches('#').len();
+            if raw[hashes..].starts_with('"') {
+                let close = format!("\"{}", "#".repeat(hashes));
+                let body = &raw[hashes + 1..];
+                let end = body.find(&close).map(|i| i + close.len()).unwrap_or(body.len());
+                return Some(prefix + 1 + hashes + 1 + end);
+            }
+        }
+        if unprefixed.starts_with('"') {
+            return Some(prefix + quoted_len(unprefixed, '"'));
+        }
+        if unprefixed.starts_with('\'') {
+            let mut chars = unprefixed.chars().skip(1);
+            let is_char_lit = matches!(
+                (chars.next(), chars.next()),
+                (Some('\\'), _) | (Some(_), Some('\''))
+            );
+            if is_char_lit {
+                return Some(prefix + quoted_len(unprefixed, '\''));
+            }
+        }
+        None
+    }
+}
+
+impl<'a> Iterator for CodeChars<'a> {
+    type Item = (usize, char);
+
+    fn next(&mut self) -> Option<(usize, char)> {
+        while let Some(len) = self.skip_len() {
+            self.pos += len;
+        }
+        let c = self.rest().chars().next()?;
+        let idx = self.pos;
+        self.pos += c.len_utf8();
+        Some((idx, c))
+    }
+}
+
+fn quoted_len(s: &str, quote: char) -> usize {
+    let mut escaped = false;
+    for (idx, c) in s.char_indices().skip(1) {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == quote {
+            return idx + 1;
+        }
+    }
+    s.len()
+}
+
+fn block_comment_len(s: &str) -> usize {
+    let mut depth = 0;
+    let mut idx = 0;
+    while idx < s.len() {
+        let rest = &s[idx..];
+        if rest.starts_with("/*") {
+            depth += 1;
+            idx += 2;
+        } else if rest.starts_with("*/") {
+            depth -= 1;
+            idx += 2;
+            if depth == 0 {
+                return idx;
+            }
+        } else {
+            idx += rest.chars().next().map(char::len_utf8).unwrap_or(1);
+        }
+    }
+    s.len()
+}
+
+pub(crate) fn is_ident_char(c: char) -> bool {
+    c == '_' || c.is_alphanumeric()
+}
+
+/// Index of the bracket closing the one at `open_idx`, skipping literals and comments.
+pub(crate) fn find_close(src: &str, open_idx: usize) -> Option<usize> {
+    let mut depth = 0i32;
+    for (idx, c) in CodeChars::new(&src[open_idx..]) {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(open_idx + idx);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Index of the `>` closing the `<` at `open_idx` (the `>` of `->` is ignored).
+pub(crate) fn find_close_angle(src: &str, open_idx: usize) -> Option<usize> {
+    let mut depth = 0i32;
+    let mut prev = ' ';
+    for (idx, c) in CodeChars::new(&src[open_idx..]) {
+        match c {
+            '<' => depth += 1,
+            '>' if prev != '-' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(open_idx + idx);
+                }
+            }
+            _ => {}
+        }
+        prev = c;
+    }
+    None
+}
+
+/// Split leading attributes, doc comments and comments off `src`, returning
+/// `(attrs, rest)` with `attrs` trimmed.
+pub(crate) fn split_leading_attrs(src: &str) -> (&str, &str) {
+    let mut idx = 0;
+    loop {
+        let rest = &src[idx..];
+        let trimmed = rest.trim_start();
+        idx += rest.len() - trimmed.len();
+        if trimmed.starts_with("//") {
+            idx += trimmed.find('\n').map(|i| i + 1).unwrap_or(trimmed.len());
+        } else if trimmed.starts_with("/*") {
+            idx += block_comment_len(trimmed);
+        } else if let Some(attr) = trimmed.strip_prefix('#') {
+            let attr = attr.trim_start();
+            let attr = attr.strip_prefix('!').unwrap_or(attr).trim_start();
+            if !attr.starts_with('[') {
+                break;
+            }
+            match find_close(src, idx + trimmed.len() - attr.len()) {
+                Some(close) => idx = close + 1,
+                None => break,
+            }
+        } else {
+            break;
+        }
+    }
+    (src[..idx].trim(), &src[idx..])
+}
+
+/// Byte ranges `(start, end)` of the `#[...]` attributes in a run of leading
+/// attributes and comments, as accepted by [`split_leading_attrs`].
+pub(crate) fn attr_ranges(src: &str) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    let mut idx = 0;
+    loop {
+        let rest = &src[idx..];
+        let trimmed = rest.trim_start();
+        idx += rest.len() - trimmed.len();
+        if trimmed.starts_with("//") {
+            idx += trimmed.find('\n').map(|i| i + 1).unwrap_or(trimmed.len());
+        } else if trimmed.starts_with("/*") {
+            idx += block_comment_len(trimmed);
+        } else if let Some(attr) = trimmed.strip_prefix('#') {
+            let attr = attr.trim_start();
+            if !attr.starts_with('[') {
+                break;
+            }
+            match find_close(src, idx + trimmed.len() - attr.len()) {
+                Some(close) => {
+                    ranges.push((idx, close + 1));
+                    idx = close + 1;
+                }
+                None => break,
+            }
+        } else {
+            break;
+        }
+    }
+    ranges
+}
+
+/// Split a leading visibility (`pub`, `pub(crate)`, `pub(in path)`, ...) off
+/// `src`, returning `(visibility, rest)`.
+pub(crate) fn split_visibility(src: &str) -> (&str, &str) {
+    let trimmed = src.trim_start();
+    let after_pub = match trimmed.strip_prefix("pub") {
+        Some(rest) if !rest.starts_with(is_ident_char) => rest,
+        _ => return ("", trimmed),
+    };
+    let restricted = after_pub.trim_start();
+    let is_restriction = restricted
+        .strip_prefix('(')
+        .map(|r| r.trim_start())
+        .map(|r| ["crate", "self", "super", "in "].iter().any(|kw| r.starts_with(kw)))
+        .unwrap_or(false);
+    if is_restriction {
+        let open = trimmed.len() - restricted.len();
+        if let Some(close) = find_close(trimmed, open) {
+            return (&trimmed[..=close], trimmed[close + 1..].trim_start());
+        }
+    }
+    ("pub", after_pub.trim_start())
+}
+
+/// Index of the first `needle` outside of brackets, literals and comments.
+///
+/// With `angles` set, `<...>` also counts as nesting (for types and generics;
+/// the `>` of `->` is ignored).
+pub(crate) fn find_top_level(src: &str, needle: char, angles: bool) -> Option<usize> {
+    let mut depth = 0i32;
+    let mut prev = ' ';
+    for (idx, c) in CodeChars::new(src) {
+        match c {
+            // `::` in a path is never a separator.
+            ':' if needle == ':' && (prev == ':' || src[idx + 1..].starts_with(':')) => {}
+            _ if c == needle && depth == 0 => return Some(idx),
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            '<' if angles => depth += 1,
+            '>' if angles && prev != '-' && prev != '=' => depth -= 1,
+            _ => {}
+        }
+        prev = c;
+    }
+    None
+}
+
+/// Split `src` on every top-level `sep` (see [`find_top_level`]).
+pub(crate) fn split_top_level(src: &str, sep: char, angles: bool) -> Vec<&str> {
+    let mut out = Vec::new();
+    let mut rest = src;
+    while let Some(idx) = find_top_level(rest, sep, angles) {
+        out.push(&rest[..idx]);
+        rest = &rest[idx + sep.len_utf8()..];
+    }
+    out.push(rest);
+    out
+}
+
+/// Split a sequence of items (the inside of an `impl`, `trait` or `mod`)
+/// into the text of each item, including its leading attributes.
+///
+/// An item ends at a top-level `;`, or at the `}` closing its first top-level
+/// block unless it is a `const`/`static`/`type`/`let`/`use`/`extern crate`,
+/// which run to their `;`.
+pub(crate) fn split_items(src: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let mut start = 0;
+    let mut depth = 0i32;
+    for (idx, c) in CodeChars::new(src) {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' => depth -= 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 && !runs_to_semicolon(&src[start..idx]) {
+                    items.push(src[start..=idx].trim());
+                    start = idx + 1;
+                }
+            }
+            ';' if depth == 0 => {
+                items.push(src[start..=idx].trim());
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    if !split_leading_attrs(&src[start..]).1.trim().is_empty() {
+        items.push(src[start..].trim());
+    }
+    items.retain(|i| !i.is_empty() && *i != ";");
+    items
+}
+
+fn runs_to_semicolon(item: &str) -> bool {
+    let (_, rest) = split_leading_attrs(item);
+    let (_, rest) = split_visibility(rest);
+    let first = rest.split_whitespace().next().unwrap_or("");
+    let second = rest.split_whitespace().nth(1).unwrap_or("");
+    match first {
+        "static" | "type" | "let" | "use" => true,
+        "const" => !matches!(second, "fn" | "unsafe" | "async" | "extern"),
+        "extern" => second == "crate",
+        _ => false,
+    }
+}
+
+/// Index of the first top-level occurrence of the keyword/identifier `kw`.
+pub(crate) fn find_keyword(src: &str, kw: &str) -> Option<usize> {
+    let mut depth = 0i32;
+    for (idx, c) in CodeChars::new(src) {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            _ if depth == 0 && src[idx..].starts_with(kw) => {
+                let before = src[..idx].chars().next_back().map(is_ident_char).unwrap_or(false);
+                let after = src[idx + kw.len()..].chars().next().map(is_ident_char).unwrap_or(false);
+                if !before && !after {
+                    return Some(idx);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::scan::{
+        find_close, find_close_angle, find_keyword, find_top_level, split_items, split_leading_attrs, split_visibility,
+        CodeChars,
+    };
+
+    fn code(src: &str) -> String {
+        CodeChars::new(src).map(|(_, c)| c).collect()
+    }
+
+    #[test]
+    fn test_skips_literals_and_comments() {
+        assert_eq!(code(r#"a("}") b"#), "a() b");
+        assert_eq!(code("a // }\nb"), "a \nb");
+        assert_eq!(code("a /* /* } */ } */ b"), "a  b");
+        assert_eq!(code(r##"a(r#"x"}"#) b"##), "a() b");
+        assert_eq!(code(r#"a(b"}", '}', '\'', b'{')"#), "a(, , , )");
+        assert_eq!(code(r#"a("\"}")"#), "a()");
+    }
+
+    #[test]
+    fn test_lifetimes_are_code() {
+        assert_eq!(code("fn a<'a>(s: &'a str) {}"), "fn a<'a>(s: &'a str) {}");
+    }
+
+    #[test]
+    fn test_find_close() {
+        let src = r#"{ let s = "}"; if x { y } /* } */ } tail"#;
+        assert_eq!(find_close(src, 0), Some(src.len() - 6));
+        assert_eq!(find_close("{ (", 0), None);
+    }
+
+    #[test]
+    fn test_find_top_level() {
+        assert_eq!(find_top_level("a: HashMap<u8, u8>, b", ',', true), Some(18));
+        assert_eq!(find_top_level("a: HashMap<u8, u8>, b", ',', false), Some(13));
+        assert_eq!(find_top_level("f: impl Fn(u8) -> u8, b", ',', true), Some(20));
+        assert_eq!(find_top_level("p: std::path::Path", ':', true), Some(1));
+    }
+
+    #[test]
+    fn test_find_keyword() {
+        assert_eq!(find_keyword("/// fn doc\n#[a(fn)] pub fn x()", "fn"), Some(24));
+        assert_eq!(find_keyword("fnord", "fn"), None);
+    }
+
+    #[test]
+    fn test_find_close_angle() {
+        assert_eq!(find_close_angle("<F: Fn() -> u8, T> x", 0), Some(17));
+    }
+
+    #[test]
+    fn test_split_leading_attrs() {
+        let (attrs, rest) = split_leading_attrs("  /// doc }\n#[derive(Debug)]\n# [a(\"]\")]\n// note\npub enum A {}");
+        assert_eq!(attrs, "/// doc }\n#[derive(Debug)]\n# [a(\"]\")]\n// note");
+        assert_eq!(rest, "pub enum A {}");
+        assert_eq!(split_leading_attrs("enum A {}"), ("", "enum A {}"));
+    }
+
+    #[test]
+    fn test_split_visibility() {
+        assert_eq!(split_visibility("pub enum A"), ("pub", "enum A"));
+        assert_eq!(split_visibility("pub(crate) fn a"), ("pub(crate)", "fn a"));
+        assert_eq!(split_visibility("pub (in crate::x) a: u8"), ("pub (in crate::x)", "a: u8"));
+        assert_eq!(split_visibility("public: u8"), ("", "public: u8"));
+        assert_eq!(split_visibility("pub (u8, u8)"), ("pub", "(u8, u8)"));
+        assert_eq!(split_visibility("a: u8"), ("", "a: u8"));
+    }
+
+    #[test]
+    fn test_split_items() {
+        let src = "
+            type Out = u8;
+            /// Doc
+            #[inline]
+            fn a(&self) -> u8 { if true { 1 } else { 2 } }
+            const C: u8 = { 3 };
+            const fn b() {}
+            m! { x }
+            n!(y);
+        ";
+        assert_eq!(
+            split_items(src),
+            vec![
+                "type Out = u8;",
+                "/// Doc\n            #[inline]\n            fn a(&self) -> u8 { if true { 1 } else { 2 } }",
+                "const C: u8 = { 3 };",
+                "const fn b() {}",
+                "m! { x }",
+                "n!(y);",
+            ]
+        );
+    }
+}
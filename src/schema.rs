@@ -0,0 +1,201 @@
+use crate::{FieldDecl, Fields, FnSignature, StructDecl, TypeKind};
+
+/// Output format for the `to_schema` methods.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SchemaFormat {
+    /// A single-line JSON object.
+    Json,
+    /// A single-line RON value, with anonymous structs for objects and
+    /// `Some`/`None` for optional values.
+    Ron,
+}
+
+/// A schema value, rendered in either format.
+enum Value {
+    Str(String),
+    Bool(bool),
+    Opt(Option<Box<Value>>),
+    List(Vec<Value>),
+    Map(Vec<(&'static str, Value)>),
+}
+
+impl Value {
+    fn opt_str(s: Option<&str>) -> Self {
+        Value::Opt(s.map(|s| Box::new(Value::Str(s.to_string()))))
+    }
+
+    fn render(&self, format: SchemaFormat) -> String {
+        let join = |items: Vec<String>| items.join(",");
+        match (self, format) {
+            (Value::Str(s), _) => json_str(s),
+            (Value::Bool(b), _) => b.to_string(),
+            (Value::Opt(None), SchemaFormat::Json) => "null".to_string(),
+            (Value::Opt(None), SchemaFormat::Ron) => "None".to_string(),
+            (Value::Opt(Some(v)), SchemaFormat::Json) => v.render(format),
+            (Value::Opt(Some(v)), SchemaFormat::Ron) => format!("Some({})", v.render(format)),
+            (Value::List(items), _) => format!("[{}]", join(items.iter().map(|v| v.render(format)).collect())),
+            (Value::Map(entries), SchemaFormat::Json) => format!(
+                "{{{}}}",
+                join(entries.iter().map(|(k, v)| format!("{}:{}", json_str(k), v.render(format))).collect())
+            ),
+            (Value::Map(entries), SchemaFormat::Ron) => format!(
+                "({})",
+                join(entries.iter().map(|(k, v)| format!("{}:{}", k, v.render(format))).collect())
+            ),
+        }
+    }
+}
+
+impl<'a> FnSignature<'a> {
+    /// A machine-readable description of the signature: name, `async`, the
+    /// receiver, each parameter with its type, and the return type (absent
+    /// for unit). Types are kept as written.
+    ///
+    /// With [`SchemaFormat::Json`]: `{"name":"get","async":false,"receiver":"&self",
+    /// "params":[{"name":"id","type":"u64"}],"returns":"Item"}`.
+    pub fn to_schema(&self, format: SchemaFormat) -> String {
+        let params = self.params();
+        let receiver = params.iter().find(|p| p.is_receiver()).map(|p| p.to_string());
+        let ret = self.return_decl();
+        Value::Map(vec![
+            ("name", Value::Str(self.name().to_string())),
+            ("async", Value::Bool(self.is_async())),
+            ("receiver", Value::opt_str(receiver.as_deref())),
+            (
+                "params",
+                Value::List(
+                    params
+                        .iter()
+                        .filter(|p| !p.is_receiver())
+                        .map(|p| Value::Map(vec![("name", Value::Str(p.name.clone())), ("type", Value::Str(p.ty.clone()))]))
+                        .collect(),
+                ),
+            ),
+            ("returns", Value::opt_str(if ret.is_empty() { None } else { Some(ret) })),
+        ])
+        .render(format)
+    }
+
+    /// `pub const {name}: &str = "...";` holding [`FnSignature::to_schema`],
+    /// for embedding next to the generated code.
+    pub fn schema_const(&self, name: &str, format: SchemaFormat) -> String {
+        schema_const(name, &self.to_schema(format))
+    }
+}
+
+impl StructDecl {
+    /// A machine-readable description of the struct: its name, shape
+    /// (`named`, `tuple` or `unit`), doc comment and fields. Each field has its
+    /// name (the index for tuple fields), type as written, whether it is an
+    /// `Option`, and its doc comment.
+    pub fn to_schema(&self, format: SchemaFormat) -> String {
+        let (shape, fields) = match &self.fields {
+            Fields::Unit => ("unit", Vec::new()),
+            Fields::Tuple(f) => ("tuple", f.iter().enumerate().map(|(i, f)| field_schema(&i.to_string(), f)).collect()),
+            Fields::Named(f) => ("named", f.iter().map(|f| field_schema(&f.name, f)).collect()),
+        };
+        Value::Map(vec![
+            ("name", Value::Str(self.name.clone())),
+            ("shape", Value::Str(shape.to_string())),
+            ("doc", Value::opt_str(doc_comment(&self.prologue).as_deref())),
+            ("fields", Value::List(fields)),
+        ])
+        .render(format)
+    }
+
+    /// `pub const {name}: &str = "...";` holding [`StructDecl::to_schema`].
+    pub fn schema_const(&self, name: &str, format: SchemaFormat) -> String {
+        schema_const(name, &self.to_schema(format))
+    }
+}
+
+fn field_schema(name: &str, field: &FieldDecl) -> Value {
+    Value::Map(vec![
+        ("name", Value::Str(name.to_string())),
+        ("type", Value::Str(field.ty.clone())),
+        ("optional", Value::Bool(matches!(field.kind(), TypeKind::Option(_)))),
+        ("doc", Value::opt_str(doc_comment(&field.prologue).as_deref())),
+    ])
+}
+
+fn schema_const(name: &str, schema: &str) -> String {
+    format!("pub const {}: &str = {:?};", name, schema)
+}
+
+/// The text of the `///` lines in `prologue`, joined by newlines.
+fn doc_comment(prologue: &str) -> Option<String> {
+    let lines: Vec<&str> = prologue
+        .lines()
+        .filter_map(|l| l.trim().strip_prefix("///"))
+        .map(|l| l.strip_prefix(' ').unwrap_or(l))
+        .collect();
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.join("\n"))
+    }
+}
+
+/// `s` as a quoted JSON string.
+pub(crate) fn json_str(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::schema::{json_str, SchemaFormat};
+    use crate::{FunctionDecl, StructDecl};
+
+    #[test]
+    fn test_fn_schema_json() {
+        let decl = FunctionDecl::from_string(
+            "pub async fn get(&self, id: u64, (a, b): (u8, u8)) -> Result<Item, Error> { todo!() }".to_string(),
+        );
+        assert_eq!(
+            decl.signature().to_schema(SchemaFormat::Json),
+            "{\"name\":\"get\",\"async\":true,\"receiver\":\"&self\",\"params\":[{\"name\":\"id\",\"type\":\"u64\"},{\"name\":\"(a, b)\",\"type\":\"(u8, u8)\"}],\"returns\":\"Result<Item, Error>\"}"
+        );
+        let decl = FunctionDecl::from_string("fn ping() {}".to_string());
+        assert_eq!(
+            decl.signature().schema_const("PING_SCHEMA", SchemaFormat::Json),
+            "pub const PING_SCHEMA: &str = \"{\\\"name\\\":\\\"ping\\\",\\\"async\\\":false,\\\"receiver\\\":null,\\\"params\\\":[],\\\"returns\\\":null}\";"
+        );
+        assert_eq!(
+            decl.signature().to_schema(SchemaFormat::Ron),
+            "(name:\"ping\",async:false,receiver:None,params:[],returns:None)"
+        );
+        assert_eq!(json_str("a\"b\\\n\u{1}"), "\"a\\\"b\\\\\\n\\u0001\"");
+    }
+
+    #[test]
+    fn test_struct_schema() {
+        let decl = StructDecl::from_string(
+            "/// A user\n/// record\n#[derive(Debug)]\npub struct User {\n    /// Login name\n    pub name: String,\n    email: Option<String>,\n}"
+                .to_string(),
+        );
+        assert_eq!(
+            decl.to_schema(SchemaFormat::Json),
+            "{\"name\":\"User\",\"shape\":\"named\",\"doc\":\"A user\\nrecord\",\"fields\":[{\"name\":\"name\",\"type\":\"String\",\"optional\":false,\"doc\":\"Login name\"},{\"name\":\"email\",\"type\":\"Option<String>\",\"optional\":true,\"doc\":null}]}"
+        );
+        let decl = StructDecl::from_string("struct Pair(u8, Option<u8>);".to_string());
+        assert_eq!(
+            decl.to_schema(SchemaFormat::Ron),
+            "(name:\"Pair\",shape:\"tuple\",doc:None,fields:[(name:\"0\",type:\"u8\",optional:false,doc:None),(name:\"1\",type:\"Option<u8>\",optional:true,doc:None)])"
+        );
+        assert!(decl.schema_const("PAIR_SCHEMA", SchemaFormat::Ron).starts_with("pub const PAIR_SCHEMA: &str = \"(name:"));
+    }
+}
@@ -0,0 +1,138 @@
+use crate::{FunctionDecl, Param, Receiver};
+
+/// A read-only view of a function's signature, without its attributes or body.
+#[derive(Clone, Copy, Debug)]
+pub struct FnSignature<'a> {
+    decl: &'a FunctionDecl,
+}
+
+/// Options for [`FnSignature::metric_labels`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct LabelOptions {
+    /// Label key for the function name; empty to omit it.
+    pub fn_key: String,
+    /// Label key for `module_path!()`; empty to omit it.
+    pub module_key: String,
+    /// Extra `(key, expression)` labels, appended in order.
+    pub extras: Vec<(String, String)>,
+}
+
+impl Default for LabelOptions {
+    fn default() -> Self {
+        LabelOptions {
+            fn_key: "function".to_string(),
+            module_key: "module".to_string(),
+            extras: Vec::new(),
+        }
+    }
+}
+
+impl LabelOptions {
+    pub fn new() -> Self {
+        LabelOptions::default()
+    }
+
+    /// Add a label; a later extra with the same key replaces an earlier one.
+    pub fn with_extra(mut self, key: &str, expr: &str) -> Self {
+        self.extras.retain(|(k, _)| k != key);
+        self.extras.push((key.to_string(), expr.to_string()));
+        self
+    }
+}
+
+impl<'a> FnSignature<'a> {
+    pub fn name(&self) -> &'a str {
+        &self.decl.fn_name
+    }
+
+    pub fn params(&self) -> Vec<Param> {
+        self.decl.params()
+    }
+
+    pub fn receiver(&self) -> Option<Receiver> {
+        self.decl.receiver()
+    }
+
+    pub fn is_async(&self) -> bool {
+        !self.decl.async_str.is_empty()
+    }
+
+    /// The return type as written, empty for none.
+    pub fn return_decl(&self) -> &'a str {
+        &self.decl.ret_decl
+    }
+
+    /// The signature as written, without attributes, e.g. `pub fn a(x: u8) -> u8`.
+    pub fn text(&self) -> String {
+        let mut sig = self.decl.clone();
+        sig.func_prologue = String::new();
+        sig.func_signature()
+    }
+
+    /// Label `(key, value)` pairs identifying this function, with each value a
+    /// `&'static str` expression: the function name as a literal, the module
+    /// via `module_path!()`, then `options.extras`.
+    pub fn metric_labels(&self, options: &LabelOptions) -> Vec<(String, String)> {
+        let mut labels = Vec::new();
+        if !options.fn_key.is_empty() {
+            labels.push((options.fn_key.clone(), format!("{:?}", self.decl.fn_name)));
+        }
+        if !options.module_key.is_empty() {
+            labels.push((options.module_key.clone(), "module_path!()".to_string()));
+        }
+        labels.extend(options.extras.iter().cloned());
+        labels
+    }
+}
+
+impl FunctionDecl {
+    pub fn signature(&self) -> FnSignature<'_> {
+        FnSignature { decl: self }
+    }
+}
+
+/// Render labels as a slice expression, e.g. `&[("function", "a")]`.
+pub fn render_labels(labels: &[(String, String)]) -> String {
+    format!(
+        "&[{}]",
+        labels
+            .iter()
+            .map(|(k, v)| format!("({:?}, {})", k, v))
+            .collect::<Vec<String>>()
+            .join(", ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::signature::{render_labels, LabelOptions};
+    use crate::FunctionDecl;
+
+    #[test]
+    fn test_signature_view() {
+        let decl = FunctionDecl::from_string("/// Doc\npub async fn load(&self, id: u64) -> u8 { 0 }".to_string());
+        let sig = decl.signature();
+        assert_eq!(sig.name(), "load");
+        assert!(sig.is_async());
+        assert!(sig.receiver().is_some());
+        assert_eq!(sig.text(), "pub async fn load(&self, id: u64) -> u8");
+    }
+
+    #[test]
+    fn test_metric_labels() {
+        let decl = FunctionDecl::from_string("fn load() {}".to_string());
+        let options = LabelOptions::new()
+            .with_extra("service", "\"users\"")
+            .with_extra("service", "SERVICE");
+        let labels = decl.signature().metric_labels(&options);
+        assert_eq!(
+            render_labels(&labels),
+            "&[(\"function\", \"load\"), (\"module\", module_path!()), (\"service\", SERVICE)]"
+        );
+        let options = LabelOptions {
+            module_key: String::new(),
+            ..LabelOptions::new()
+        };
+        assert_eq!(decl.signature().metric_labels(&options).len(), 1);
+    }
+}
@@ -0,0 +1,216 @@
+use std::ops::Range;
+
+use proc_macro2::{Delimiter, Literal, Spacing, Span, TokenStream, TokenTree};
+use quote::quote_spanned;
+
+use crate::{scan, FunctionDecl, FunctionDeclRef, ParseError};
+
+/// Byte ranges of the fields of a parsed function in the text it was parsed
+/// from. An absent part is an empty range where it would be: qualifiers at
+/// the `fn` keyword, generics after the name, the return type and `where`
+/// clause after the parameters.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FieldOffsets {
+    pub func_prologue: Range<usize>,
+    pub vis: Range<usize>,
+    pub const_str: Range<usize>,
+    pub async_str: Range<usize>,
+    pub unsafe_str: Range<usize>,
+    pub abi: Range<usize>,
+    /// The `fn` keyword itself.
+    pub fn_token: Range<usize>,
+    pub fn_name: Range<usize>,
+    pub generics: Range<usize>,
+    pub fn_decl: Range<usize>,
+    pub ret_decl: Range<usize>,
+    pub where_clause: Range<usize>,
+    /// The body between its braces, trimmed.
+    pub fn_body: Range<usize>,
+}
+
+impl FunctionDeclRef<'_> {
+    /// Where each field lies in [`FunctionDeclRef::source`].
+    pub fn offsets(&self) -> FieldOffsets {
+        let range = |part: &str| {
+            let start = part.as_ptr() as usize - self.source.as_ptr() as usize;
+            start..start + part.len()
+        };
+        let fn_idx = scan::find_keyword(self.source, "fn").unwrap_or(0);
+        FieldOffsets {
+            func_prologue: range(self.func_prologue),
+            vis: range(self.vis),
+            const_str: range(self.const_str),
+            async_str: range(self.async_str),
+            unsafe_str: range(self.unsafe_str),
+            abi: range(self.abi),
+            fn_token: fn_idx..fn_idx + 2,
+            fn_name: range(self.fn_name),
+            generics: range(self.generics),
+            fn_decl: range(self.fn_decl),
+            ret_decl: range(self.ret_decl),
+            where_clause: range(self.where_clause),
+            fn_body: range(self.fn_body),
+        }
+    }
+}
+
+impl FunctionDecl {
+    /// Parse a function, also returning where each field lies in `in_str`,
+    /// e.g. to report a problem with the return type at the return type.
+    pub fn parse_with_field_offsets(in_str: &str) -> Result<(Self, FieldOffsets), ParseError> {
+        let decl = FunctionDeclRef::parse(in_str)?;
+        Ok((decl.into_owned(), decl.offsets()))
+    }
+}
+
+/// Maps byte offsets in the text of a token stream back to the spans of its
+/// tokens, so that a macro parsing that text can report errors at the code
+/// they concern instead of the whole item: parse [`SpanMap::text`] with e.g.
+/// [`FunctionDecl::parse_with_field_offsets`], then pass a field's range to
+/// [`SpanMap::error`].
+#[derive(Clone, Debug)]
+pub struct SpanMap {
+    text: String,
+    tokens: Vec<(Range<usize>, Span, Option<Literal>)>,
+}
+
+impl SpanMap {
+    /// Render `ts` to text, recording the range of every token.
+    pub fn new(ts: &TokenStream) -> Self {
+        let mut map = SpanMap {
+            text: String::new(),
+            tokens: Vec::new(),
+        };
+        map.push_stream(ts.clone());
+        map
+    }
+
+    /// The rendered text, to be parsed by any decl type.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The span covering the tokens in `range`.
+    ///
+    /// Several tokens are joined into one span where the compiler supports
+    /// it (nightly), otherwise the first token's span is used; a range inside
+    /// a literal gets a subspan of it, again on nightly only. A range past
+    /// every token gives the last token's span, and an empty stream
+    /// [`Span::call_site`].
+    pub fn span(&self, range: Range<usize>) -> Span {
+        let first = match self.tokens.iter().position(|(r, _, _)| r.end > range.start) {
+            Some(idx) => idx,
+            None => return self.tokens.last().map_or_else(Span::call_site, |(_, span, _)| *span),
+        };
+        let (first_range, first_span, literal) = &self.tokens[first];
+        if let Some(literal) = literal {
+            if range.start > first_range.start && range.end < first_range.end {
+                let sub = range.start - first_range.start..range.end - first_range.start;
+                return literal.subspan(sub).unwrap_or(*first_span);
+            }
+        }
+        let last = self.tokens[first..]
+            .iter()
+            .take_while(|(r, _, _)| r.start < range.end)
+            .last()
+            .map_or(*first_span, |(_, span, _)| *span);
+        first_span.join(last).unwrap_or(*first_span)
+    }
+
+    /// A `compile_error!` with `msg` at the tokens in `range`.
+    pub fn error(&self, range: Range<usize>, msg: &str) -> TokenStream {
+        quote_spanned!(self.span(range)=> ::core::compile_error! { #msg })
+    }
+
+    /// A `compile_error!` for a parse error in [`SpanMap::text`], at the
+    /// token where it was detected.
+    pub fn parse_error(&self, e: &ParseError) -> TokenStream {
+        e.to_compile_error_at(self.span(e.offset..e.offset + 1))
+    }
+
+    fn push_stream(&mut self, ts: TokenStream) {
+        let mut joint = true;
+        for tt in ts {
+            if !joint {
+                self.text.push(' ');
+            }
+            joint = false;
+            match tt {
+                TokenTree::Group(g) => {
+                    let (open, close) = match g.delimiter() {
+                        Delimiter::Parenthesis => ("(", ")"),
+                        Delimiter::Brace => ("{", "}"),
+                        Delimiter::Bracket => ("[", "]"),
+                        Delimiter::None => ("", ""),
+                    };
+                    self.push_token(open, g.span_open(), None);
+                    self.push_stream(g.stream());
+                    self.push_token(close, g.span_close(), None);
+                }
+                TokenTree::Punct(p) => {
+                    joint = p.spacing() == Spacing::Joint;
+                    self.push_token(&p.to_string(), p.span(), None);
+                }
+                TokenTree::Ident(i) => self.push_token(&i.to_string(), i.span(), None),
+                TokenTree::Literal(l) => self.push_token(&l.to_string(), l.span(), Some(l)),
+            }
+        }
+    }
+
+    fn push_token(&mut self, text: &str, span: Span, literal: Option<Literal>) {
+        if text.is_empty() {
+            return;
+        }
+        let start = self.text.len();
+        self.text.push_str(text);
+        self.tokens.push((start..self.text.len(), span, literal));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proc_macro2::TokenStream;
+
+    use crate::{FunctionDecl, FunctionDeclRef, SpanMap};
+
+    #[test]
+    fn test_field_offsets() {
+        let src = "/// Doc\npub async fn get<T>(&self, k: T) -> Option<T> where T: Key { self.map.get(k) }";
+        let (decl, offsets) = FunctionDecl::parse_with_field_offsets(src).unwrap();
+        assert_eq!(&src[offsets.func_prologue.clone()], decl.func_prologue);
+        assert_eq!(&src[offsets.vis], "pub");
+        assert_eq!(&src[offsets.async_str], "async");
+        assert_eq!(&src[offsets.fn_token.clone()], "fn");
+        assert_eq!(&src[offsets.fn_name], "get");
+        assert_eq!(&src[offsets.generics], "<T>");
+        assert_eq!(&src[offsets.fn_decl], "(&self, k: T)");
+        assert_eq!(&src[offsets.ret_decl], "Option<T>");
+        assert_eq!(&src[offsets.where_clause], "where T: Key");
+        assert_eq!(&src[offsets.fn_body], "self.map.get(k)");
+        assert_eq!(offsets.const_str, offsets.fn_token.start..offsets.fn_token.start);
+
+        let src = "fn f() {}";
+        let offsets = FunctionDeclRef::parse(src).unwrap().offsets();
+        assert_eq!((offsets.vis, offsets.fn_token, offsets.generics), (0..0, 0..2, 4..4));
+        assert!(offsets.ret_decl.is_empty() && offsets.where_clause.is_empty());
+    }
+
+    #[test]
+    fn test_span_map() {
+        let ts: TokenStream = "#[get] pub fn user(id: u32) -> User { load(id) }".parse().unwrap();
+        let spans = SpanMap::new(&ts);
+        let (decl, offsets) = FunctionDecl::parse_with_field_offsets(spans.text()).unwrap();
+        assert_eq!(decl.fn_name, "user");
+        assert_eq!(&spans.text()[offsets.fn_decl], "(id : u32)");
+        assert_eq!(&spans.text()[offsets.ret_decl.clone()], "User");
+        // Outside a proc macro every span is the call site; this only checks
+        // the lookups stay in bounds.
+        let _ = spans.span(offsets.ret_decl.clone());
+        let _ = spans.span(1000..1001);
+        let _ = SpanMap::new(&TokenStream::new()).span(0..1);
+        let err = spans.error(offsets.fn_token, "this macro requires an async fn");
+        assert!(err.to_string().contains("this macro requires an async fn"));
+        let err = FunctionDecl::parse_with_field_offsets("struct A;").unwrap_err();
+        assert!(spans.parse_error(&err).to_string().contains("compile_error"));
+    }
+}
@@ -0,0 +1,181 @@
+use proc_macro2::TokenStream;
+
+use crate::{indent, Decl, FunctionDecl, ImplBlockDecl, ParseError, ParseErrorKind};
+
+/// A named place in a [`Splice`] where code can be inserted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SplicePoint {
+    /// Before the item, e.g. for a helper it uses.
+    Before,
+    /// After the existing attributes and doc comments, for more attributes.
+    AfterAttrs,
+    /// At the start of a function body, before the existing statements.
+    BeforeBody,
+    /// At the end of an impl block, after the existing items.
+    EndOfImpl,
+    /// After the item, e.g. for a companion item.
+    After,
+}
+
+#[derive(Clone, Debug)]
+enum Part {
+    Text(String),
+    Point(SplicePoint),
+}
+
+/// A rendered item with named insertion points, so that several edits can
+/// be added independently and rendered at once instead of assembled with
+/// `format!`.
+///
+/// Every inserted snippet must lex as Rust tokens with balanced delimiters,
+/// so the final render is as well-formed as the original item.
+#[derive(Clone, Debug)]
+pub struct Splice {
+    parts: Vec<Part>,
+    inserts: Vec<(SplicePoint, String)>,
+}
+
+impl Splice {
+    /// A function, with every point but [`SplicePoint::EndOfImpl`].
+    pub fn from_fn(decl: &FunctionDecl) -> Self {
+        let mut sig = decl.clone();
+        sig.func_prologue = String::new();
+        Splice::new(vec![
+            Part::Point(SplicePoint::Before),
+            Part::Text(decl.func_prologue.clone()),
+            Part::Point(SplicePoint::AfterAttrs),
+            Part::Text(format!("{}\n", sig.func_prelude())),
+            Part::Point(SplicePoint::BeforeBody),
+            Part::Text(format!("{}\n{}", decl.fn_body, decl.func_end())),
+            Part::Point(SplicePoint::After),
+        ])
+    }
+
+    /// An impl block, with every point but [`SplicePoint::BeforeBody`].
+    pub fn from_impl(block: &ImplBlockDecl) -> Self {
+        let mut unattributed = block.clone();
+        unattributed.prologue = String::new();
+        let rendered = unattributed.render();
+        let prologue = if block.prologue.is_empty() {
+            String::new()
+        } else {
+            format!("{}\n", block.prologue)
+        };
+        Splice::new(vec![
+            Part::Point(SplicePoint::Before),
+            Part::Text(prologue),
+            Part::Point(SplicePoint::AfterAttrs),
+            Part::Text(rendered.strip_suffix('}').unwrap_or(&rendered).trim_end().to_string()),
+            Part::Point(SplicePoint::EndOfImpl),
+            Part::Text("\n}".to_string()),
+            Part::Point(SplicePoint::After),
+        ])
+    }
+
+    fn new(parts: Vec<Part>) -> Self {
+        Splice {
+            parts,
+            inserts: Vec::new(),
+        }
+    }
+
+    pub fn has_point(&self, point: SplicePoint) -> bool {
+        self.parts.iter().any(|p| matches!(p, Part::Point(p) if *p == point))
+    }
+
+    /// Queue `code` for insertion at `point`, after anything already queued
+    /// there. Errors if this item has no such point or `code` does not lex.
+    pub fn insert(&mut self, point: SplicePoint, code: &str) -> Result<&mut Self, ParseError> {
+        if !self.has_point(point) {
+            return Err(ParseError::new(
+                ParseErrorKind::Invalid(format!("no `{:?}` insertion point", point)),
+                0,
+            ));
+        }
+        code.parse::<TokenStream>()
+            .map_err(|e| ParseError::new(ParseErrorKind::Invalid(e.to_string()), 0))?;
+        self.inserts.push((point, code.trim().to_string()));
+        Ok(self)
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => out.push_str(text),
+                Part::Point(point) => {
+                    for (_, code) in self.inserts.iter().filter(|(p, _)| p == point) {
+                        match point {
+                            SplicePoint::Before => out.push_str(&format!("{}\n\n", code)),
+                            SplicePoint::AfterAttrs | SplicePoint::BeforeBody => out.push_str(&format!("{}\n", code)),
+                            SplicePoint::EndOfImpl => out.push_str(&format!("\n\n{}", indent(code))),
+                            SplicePoint::After => out.push_str(&format!("\n\n{}", code)),
+                        }
+                    }
+                }
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{FunctionDecl, ImplBlockDecl, Splice, SplicePoint};
+
+    #[test]
+    fn test_splice_fn() {
+        let decl = FunctionDecl::from_string("/// Adds\npub fn add(a: u8, b: u8) -> u8 { a + b }".to_string());
+        let mut splice = Splice::from_fn(&decl);
+        splice
+            .insert(SplicePoint::BeforeBody, "trace!(\"add\");")
+            .unwrap()
+            .insert(SplicePoint::AfterAttrs, "#[inline]")
+            .unwrap()
+            .insert(SplicePoint::After, "fn helper() {}")
+            .unwrap();
+        let expected = "/// Adds
+#[inline]
+pub fn add(a: u8, b: u8) -> u8 {
+trace!(\"add\");
+a + b
+}
+
+fn helper() {}";
+        assert_eq!(splice.render(), expected);
+        assert!(splice.insert(SplicePoint::EndOfImpl, "fn x() {}").is_err());
+        assert!(splice.insert(SplicePoint::BeforeBody, "let s = (1;").is_err());
+        assert!(splice.insert(SplicePoint::BeforeBody, "\"open").is_err());
+        assert_eq!(splice.render(), expected);
+    }
+
+    #[test]
+    fn test_splice_impl() {
+        let block = ImplBlockDecl::from_string(
+            "#[automatically_derived] impl Counter { fn inc(&mut self) { self.0 += 1; } }".to_string(),
+        );
+        let mut splice = Splice::from_impl(&block);
+        splice
+            .insert(SplicePoint::EndOfImpl, "fn get(&self) -> u8 {\n    self.0\n}")
+            .unwrap()
+            .insert(SplicePoint::AfterAttrs, "#[allow(dead_code)]")
+            .unwrap()
+            .insert(SplicePoint::Before, "struct Counter(u8);")
+            .unwrap();
+        let expected = "struct Counter(u8);
+
+#[automatically_derived]
+#[allow(dead_code)]
+impl Counter {
+    fn inc(&mut self) {
+    self.0 += 1;
+    }
+
+    fn get(&self) -> u8 {
+        self.0
+    }
+}";
+        assert_eq!(splice.render(), expected);
+        assert!(splice.insert(SplicePoint::BeforeBody, "a();").is_err());
+    }
+}
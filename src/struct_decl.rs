@@ -0,0 +1,201 @@
+use crate::field::Fields;
+use crate::generics::{generic_args, split_type_header};
+use crate::scan::{find_close, find_keyword, split_leading_attrs, split_visibility, CodeChars};
+use crate::{sealed, Decl, ParseError, ParseErrorKind, Visibility};
+
+/// A parsed `struct` definition (named, tuple or unit).
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StructDecl {
+    pub prologue: String,
+    pub vis: Visibility,
+    pub name: String,
+    /// Generic parameters including the angle brackets, e.g. `<T: Clone>`.
+    pub generics: String,
+    /// The `where` clause, empty if there is none.
+    pub where_clause: String,
+    pub fields: Fields,
+}
+
+impl StructDecl {
+    /// Parse a struct definition, panicking if the input is not one.
+    pub fn from_string(in_str: String) -> Self {
+        Self::try_from_string(&in_str).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_from_string(in_str: &str) -> Result<Self, ParseError> {
+        let struct_idx = find_keyword(in_str, "struct")
+            .ok_or_else(|| ParseError::new(ParseErrorKind::MissingKeyword("struct"), 0))?;
+        let (prologue, head) = split_leading_attrs(&in_str[..struct_idx]);
+        let (vis, _) = split_visibility(head);
+
+        let after_struct = struct_idx + 6;
+        let mut angle_depth = 0i32;
+        let mut prev = ' ';
+        let mut body = None;
+        for (idx, c) in CodeChars::new(&in_str[after_struct..]) {
+            match c {
+                '<' => angle_depth += 1,
+                '>' if prev != '-' => angle_depth -= 1,
+                '{' | '(' | ';' if angle_depth == 0 => {
+                    body = Some((after_struct + idx, c));
+                    break;
+                }
+                _ => {}
+            }
+            prev = c;
+        }
+        let (body_idx, open) = body.ok_or_else(|| ParseError::new(ParseErrorKind::Expected(';'), after_struct))?;
+
+        let (fields, header_end, trailer) = if open == ';' {
+            (Fields::Unit, body_idx, "")
+        } else {
+            let body_end = find_close(in_str, body_idx)
+                .ok_or_else(|| ParseError::new(ParseErrorKind::Unbalanced(open), body_idx))?;
+            let fields = Fields::from_string(&in_str[body_idx..=body_end]);
+            // A tuple struct's where clause follows its fields: `struct A<T>(T) where T: X;`
+            let trailer = if open == '(' {
+                in_str[body_end + 1..].split(';').next().unwrap_or("").trim()
+            } else {
+                ""
+            };
+            (fields, body_idx, trailer)
+        };
+
+        let (name, generics, mut where_clause) = split_type_header(&in_str[after_struct..header_end])
+            .ok_or_else(|| ParseError::new(ParseErrorKind::Unbalanced('<'), after_struct))?;
+        if !trailer.is_empty() {
+            where_clause = trailer.to_string();
+        }
+
+        Ok(StructDecl {
+            prologue: prologue.to_string(),
+            vis: Visibility::from_string(vis),
+            name,
+            generics,
+            where_clause,
+            fields,
+        })
+    }
+
+    /// An expression constructing this struct from one expression per field,
+    /// in declaration order.
+    pub fn constructor(&self, values: &[String]) -> String {
+        self.constructor_at(&self.name, values)
+    }
+
+    /// Like [`StructDecl::constructor`] but naming the struct as `Self`.
+    pub fn self_constructor(&self, values: &[String]) -> String {
+        self.constructor_at("Self", values)
+    }
+
+    /// The `impl` header for this struct, e.g. `impl<T: Copy> Point<T> where T: Eq`.
+    pub fn impl_header(&self) -> String {
+        format!(
+            "impl{} {}{}{}",
+            self.generics,
+            self.name,
+            generic_args(&self.generics),
+            if self.where_clause.is_empty() {
+                String::new()
+            } else {
+                format!(" {}", self.where_clause)
+            }
+        )
+    }
+
+    fn constructor_at(&self, path: &str, values: &[String]) -> String {
+        match &self.fields {
+            Fields::Unit => path.to_string(),
+            Fields::Tuple(_) => format!("{}({})", path, values.join(", ")),
+            Fields::Named(fields) => format!(
+                "{} {{ {} }}",
+                path,
+                fields
+                    .iter()
+                    .zip(values)
+                    .map(|(f, v)| if f.name == *v { v.clone() } else { format!("{}: {}", f.name, v) })
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+impl std::str::FromStr for StructDecl {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, ParseError> {
+        Self::try_from_string(s)
+    }
+}
+
+impl sealed::Sealed for StructDecl {}
+
+impl Decl for StructDecl {
+    fn render(&self) -> String {
+        let where_clause = if self.where_clause.is_empty() {
+            String::new()
+        } else {
+            format!(" {}", self.where_clause)
+        };
+        let (body, trailer) = match &self.fields {
+            Fields::Unit => (format!("{};", where_clause), String::new()),
+            Fields::Tuple(_) => (self.fields.render(""), format!("{};", where_clause)),
+            Fields::Named(_) => (format!("{}{}", where_clause, self.fields.render("")), String::new()),
+        };
+        format!(
+            "{}{}{}struct {}{}{}{}",
+            self.prologue,
+            if self.prologue.is_empty() { "" } else { "\n" },
+            self.vis.prefix(),
+            self.name,
+            self.generics,
+            body,
+            trailer
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::field::Fields;
+    use crate::struct_decl::StructDecl;
+    use crate::{Decl, Visibility};
+
+    #[test]
+    fn test_struct_named() {
+        let decl: StructDecl = "#[derive(Clone)]\npub struct Point<T> where T: Copy { pub x: T, y: T }"
+            .parse()
+            .unwrap();
+        assert_eq!(decl.prologue, "#[derive(Clone)]");
+        assert_eq!(decl.vis, Visibility::Pub);
+        assert_eq!(decl.name, "Point");
+        assert_eq!(decl.generics, "<T>");
+        assert_eq!(decl.where_clause, "where T: Copy");
+        assert_eq!(decl.fields.len(), 2);
+        assert_eq!(
+            decl.render(),
+            "#[derive(Clone)]\npub struct Point<T> where T: Copy {\n    pub x: T,\n    y: T,\n}"
+        );
+        assert_eq!(
+            decl.constructor(&["x".to_string(), "0".to_string()]),
+            "Point { x, y: 0 }"
+        );
+    }
+
+    #[test]
+    fn test_struct_tuple_and_unit() {
+        let decl = StructDecl::from_string("struct Wrapper<T>(pub T, u8) where T: Clone;".to_string());
+        assert!(matches!(decl.fields, Fields::Tuple(_)));
+        assert_eq!(decl.where_clause, "where T: Clone");
+        assert_eq!(decl.render(), "struct Wrapper<T>(pub T, u8) where T: Clone;");
+        assert_eq!(decl.constructor(&["a".to_string(), "b".to_string()]), "Wrapper(a, b)");
+        assert_eq!(decl.self_constructor(&["a".to_string(), "b".to_string()]), "Self(a, b)");
+        assert_eq!(decl.impl_header(), "impl<T> Wrapper<T> where T: Clone");
+
+        let decl = StructDecl::from_string("pub(crate) struct Marker;".to_string());
+        assert_eq!(decl.fields, Fields::Unit);
+        assert_eq!(decl.render(), "pub(crate) struct Marker;");
+    }
+}
@@ -0,0 +1,134 @@
+use std::str::FromStr;
+
+use proc_macro2::{Group, Span, TokenStream, TokenTree};
+use quote::{quote_spanned, ToTokens};
+
+use crate::{FunctionDecl, ParseError, ParseErrorKind};
+
+/// Set `span` on every token in `stream`, recursing into groups.
+pub fn respan(stream: TokenStream, span: Span) -> TokenStream {
+    stream
+        .into_iter()
+        .map(|tt| match tt {
+            TokenTree::Group(g) => {
+                let mut group = Group::new(g.delimiter(), respan(g.stream(), span));
+                group.set_span(span);
+                TokenTree::Group(group)
+            }
+            mut other => {
+                other.set_span(span);
+                other
+            }
+        })
+        .collect()
+}
+
+/// Lex `code` into tokens that all carry `span`, so that compiler errors in
+/// generated code point at the user's item.
+pub fn parse_spanned(code: &str, span: Span) -> Result<TokenStream, ParseError> {
+    code.parse::<TokenStream>()
+        .map(|ts| respan(ts, span))
+        .map_err(|e| ParseError::new(ParseErrorKind::Invalid(e.to_string()), 0))
+}
+
+/// Parse any decl type from a token stream.
+pub fn parse_tokens<T: FromStr<Err = ParseError>>(ts: TokenStream) -> Result<T, ParseError> {
+    ts.to_string().parse()
+}
+
+/// Run a macro expansion, turning a parse failure into a `compile_error!`
+/// pointed at the first token of `input` instead of panicking.
+pub fn expand_or_error<F>(input: TokenStream, expand: F) -> TokenStream
+where
+    F: FnOnce(TokenStream) -> Result<TokenStream, ParseError>,
+{
+    let span = input.clone().into_iter().next().map(|tt| tt.span()).unwrap_or_else(Span::call_site);
+    expand(input).unwrap_or_else(|e| e.to_compile_error_at(span))
+}
+
+impl ParseError {
+    /// A `compile_error!` invocation reporting this error at the call site.
+    pub fn to_compile_error(&self) -> TokenStream {
+        self.to_compile_error_at(Span::call_site())
+    }
+
+    /// A `compile_error!` invocation reporting this error at `span`.
+    pub fn to_compile_error_at(&self, span: Span) -> TokenStream {
+        let msg = self.to_string();
+        quote_spanned!(span=> ::core::compile_error! { #msg })
+    }
+}
+
+impl ToTokens for FunctionDecl {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let span = self.span.unwrap_or_else(Span::call_site);
+        tokens.extend(
+            self.to_token_stream_spanned(span)
+                .unwrap_or_else(|e| panic!("Generated function does not lex: {}", e)),
+        );
+    }
+}
+
+impl From<FunctionDecl> for TokenStream {
+    fn from(decl: FunctionDecl) -> Self {
+        decl.into_token_stream()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proc_macro2::{Span, TokenStream};
+    use quote::quote;
+
+    use crate::tokens::{expand_or_error, parse_spanned, parse_tokens};
+    use crate::{FunctionDecl, StructDecl, Visibility};
+
+    #[test]
+    fn test_to_token_stream_spanned() {
+        let funcdecl = FunctionDecl::from_string("pub fn spanned(a: u8) -> u8 { (a + 1) }".to_string());
+        let ts = funcdecl.to_token_stream_spanned(Span::call_site()).unwrap();
+        assert_eq!(ts.to_string(), "pub fn spanned (a : u8) -> u8 { (a + 1) }");
+    }
+
+    #[test]
+    fn test_parse_spanned_lex_error() {
+        assert!(parse_spanned("fn broken() { \"unterminated }", Span::call_site()).is_err());
+    }
+
+    #[test]
+    fn test_from_token_stream_round_trip() {
+        let input = quote! {
+            #[some_macro]
+            pub async fn from_tokens(a: u8, b: &str) -> Result<u8, String> {
+                Ok(a)
+            }
+        };
+        let funcdecl = FunctionDecl::from_token_stream(input.clone()).unwrap();
+        assert_eq!(funcdecl.fn_name, "from_tokens");
+        assert_eq!(funcdecl.vis, Visibility::Pub);
+        assert_eq!(funcdecl.async_str, "async");
+        assert_eq!(funcdecl.params().len(), 2);
+        assert_eq!(funcdecl.ret_decl, "Result < u8 , String >");
+        assert!(funcdecl.span.is_some());
+
+        let output = quote! { #funcdecl };
+        assert_eq!(output.to_string(), input.to_string());
+        let ts: TokenStream = funcdecl.into();
+        assert_eq!(ts.to_string(), input.to_string());
+    }
+
+    #[test]
+    fn test_compile_error() {
+        let input = quote! { struct NotAFn; };
+        let output = expand_or_error(input, |item| {
+            let decl = FunctionDecl::from_token_stream(item)?;
+            Ok(decl.into())
+        });
+        assert_eq!(output.to_string(), ":: core :: compile_error ! { \"expected a `fn` declaration\" }");
+
+        let decl: StructDecl = parse_tokens(quote! { pub struct A(u8); }).unwrap();
+        assert_eq!(decl.name, "A");
+        let err = parse_tokens::<StructDecl>(quote! { enum B {} }).unwrap_err();
+        assert!(err.to_compile_error().to_string().contains("expected a `struct` declaration"));
+    }
+}
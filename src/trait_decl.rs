@@ -0,0 +1,362 @@
+use crate::generics::{generic_args, split_type_header};
+use crate::impl_decl::{ImplBlockDecl, ImplItem};
+use crate::scan::{find_close, find_keyword, find_top_level, split_items, split_leading_attrs, split_visibility};
+use crate::{indent, sealed, Decl, FunctionDecl, ParseError, ParseErrorKind, Visibility};
+
+/// A method declared in a trait, which may or may not have a default body.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TraitMethod {
+    /// The method signature; its `fn_body` is always empty, see `body`.
+    pub sig: FunctionDecl,
+    /// The default body, `None` for a required method ending in `;`.
+    pub body: Option<String>,
+}
+
+impl TraitMethod {
+    pub fn name(&self) -> &str {
+        &self.sig.fn_name
+    }
+
+    pub fn has_default(&self) -> bool {
+        self.body.is_some()
+    }
+
+    /// A full function with the given body, e.g. for a generated impl.
+    pub fn with_body(&self, body: &str) -> FunctionDecl {
+        let mut decl = self.sig.clone();
+        decl.fn_body = body.to_string();
+        decl
+    }
+
+    pub fn render(&self) -> String {
+        match &self.body {
+            Some(body) => format!("{}\n{}\n{}", self.sig.func_prelude(), body, self.sig.func_end()),
+            None => format!("{};", self.sig.func_signature()),
+        }
+    }
+}
+
+/// One item inside a trait definition.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[allow(clippy::large_enum_variant)]
+pub enum TraitItem {
+    Method(TraitMethod),
+    /// Any other item (associated const or type), kept verbatim.
+    Other(String),
+}
+
+impl TraitItem {
+    pub fn render(&self) -> String {
+        match self {
+            TraitItem::Method(m) => m.render(),
+            TraitItem::Other(s) => s.clone(),
+        }
+    }
+}
+
+/// A parsed `trait` definition.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TraitDecl {
+    pub prologue: String,
+    pub vis: Visibility,
+    /// `unsafe` if this is an unsafe trait, otherwise empty.
+    pub unsafe_str: String,
+    pub name: String,
+    /// Generic parameters including the angle brackets, e.g. `<T: Clone>`.
+    pub generics: String,
+    /// Supertrait bounds without the leading colon, e.g. `Clone + Send`.
+    pub supertraits: String,
+    /// The `where` clause, empty if there is none.
+    pub where_clause: String,
+    pub items: Vec<TraitItem>,
+}
+
+impl TraitDecl {
+    /// Parse a trait definition, panicking if the input is not one.
+    pub fn from_string(in_str: String) -> Self {
+        Self::try_from_string(&in_str).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_from_string(in_str: &str) -> Result<Self, ParseError> {
+        let trait_idx = find_keyword(in_str, "trait")
+            .ok_or_else(|| ParseError::new(ParseErrorKind::MissingKeyword("trait"), 0))?;
+        let (prologue, head) = split_leading_attrs(&in_str[..trait_idx]);
+        let (vis, qualifiers) = split_visibility(head);
+
+        let after_trait = trait_idx + 5;
+        let body_idx = after_trait
+            + find_top_level(&in_str[after_trait..], '{', true)
+                .ok_or_else(|| ParseError::new(ParseErrorKind::Expected('{'), after_trait))?;
+        let body_end = find_close(in_str, body_idx)
+            .ok_or_else(|| ParseError::new(ParseErrorKind::Unbalanced('{'), body_idx))?;
+
+        let (name, generics, rest) = split_type_header(&in_str[after_trait..body_idx])
+            .ok_or_else(|| ParseError::new(ParseErrorKind::Unbalanced('<'), after_trait))?;
+        let (supertraits, where_clause) = match find_keyword(&rest, "where") {
+            Some(idx) => (rest[..idx].trim(), rest[idx..].trim()),
+            None => (rest.trim(), ""),
+        };
+
+        let items = split_items(&in_str[body_idx + 1..body_end])
+            .into_iter()
+            .map(|item| {
+                let (_, rest) = split_leading_attrs(item);
+                match (find_keyword(rest, "fn"), FunctionDecl::parse_signature(item)) {
+                    (Some(_), Ok((sig, body))) => TraitItem::Method(TraitMethod { sig, body }),
+                    _ => TraitItem::Other(item.to_string()),
+                }
+            })
+            .collect();
+
+        Ok(TraitDecl {
+            prologue: prologue.to_string(),
+            vis: Visibility::from_string(vis),
+            unsafe_str: if qualifiers.trim() == "unsafe" {
+                "unsafe".to_string()
+            } else {
+                String::new()
+            },
+            name,
+            generics,
+            supertraits: supertraits.trim_start_matches(':').trim().to_string(),
+            where_clause: where_clause.to_string(),
+            items,
+        })
+    }
+
+    pub fn methods(&self) -> impl Iterator<Item = &TraitMethod> {
+        self.items.iter().filter_map(|i| match i {
+            TraitItem::Method(m) => Some(m),
+            TraitItem::Other(_) => None,
+        })
+    }
+
+    pub fn method(&self, name: &str) -> Option<&TraitMethod> {
+        self.methods().find(|m| m.name() == name)
+    }
+
+    /// Methods without a default body, which every implementation must provide.
+    pub fn required_methods(&self) -> impl Iterator<Item = &TraitMethod> {
+        self.methods().filter(|m| !m.has_default())
+    }
+
+    /// Body template for [`TraitDecl::implement_for`] that panics naming the method.
+    pub const PANIC_STUB: &'static str = "unimplemented!(\"{name}\")";
+
+    /// Body template for [`TraitDecl::implement_for`] that records the call
+    /// in a `calls: RefCell<Vec<&'static str>>` field and returns the
+    /// default value. Associated functions without a receiver get
+    /// [`TraitDecl::PANIC_STUB`] instead.
+    pub const RECORD_STUB: &'static str = "self.calls.borrow_mut().push(\"{name}\");\nDefault::default()";
+
+    /// An impl of this trait for `type_name`, e.g. a mock, in which every
+    /// method has the body `body_template` with `{name}` replaced by the
+    /// method name, `{ret}` by its return type (`()` if none) and `{args}` by
+    /// its forwarded arguments. Associated types are set to `()` and
+    /// associated consts without a default to `unimplemented!()`. The impl
+    /// is `unsafe` if the trait is.
+    pub fn implement_for(&self, type_name: &str, body_template: &str) -> ImplBlockDecl {
+        let items = self
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                TraitItem::Method(m) => {
+                    let mut method = m.sig.clone();
+                    method.set_params(&m.sig.forwardable_params());
+                    let ret = if method.ret_decl.is_empty() { "()" } else { method.ret_decl.as_str() };
+                    let template = if body_template == Self::RECORD_STUB && m.sig.receiver().is_none() {
+                        Self::PANIC_STUB
+                    } else {
+                        body_template
+                    };
+                    method.fn_body = template
+                        .replace("{name}", m.name())
+                        .replace("{ret}", ret)
+                        .replace("{args}", &method.forward_args().unwrap_or_default());
+                    Some(ImplItem::Fn(method))
+                }
+                TraitItem::Other(other) => stub_assoc_item(other).map(ImplItem::Other),
+            })
+            .collect();
+        ImplBlockDecl {
+            prologue: if self.unsafe_str.is_empty() {
+                "#[allow(unused_variables)]".to_string()
+            } else {
+                "#[allow(unused_variables)] unsafe".to_string()
+            },
+            generics: self.generics.clone(),
+            trait_name: format!("{}{}", self.name, generic_args(&self.generics)),
+            self_ty: type_name.to_string(),
+            where_clause: self.where_clause.clone(),
+            items,
+        }
+    }
+}
+
+/// The impl counterpart of an associated type or const declared in a trait,
+/// `None` for a const with a default or anything else.
+fn stub_assoc_item(item: &str) -> Option<String> {
+    let (_, decl) = split_leading_attrs(item);
+    let decl = decl.trim().trim_end_matches(';').trim_end();
+    let name_end = |s: &str| s.find(|c: char| !(c == '_' || c.is_alphanumeric())).unwrap_or(s.len());
+    if let Some(rest) = decl.strip_prefix("type ") {
+        let rest = rest.trim_start();
+        Some(format!("type {} = ();", &rest[..name_end(rest)]))
+    } else if decl.starts_with("const ") && find_top_level(decl, '=', true).is_none() {
+        Some(format!("{} = unimplemented!();", decl))
+    } else {
+        None
+    }
+}
+
+impl std::str::FromStr for TraitDecl {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, ParseError> {
+        Self::try_from_string(s)
+    }
+}
+
+impl sealed::Sealed for TraitDecl {}
+
+impl Decl for TraitDecl {
+    fn render(&self) -> String {
+        format!(
+            "{}{}{}{}trait {}{}{}{} {{\n{}\n}}",
+            self.prologue,
+            if self.prologue.is_empty() { "" } else { "\n" },
+            self.vis.prefix(),
+            if self.unsafe_str.is_empty() { "" } else { "unsafe " },
+            self.name,
+            self.generics,
+            if self.supertraits.is_empty() {
+                String::new()
+            } else {
+                format!(": {}", self.supertraits)
+            },
+            if self.where_clause.is_empty() {
+                String::new()
+            } else {
+                format!(" {}", self.where_clause)
+            },
+            self.items
+                .iter()
+                .map(|i| indent(&i.render()))
+                .collect::<Vec<String>>()
+                .join("\n\n")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trait_decl::{TraitDecl, TraitItem};
+    use crate::{Decl, Visibility};
+
+    const INPUT: &str = "/// A store
+    pub unsafe trait Store<K>: Send + Sync where K: Eq {
+        type Value;
+        const NAME: &'static str = \"store\";
+
+        /// Fetch a value
+        fn get(&self, key: &K) -> Option<Self::Value>;
+
+        fn contains(&self, key: &K) -> bool {
+            self.get(key).is_some()
+        }
+
+        async fn flush(&mut self) -> Result<(), String>;
+    }";
+
+    #[test]
+    fn test_trait_parse() {
+        let decl: TraitDecl = INPUT.parse().unwrap();
+        assert_eq!(decl.prologue, "/// A store");
+        assert_eq!(decl.vis, Visibility::Pub);
+        assert_eq!(decl.unsafe_str, "unsafe");
+        assert_eq!(decl.name, "Store");
+        assert_eq!(decl.generics, "<K>");
+        assert_eq!(decl.supertraits, "Send + Sync");
+        assert_eq!(decl.where_clause, "where K: Eq");
+        assert_eq!(decl.items.len(), 5);
+        assert!(matches!(&decl.items[0], TraitItem::Other(s) if s == "type Value;"));
+
+        let get = decl.method("get").unwrap();
+        assert_eq!(get.sig.func_prologue, "/// Fetch a value\n");
+        assert_eq!(get.sig.ret_decl, "Option<Self::Value>");
+        assert!(get.body.is_none());
+        let contains = decl.method("contains").unwrap();
+        assert_eq!(contains.body.as_deref(), Some("self.get(key).is_some()"));
+        assert_eq!(decl.method("flush").unwrap().sig.async_str, "async");
+        assert_eq!(
+            decl.required_methods().map(|m| m.name()).collect::<Vec<&str>>(),
+            vec!["get", "flush"]
+        );
+    }
+
+    #[test]
+    fn test_trait_render() {
+        let decl = TraitDecl::from_string("trait Greeter: Clone { fn name(&self) -> String; fn greet(&self) -> String { format!(\"hi {}\", self.name()) } }".to_string());
+        let expected = "trait Greeter: Clone {
+    fn name(&self) -> String;
+
+    fn greet(&self) -> String {
+    format!(\"hi {}\", self.name())
+    }
+}";
+        assert_eq!(decl.render(), expected);
+        assert_eq!(
+            decl.method("name").unwrap().with_body("\"x\".into()").fn_body,
+            "\"x\".into()"
+        );
+    }
+
+    #[test]
+    fn test_implement_for() {
+        let decl: TraitDecl = INPUT.parse().unwrap();
+        let mock = decl.implement_for("MockStore", TraitDecl::RECORD_STUB);
+        let expected = "#[allow(unused_variables)] unsafe impl<K> Store<K> for MockStore where K: Eq {
+    type Value = ();
+
+    /// Fetch a value
+    fn get(&self, key: &K) -> Option<Self::Value> {
+    self.calls.borrow_mut().push(\"get\");
+    Default::default()
+    }
+
+    fn contains(&self, key: &K) -> bool {
+    self.calls.borrow_mut().push(\"contains\");
+    Default::default()
+    }
+
+    async fn flush(&mut self) -> Result<(), String> {
+    self.calls.borrow_mut().push(\"flush\");
+    Default::default()
+    }
+}";
+        assert_eq!(mock.render(), expected);
+
+        let decl = TraitDecl::from_string(
+            "trait Sized2 { const N: usize; fn size(&self, (a, b): (u8, u8)) -> usize { (a + b).into() } }".to_string(),
+        );
+        let stub = decl.implement_for("S", "log::<{ret}>(\"{name}\", ({args}))");
+        assert_eq!(stub.items[0].render(), "const N: usize = unimplemented!();");
+        assert_eq!(stub.method("size").unwrap().fn_body, "log::<usize>(\"size\", (__arg1))");
+        assert_eq!(
+            decl.implement_for("S", TraitDecl::PANIC_STUB).method("size").unwrap().fn_body,
+            "unimplemented!(\"size\")"
+        );
+
+        let decl = TraitDecl::from_string("trait Make { fn new() -> Self; fn id(&self) -> u8; }".to_string());
+        let mock = decl.implement_for("M", TraitDecl::RECORD_STUB);
+        assert!(mock.render().starts_with("#[allow(unused_variables)] impl Make for M {"));
+        assert_eq!(mock.method("new").unwrap().fn_body, "unimplemented!(\"new\")");
+        assert_eq!(
+            mock.method("id").unwrap().fn_body,
+            "self.calls.borrow_mut().push(\"id\");\nDefault::default()"
+        );
+    }
+}
@@ -0,0 +1,82 @@
+use crate::const_decl::split_item;
+use crate::generics::split_type_header;
+use crate::scan::find_top_level;
+use crate::{sealed, Decl, ParseError, ParseErrorKind, Visibility};
+
+/// A parsed `type` alias, e.g. `pub type Result<T> = std::result::Result<T, Error>;`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TypeAliasDecl {
+    pub prologue: String,
+    pub vis: Visibility,
+    pub name: String,
+    /// Generic parameters including the angle brackets, e.g. `<T>`.
+    pub generics: String,
+    /// The aliased type, empty for a declaration without one (in a trait).
+    pub ty: String,
+}
+
+impl TypeAliasDecl {
+    /// Parse a type alias, panicking if the input is not one.
+    pub fn from_string(in_str: String) -> Self {
+        Self::try_from_string(&in_str).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_from_string(in_str: &str) -> Result<Self, ParseError> {
+        let (prologue, vis, rest) = split_item(in_str, "type")?;
+        let rest = rest.trim_end().trim_end_matches(';').trim_end();
+        let (header, ty) = match find_top_level(rest, '=', true) {
+            Some(idx) => (&rest[..idx], rest[idx + 1..].trim()),
+            None => (rest, ""),
+        };
+        let offset = in_str.len() - rest.len();
+        let (name, generics, _) =
+            split_type_header(header).ok_or_else(|| ParseError::new(ParseErrorKind::Unbalanced('<'), offset))?;
+        Ok(TypeAliasDecl {
+            prologue,
+            vis,
+            name,
+            generics,
+            ty: ty.to_string(),
+        })
+    }
+}
+
+impl std::str::FromStr for TypeAliasDecl {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, ParseError> {
+        Self::try_from_string(s)
+    }
+}
+
+impl sealed::Sealed for TypeAliasDecl {}
+
+impl Decl for TypeAliasDecl {
+    fn render(&self) -> String {
+        format!(
+            "{}{}{}type {}{}{};",
+            self.prologue,
+            if self.prologue.is_empty() { "" } else { "\n" },
+            self.vis.prefix(),
+            self.name,
+            self.generics,
+            if self.ty.is_empty() { String::new() } else { format!(" = {}", self.ty) }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Decl, TypeAliasDecl, Visibility};
+
+    #[test]
+    fn test_type_alias() {
+        let decl: TypeAliasDecl = "pub type Result<T, E = Error> = std::result::Result<T, E>;".parse().unwrap();
+        assert_eq!(decl.vis, Visibility::Pub);
+        assert_eq!((decl.name.as_str(), decl.generics.as_str()), ("Result", "<T, E = Error>"));
+        assert_eq!(decl.ty, "std::result::Result<T, E>");
+        assert_eq!(decl.render(), "pub type Result<T, E = Error> = std::result::Result<T, E>;");
+        assert_eq!(TypeAliasDecl::from_string("type Item;".to_string()).ty, "");
+    }
+}
@@ -0,0 +1,300 @@
+use crate::scan::split_top_level;
+
+/// A coarse classification of a type as written in source.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TypeKind {
+    Bool,
+    Char,
+    Integer,
+    Float,
+    /// `String`, `&str` or `str`.
+    String,
+    /// `Option<T>`, holding `T`.
+    Option(String),
+    /// `Vec<T>`, holding `T`.
+    Vec(String),
+    /// `Box<T>`, holding `T`.
+    Box(String),
+    /// `Result<T, E>`, holding `T` and `E` (`E` is empty for an alias such
+    /// as `io::Result<T>`).
+    Result(String, String),
+    /// `&T` or `&mut T`, holding `T`.
+    Reference(String),
+    /// `(A, B, ...)`, holding each element type; `()` has none.
+    Tuple(Vec<String>),
+    /// `[T; N]`, holding `T` and `N`.
+    Array(String, String),
+    /// Any other path type.
+    Other,
+}
+
+impl TypeKind {
+    pub fn classify(ty: &str) -> Self {
+        let ty = ty.trim();
+        if let Some(inner) = ty.strip_prefix('&') {
+            let inner = inner.trim_start();
+            // Drop a lifetime, e.g. `&'a str`.
+            let inner = match inner.strip_prefix('\'') {
+                Some(lt) => lt.trim_start_matches(|c: char| c == '_' || c.is_alphanumeric()).trim_start(),
+                None => inner,
+            };
+            let inner = inner.strip_prefix("mut ").unwrap_or(inner).trim();
+            return if inner == "str" {
+                TypeKind::String
+            } else {
+                TypeKind::Reference(inner.to_string())
+            };
+        }
+        if let Some(inner) = ty.strip_prefix('(').and_then(|t| t.strip_suffix(')')) {
+            return TypeKind::Tuple(
+                split_top_level(inner, ',', true)
+                    .into_iter()
+                    .map(|t| t.trim().to_string())
+                    .filter(|t| !t.is_empty())
+                    .collect(),
+            );
+        }
+        if let Some(inner) = ty.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+            let mut parts = split_top_level(inner, ';', true).into_iter();
+            let elem = parts.next().unwrap_or("").trim().to_string();
+            return match parts.next() {
+                Some(len) => TypeKind::Array(elem, len.trim().to_string()),
+                None => TypeKind::Other,
+            };
+        }
+        let (path, arg) = match ty.find('<') {
+            Some(idx) if ty.ends_with('>') => (ty[..idx].trim(), Some(ty[idx + 1..ty.len() - 1].trim())),
+            _ => (ty, None),
+        };
+        let last = path.rsplit("::").next().unwrap_or(path);
+        match (last, arg) {
+            ("bool", None) => TypeKind::Bool,
+            ("char", None) => TypeKind::Char,
+            ("i8", None) | ("i16", None) | ("i32", None) | ("i64", None) | ("i128", None) | ("isize", None)
+            | ("u8", None) | ("u16", None) | ("u32", None) | ("u64", None) | ("u128", None) | ("usize", None) => {
+                TypeKind::Integer
+            }
+            ("f32", None) | ("f64", None) => TypeKind::Float,
+            ("String", None) | ("str", None) => TypeKind::String,
+            ("Option", Some(inner)) => TypeKind::Option(inner.to_string()),
+            ("Vec", Some(inner)) => TypeKind::Vec(inner.to_string()),
+            ("Box", Some(inner)) => TypeKind::Box(inner.to_string()),
+            ("Result", Some(inner)) => {
+                let mut args = split_top_level(inner, ',', true).into_iter();
+                let ok = args.next().unwrap_or("").trim().to_string();
+                let err = args.next().unwrap_or("").trim().to_string();
+                TypeKind::Result(ok, err)
+            }
+            _ => TypeKind::Other,
+        }
+    }
+
+    pub fn is_primitive(&self) -> bool {
+        matches!(
+            self,
+            TypeKind::Bool | TypeKind::Char | TypeKind::Integer | TypeKind::Float
+        )
+    }
+}
+
+/// What a function returns, as far as wrapping code cares.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ReturnType {
+    /// No return type, or `()`.
+    Unit,
+    /// `!`
+    Never,
+    /// `Result<T, E>`; `err` is empty for an alias such as `io::Result<T>`.
+    Result { ok: String, err: String },
+    /// `Option<T>`, holding `T`.
+    Option(String),
+    /// `impl Future<Output = T>` (possibly with further bounds), holding `T`.
+    ImplFuture(String),
+    /// Any other `impl Trait`, holding the bounds.
+    ImplTrait(String),
+    /// Any other type, as written.
+    Other(String),
+}
+
+impl ReturnType {
+    /// Classify a return type as stored in `ret_decl` (without the `->`).
+    pub fn from_string(ret: &str) -> Self {
+        let ret = ret.trim().trim_start_matches("->").trim();
+        if ret.is_empty() || ret.split_whitespace().collect::<String>() == "()" {
+            return ReturnType::Unit;
+        }
+        if ret == "!" {
+            return ReturnType::Never;
+        }
+        if let Some(bounds) = ret.strip_prefix("impl ") {
+            return match future_output(bounds) {
+                Some(output) => ReturnType::ImplFuture(output),
+                None => ReturnType::ImplTrait(bounds.trim().to_string()),
+            };
+        }
+        match TypeKind::classify(ret) {
+            TypeKind::Result(ok, err) => ReturnType::Result { ok, err },
+            TypeKind::Option(inner) => ReturnType::Option(inner),
+            _ => ReturnType::Other(ret.to_string()),
+        }
+    }
+
+    pub fn is_unit(&self) -> bool {
+        *self == ReturnType::Unit
+    }
+
+    pub fn is_result(&self) -> bool {
+        matches!(self, ReturnType::Result { .. })
+    }
+
+    pub fn is_option(&self) -> bool {
+        matches!(self, ReturnType::Option(_))
+    }
+
+    pub fn is_future(&self) -> bool {
+        matches!(self, ReturnType::ImplFuture(_))
+    }
+
+    /// The `T` of `Result<T, E>`.
+    pub fn ok_type(&self) -> Option<&str> {
+        match self {
+            ReturnType::Result { ok, .. } => Some(ok),
+            _ => None,
+        }
+    }
+
+    /// The `E` of `Result<T, E>`; `None` for an alias without it.
+    pub fn err_type(&self) -> Option<&str> {
+        match self {
+            ReturnType::Result { err, .. } if !err.is_empty() => Some(err),
+            _ => None,
+        }
+    }
+
+    /// The `T` of `Option<T>`.
+    pub fn some_type(&self) -> Option<&str> {
+        match self {
+            ReturnType::Option(inner) => Some(inner),
+            _ => None,
+        }
+    }
+
+    /// The type produced once any `impl Future` is awaited.
+    pub fn awaited(&self) -> ReturnType {
+        match self {
+            ReturnType::ImplFuture(output) => ReturnType::from_string(output),
+            other => other.clone(),
+        }
+    }
+}
+
+/// The lifetimes named in a type, in order of first appearance and including
+/// `'_` and `'static`: `&'a Cow<'b, str>` gives `["'a", "'b"]`.
+pub fn lifetimes_in(ty: &str) -> Vec<String> {
+    let mut found: Vec<String> = Vec::new();
+    for (idx, _) in ty.match_indices('\'') {
+        let rest = &ty[idx + 1..];
+        let len = rest.find(|c: char| !(c == '_' || c.is_alphanumeric())).unwrap_or(rest.len());
+        let lifetime = &ty[idx..idx + 1 + len];
+        if len > 0 && !found.iter().any(|l| l == lifetime) {
+            found.push(lifetime.to_string());
+        }
+    }
+    found
+}
+
+/// Whether a type leaves a lifetime for the compiler to infer: a reference
+/// without a lifetime (`&T`, `&mut T`) or an explicit `'_`.
+pub fn has_elided_lifetime(ty: &str) -> bool {
+    ty.match_indices('&').any(|(idx, _)| !ty[idx + 1..].trim_start().starts_with('\''))
+        || lifetimes_in(ty).iter().any(|l| l == "'_")
+}
+
+/// The `T` of an `impl Future<Output = T> + ...` bound list.
+fn future_output(bounds: &str) -> Option<String> {
+    split_top_level(bounds, '+', true).into_iter().find_map(|bound| {
+        let bound = bound.trim();
+        let open = bound.find('<')?;
+        let path = bound[..open].trim();
+        if path.rsplit("::").next() != Some("Future") || !bound.ends_with('>') {
+            return None;
+        }
+        split_top_level(&bound[open + 1..bound.len() - 1], ',', true)
+            .into_iter()
+            .find_map(|arg| {
+                let (name, ty) = arg.split_once('=')?;
+                if name.trim() == "Output" {
+                    Some(ty.trim().to_string())
+                } else {
+                    None
+                }
+            })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{has_elided_lifetime, lifetimes_in, ReturnType, TypeKind};
+    use crate::FunctionDecl;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(TypeKind::classify("bool"), TypeKind::Bool);
+        assert_eq!(TypeKind::classify("u64"), TypeKind::Integer);
+        assert_eq!(TypeKind::classify("f32"), TypeKind::Float);
+        assert_eq!(TypeKind::classify("std::string::String"), TypeKind::String);
+        assert_eq!(TypeKind::classify("&'a str"), TypeKind::String);
+        assert_eq!(TypeKind::classify("Option<Vec<u8>>"), TypeKind::Option("Vec<u8>".to_string()));
+        assert_eq!(TypeKind::classify("Vec<u8>"), TypeKind::Vec("u8".to_string()));
+        assert_eq!(
+            TypeKind::classify("Result<Vec<u8>, Error>"),
+            TypeKind::Result("Vec<u8>".to_string(), "Error".to_string())
+        );
+        assert_eq!(
+            TypeKind::classify("io::Result<()>"),
+            TypeKind::Result("()".to_string(), String::new())
+        );
+        assert_eq!(TypeKind::classify("&mut Foo"), TypeKind::Reference("Foo".to_string()));
+        assert_eq!(TypeKind::classify("()"), TypeKind::Tuple(vec![]));
+        assert_eq!(
+            TypeKind::classify("(u8, String)"),
+            TypeKind::Tuple(vec!["u8".to_string(), "String".to_string()])
+        );
+        assert_eq!(TypeKind::classify("[u8; 4]"), TypeKind::Array("u8".to_string(), "4".to_string()));
+        assert_eq!(TypeKind::classify("HashMap<u8, u8>"), TypeKind::Other);
+        assert!(TypeKind::classify("char").is_primitive());
+    }
+
+    #[test]
+    fn test_return_type() {
+        let ret = |s: &str| FunctionDecl::from_string(format!("fn f(){} {{}}", s)).return_type();
+        assert!(ret("").is_unit());
+        assert!(ret("-> ()").is_unit());
+        assert_eq!(ret("-> !"), ReturnType::Never);
+        let result = ret("-> Result<Vec<u8>, io::Error>");
+        assert!(result.is_result());
+        assert_eq!(result.ok_type(), Some("Vec<u8>"));
+        assert_eq!(result.err_type(), Some("io::Error"));
+        assert_eq!(ret("-> io::Result<()>").err_type(), None);
+        assert_eq!(ret("-> Option<&'static str>").some_type(), Some("&'static str"));
+
+        let future = ret("-> impl std::future::Future<Output = Result<u8, E>> + Send + 'static");
+        assert_eq!(future, ReturnType::ImplFuture("Result<u8, E>".to_string()));
+        assert_eq!(future.awaited().ok_type(), Some("u8"));
+        assert_eq!(ret("-> impl Iterator<Item = u8>"), ReturnType::ImplTrait("Iterator<Item = u8>".to_string()));
+        assert_eq!(ret("-> HashMap<u8, u8>"), ReturnType::Other("HashMap<u8, u8>".to_string()));
+    }
+
+    #[test]
+    fn test_lifetimes_in() {
+        assert_eq!(lifetimes_in("&'a Cow<'b, str>"), vec!["'a", "'b"]);
+        assert_eq!(lifetimes_in("impl Iterator<Item = &'a u8> + 'a"), vec!["'a"]);
+        assert!(lifetimes_in("Vec<u8>").is_empty());
+        assert!(has_elided_lifetime("&mut Formatter<'_>"));
+        assert!(has_elided_lifetime("Option<&&'a str>"));
+        assert!(!has_elided_lifetime("&'static str"));
+        assert!(!has_elided_lifetime("Box<dyn Fn() + 'a>"));
+    }
+}
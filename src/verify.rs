@@ -0,0 +1,87 @@
+use proc_macro2::TokenStream;
+
+use crate::scan::{split_items, CodeChars};
+use crate::{Item, ItemKind, ParseError, ParseErrorKind, ParseOptions};
+
+/// Check that generated code is well-formed before handing it to the
+/// compiler: brackets must balance, the text must lex as Rust tokens, and
+/// every top-level item this crate knows how to parse must parse. Other
+/// items, statements and expressions are only lexed.
+///
+/// Offsets in the error are into `code`, so a malformed expansion is
+/// reported where it was produced rather than as an "expected token" error
+/// from rustc.
+pub fn verify(code: &str) -> Result<(), ParseError> {
+    check_brackets(code)?;
+    code.parse::<TokenStream>()
+        .map_err(|e| ParseError::new(ParseErrorKind::Invalid(format!("generated code does not lex: {}", e)), 0))?;
+    for item in split_items(code) {
+        let offset = item.as_ptr() as usize - code.as_ptr() as usize;
+        let kind = ItemKind::sniff(item);
+        if kind == ItemKind::Mod && !item.contains('{') {
+            continue;
+        }
+        if let Some(parsed) = Item::parse_as(kind, item, &ParseOptions::default()) {
+            parsed.map_err(|e| ParseError::new(e.kind, offset + e.offset))?;
+        }
+    }
+    Ok(())
+}
+
+/// [`verify`] `code` in debug builds, panicking with the error and the code
+/// if it fails; release builds return `code` unchecked.
+#[cfg(any(test, feature = "unstable"))]
+pub(crate) fn checked(code: String) -> String {
+    if cfg!(debug_assertions) {
+        if let Err(e) = verify(&code) {
+            panic!("generated code is malformed: {}\n{}", e, code);
+        }
+    }
+    code
+}
+
+fn check_brackets(code: &str) -> Result<(), ParseError> {
+    let mut open: Vec<(usize, char)> = Vec::new();
+    for (idx, c) in CodeChars::new(code) {
+        let expected = match c {
+            '(' | '[' | '{' => {
+                open.push((idx, c));
+                continue;
+            }
+            ')' => '(',
+            ']' => '[',
+            '}' => '{',
+            _ => continue,
+        };
+        match open.pop() {
+            Some((_, o)) if o == expected => {}
+            Some((at, o)) => return Err(ParseError::new(ParseErrorKind::Unbalanced(o), at)),
+            None => return Err(ParseError::new(ParseErrorKind::Invalid(format!("unexpected `{}`", c)), idx)),
+        }
+    }
+    match open.pop() {
+        Some((at, o)) => Err(ParseError::new(ParseErrorKind::Unbalanced(o), at)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{verify, ParseErrorKind};
+
+    #[test]
+    fn test_verify() {
+        assert!(verify("struct A(u8);\n\nimpl A {\n    fn get(&self) -> u8 { self.0 }\n}").is_ok());
+        assert!(verify("let x = \"}\"; x.len()").is_ok());
+        assert!(verify("mod inner;\nuse std::fmt;").is_ok());
+
+        let err = verify("fn a() { (1 }").unwrap_err();
+        assert_eq!((err.kind, err.offset), (ParseErrorKind::Unbalanced('('), 9));
+        let err = verify("fn a() {}\n}").unwrap_err();
+        assert_eq!(err.to_string(), "unexpected `}` at offset 10");
+        assert!(verify("let s = 'ab';").is_err());
+
+        let err = verify("const A: u8 = 1;\nconst B = 2;").unwrap_err();
+        assert_eq!((err.kind, err.offset), (ParseErrorKind::Expected(':'), 23));
+    }
+}
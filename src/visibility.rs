@@ -0,0 +1,105 @@
+use std::fmt;
+
+/// The visibility of an item or field.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Visibility {
+    #[default]
+    Private,
+    /// `pub`
+    Pub,
+    /// `pub(crate)`
+    PubCrate,
+    /// `pub(super)`
+    PubSuper,
+    /// `pub(in path)`, holding the path; `pub(self)` is `PubIn("self")`.
+    PubIn(String),
+}
+
+impl Visibility {
+    /// Parse a visibility as written in source; anything unrecognised is `Private`.
+    pub fn from_string(in_str: &str) -> Self {
+        let in_str = in_str.trim();
+        let restriction = match in_str.strip_prefix("pub") {
+            Some(rest) => rest.trim(),
+            None => return Visibility::Private,
+        };
+        if restriction.is_empty() {
+            return Visibility::Pub;
+        }
+        let inner = restriction
+            .strip_prefix('(')
+            .and_then(|r| r.strip_suffix(')'))
+            .map(|r| r.trim())
+            .unwrap_or("");
+        match inner {
+            "crate" => Visibility::PubCrate,
+            "super" => Visibility::PubSuper,
+            "self" => Visibility::PubIn("self".to_string()),
+            _ => match inner.strip_prefix("in ") {
+                Some(path) => Visibility::PubIn(path.split_whitespace().collect::<Vec<&str>>().join("")),
+                None => Visibility::Private,
+            },
+        }
+    }
+
+    pub fn is_private(&self) -> bool {
+        *self == Visibility::Private
+    }
+
+    /// The visibility followed by a space, or nothing when private; for
+    /// prefixing an item.
+    pub fn prefix(&self) -> String {
+        if self.is_private() {
+            String::new()
+        } else {
+            format!("{} ", self)
+        }
+    }
+}
+
+impl fmt::Display for Visibility {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Visibility::Private => Ok(()),
+            Visibility::Pub => write!(f, "pub"),
+            Visibility::PubCrate => write!(f, "pub(crate)"),
+            Visibility::PubSuper => write!(f, "pub(super)"),
+            Visibility::PubIn(path) => write!(f, "pub(in {})", path),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::visibility::Visibility;
+    use crate::FunctionDecl;
+
+    #[test]
+    fn test_visibility_parse() {
+        assert_eq!(Visibility::from_string(""), Visibility::Private);
+        assert_eq!(Visibility::from_string("pub"), Visibility::Pub);
+        assert_eq!(Visibility::from_string("pub(crate)"), Visibility::PubCrate);
+        assert_eq!(Visibility::from_string("pub (super)"), Visibility::PubSuper);
+        assert_eq!(Visibility::from_string("pub(self)"), Visibility::PubIn("self".to_string()));
+        assert_eq!(
+            Visibility::from_string("pub(in crate :: foo)"),
+            Visibility::PubIn("crate::foo".to_string())
+        );
+        assert_eq!(Visibility::PubIn("crate::foo".to_string()).to_string(), "pub(in crate::foo)");
+    }
+
+    #[test]
+    fn test_fn_visibility_forms() {
+        let vis = |s: &str| FunctionDecl::from_string(format!("#[attr]\n{} fn f() {{}}", s));
+        assert_eq!(vis("").vis, Visibility::Private);
+        assert_eq!(vis("pub").vis, Visibility::Pub);
+        assert_eq!(vis("pub(crate)").vis, Visibility::PubCrate);
+        assert_eq!(vis("pub(super) async").vis, Visibility::PubSuper);
+        assert_eq!(vis("pub(super) async").async_str, "async");
+        let decl = vis("pub(in crate::foo)");
+        assert_eq!(decl.vis, Visibility::PubIn("crate::foo".to_string()));
+        assert_eq!(decl.func_prologue, "#[attr]\n");
+        assert_eq!(decl.func_prelude(), "#[attr]\npub(in crate::foo) fn f() {");
+    }
+}